@@ -0,0 +1,247 @@
+use crate::paths::Paths;
+use crate::run_a_star::{
+    new_a_star_solver_with_model, run_a_star_solver_with_observer, AStarOptions, LossCaches,
+};
+use crate::{try_get_heuristic_for_pile_with_kind, HeuristicKind};
+use handy_core::game::end_game::GameEndCheckType;
+use handy_core::solver::a_star::{AStarDoneReason, SolverBudget};
+use handy_core::utils::*;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// One line of a batch file in its JSON form. A line can also be just a pile string.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BatchEntry {
+    pub pile: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    // A game end check type, like --game-end-check-type takes
+    #[serde(default)]
+    pub objective: Option<String>,
+    #[serde(default)]
+    pub max_iters: Option<usize>,
+    #[serde(default)]
+    pub time_limit_secs: Option<f32>,
+    #[serde(default)]
+    pub max_memory_mb: Option<usize>,
+    // For regression tests, reported as a mismatch if the solver finds a different depth
+    #[serde(default)]
+    pub expected_depth: Option<usize>,
+}
+
+/// Limits for entries that don't set their own
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BatchDefaults {
+    pub objective: GameEndCheckType,
+    pub max_iters: Option<usize>,
+    pub time_limit_secs: Option<f32>,
+    pub max_memory_mb: Option<usize>,
+    pub heuristic: HeuristicKind,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BatchResult {
+    // 1 based line number in the batch file
+    pub line: usize,
+    pub id: Option<String>,
+    pub pile: String,
+    pub depth: Option<usize>,
+    // Ran out of states without dropping any
+    pub search_completed: bool,
+    // The search completed with a heuristic that never overestimates, which the trained models
+    // don't promise
+    pub proven_optimal: bool,
+    pub expected_depth: Option<usize>,
+    pub matches_expected: Option<bool>,
+    pub done_reason: Option<AStarDoneReason>,
+    pub iterations: usize,
+    pub elapsed_ms: f32,
+    // Set instead of the other results if the entry couldn't be solved at all
+    pub error: Option<String>,
+}
+
+/// Skips blank lines and # comments. Errors point at the line that couldn't be read.
+pub fn parse_batch_file(contents: &str) -> Result<Vec<(usize, BatchEntry)>, String> {
+    let mut entries = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = if line.starts_with('{') {
+            serde_json::from_str(line).map_err(|err| format!("Line {}: {err}", i + 1))?
+        } else {
+            BatchEntry {
+                pile: line.to_owned(),
+                ..BatchEntry::default()
+            }
+        };
+        entries.push((i + 1, entry));
+    }
+    Ok(entries)
+}
+
+fn parse_objective(
+    entry: &BatchEntry,
+    defaults: &BatchDefaults,
+) -> Result<GameEndCheckType, String> {
+    match &entry.objective {
+        Some(objective) => objective
+            .parse()
+            .map_err(|_| format!("Unknown objective {objective}")),
+        None => Ok(defaults.objective),
+    }
+}
+
+fn error_result(line: usize, entry: &BatchEntry, error: String) -> BatchResult {
+    BatchResult {
+        line,
+        id: entry.id.clone(),
+        pile: entry.pile.clone(),
+        depth: None,
+        search_completed: false,
+        proven_optimal: false,
+        expected_depth: entry.expected_depth,
+        matches_expected: None,
+        done_reason: None,
+        iterations: 0,
+        elapsed_ms: 0.0,
+        error: Some(error),
+    }
+}
+
+pub fn solve_batch_entry(
+    paths: &Paths,
+    loss_caches: &LossCaches,
+    line: usize,
+    entry: &BatchEntry,
+    defaults: &BatchDefaults,
+) -> BatchResult {
+    let setup = string_to_pile_result(&entry.pile).and_then(|pile| {
+        let objective = parse_objective(entry, defaults)?;
+        let model = try_get_heuristic_for_pile_with_kind(paths, &pile, defaults.heuristic)?;
+        Ok((pile, objective, model))
+    });
+    let (pile, objective, model) = match setup {
+        Ok(setup) => setup,
+        Err(err) => return error_result(line, entry, err),
+    };
+
+    let budget = SolverBudget {
+        max_memory_bytes: entry
+            .max_memory_mb
+            .or(defaults.max_memory_mb)
+            .map(|mb| mb << 20),
+        time_limit: entry
+            .time_limit_secs
+            .or(defaults.time_limit_secs)
+            .map(Duration::from_secs_f32),
+    };
+    let options = AStarOptions {
+        game_end_check_type: Some(objective),
+        budget,
+        loss_cache: loss_caches.get(paths, &pile, objective),
+        ..AStarOptions::default()
+    };
+    let mut solver = new_a_star_solver_with_model(pile.clone(), model, &options);
+
+    let start_time = Instant::now();
+    let result = run_a_star_solver_with_observer(
+        &mut solver,
+        entry.max_iters.or(defaults.max_iters),
+        &mut |_, _| {},
+    );
+
+    let depth = result.path.len().checked_sub(1);
+    let search_completed = !result.is_optimality_lost
+        && matches!(
+            result.done_reason,
+            AStarDoneReason::EmptyQueue | AStarDoneReason::FScoreCutoff
+        );
+    BatchResult {
+        line,
+        id: entry.id.clone(),
+        pile: pile_to_string(&pile),
+        depth,
+        search_completed,
+        proven_optimal: search_completed && solver.is_model_admissible,
+        expected_depth: entry.expected_depth,
+        matches_expected: entry.expected_depth.map(|expected| depth == Some(expected)),
+        done_reason: Some(result.done_reason),
+        iterations: solver.total_iters,
+        elapsed_ms: start_time.elapsed().as_secs_f32() * 1000.0,
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::test_paths;
+
+    fn defaults() -> BatchDefaults {
+        BatchDefaults {
+            objective: GameEndCheckType::Standard,
+            max_iters: Some(100_000),
+            time_limit_secs: None,
+            max_memory_mb: None,
+            heuristic: HeuristicKind::Default,
+        }
+    }
+
+    #[test]
+    fn test_parse_batch_file() {
+        let contents = r#"
+# Plain piles and JSON lines can be mixed
+1A 2A 6A 7A
+{"pile": "7A 6A 2A 1C", "id": "loss", "max_iters": 500, "expected_depth": 3}
+"#;
+        let entries = parse_batch_file(contents).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, 3);
+        assert_eq!(entries[0].1.pile, "1A 2A 6A 7A");
+        assert_eq!(entries[1].0, 4);
+        assert_eq!(entries[1].1.id.as_deref(), Some("loss"));
+        assert_eq!(entries[1].1.max_iters, Some(500));
+
+        let err = parse_batch_file("1A 2A\n{\"pile\": \"1A\", \"typo\": 1}").unwrap_err();
+        assert!(err.starts_with("Line 2:"), "{err}");
+    }
+
+    #[test]
+    fn test_solve_batch_entry() {
+        let paths = test_paths();
+        let loss_caches = LossCaches::default();
+        let entry = BatchEntry {
+            pile: "1A 2A 6A 7A".to_owned(),
+            expected_depth: Some(10),
+            ..BatchEntry::default()
+        };
+        let result = solve_batch_entry(&paths, &loss_caches, 1, &entry, &defaults());
+        assert_eq!(result.error, None);
+        assert_eq!(result.depth, Some(10));
+        assert!(result.search_completed);
+        // The trained models can overestimate
+        assert!(!result.proven_optimal);
+        assert_eq!(result.matches_expected, Some(true));
+
+        let lost = BatchEntry {
+            pile: "7A 6A 2A 1C".to_owned(),
+            ..BatchEntry::default()
+        };
+        let result = solve_batch_entry(&paths, &loss_caches, 2, &lost, &defaults());
+        assert_eq!(result.depth, None);
+        assert!(result.search_completed);
+        assert_eq!(result.done_reason, Some(AStarDoneReason::EmptyQueue));
+
+        let bad = BatchEntry {
+            pile: "1A 2A".to_owned(),
+            objective: Some("nonsense".to_owned()),
+            ..BatchEntry::default()
+        };
+        let result = solve_batch_entry(&paths, &loss_caches, 3, &bad, &defaults());
+        assert_eq!(result.error.as_deref(), Some("Unknown objective nonsense"));
+    }
+}
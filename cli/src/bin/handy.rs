@@ -0,0 +1,9 @@
+use clap::Parser;
+use cli::commands::{run, HandyArgs};
+
+fn main() {
+    if let Err(err) = run(HandyArgs::parse()) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,208 @@
+pub mod eval;
+pub mod generate;
+pub mod model;
+pub mod play;
+pub mod puzzle;
+pub mod render;
+pub mod search;
+pub mod solve;
+pub mod solve_batch;
+pub mod survey;
+pub mod train;
+
+use crate::paths::Paths;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use rand::{thread_rng, RngCore};
+use rand_pcg::Pcg64;
+use rand_seeder::Seeder;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Options every subcommand takes, before or after the subcommand name
+#[derive(Args, Debug, Clone, Default)]
+pub struct GlobalArgs {
+    /// Defaults to $HANDY_DATA_DIR, then the config's data_dir, then ./data
+    #[clap(long, global = true)]
+    pub data_dir: Option<PathBuf>,
+    /// Searched for models before the config's model_dirs and the data dir's models.
+    /// Defaults to $HANDY_MODEL_PATH
+    #[clap(long, global = true)]
+    pub models_dir: Vec<PathBuf>,
+    /// Yaml with data_dir and model_dirs. Defaults to $HANDY_CONFIG, or ./handy.yaml if there is one
+    #[clap(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Json prints one result on stdout. A* solves also write progress to stderr, as JSON lines.
+    #[clap(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// Makes start piles, sampling and training repeatable
+    #[clap(long, global = true)]
+    pub seed: Option<String>,
+    /// Defaults to one per core
+    #[clap(long, global = true)]
+    pub threads: Option<usize>,
+}
+
+impl GlobalArgs {
+    pub fn num_threads(&self) -> usize {
+        self.threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+            .max(1)
+    }
+
+    pub fn seed_or(&self, default: &str) -> String {
+        self.seed.clone().unwrap_or_else(|| default.to_owned())
+    }
+
+    /// Seeded when there's a seed, otherwise different every run
+    pub fn rng(&self) -> Box<dyn RngCore> {
+        match &self.seed {
+            Some(seed) => Box::new(Seeder::from(seed).make_rng::<Pcg64>()),
+            None => Box::new(thread_rng()),
+        }
+    }
+
+    /// Errors for commands that only print text so far
+    pub fn require_text_format(&self, command: &str) -> Result<(), String> {
+        match self.format {
+            OutputFormat::Text => Ok(()),
+            OutputFormat::Json => Err(format!("{command} doesn't support --format json yet")),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "handy",
+    about = "Solver, training and puzzle tools for Handy Brawl"
+)]
+pub struct HandyArgs {
+    #[clap(flatten)]
+    pub global: GlobalArgs,
+    #[clap(subcommand)]
+    pub command: HandyCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HandyCommand {
+    /// Find the shortest win from a pile
+    Solve(solve::SolveArgs),
+    /// Solve every pile in a file, in parallel, and summarize the results
+    SolveBatch(solve_batch::SolveBatchArgs),
+    /// Compare how hard each hero and monster matchup is
+    Survey(survey::SurveyArgs),
+    /// Play a game in the terminal
+    Play(play::PlayArgs),
+    /// Find every way to reach an objective in a number of turns
+    Search(search::SearchArgs),
+    /// Find and generate puzzles
+    #[clap(subcommand)]
+    Puzzle(puzzle::PuzzleCommand),
+    /// Write a solution or game as Markdown, HTML or SVG to share
+    Render(render::RenderArgs),
+    /// Generate training examples
+    #[clap(subcommand)]
+    Generate(generate::GenerateCommand),
+    /// Train models from the training examples
+    #[clap(subcommand)]
+    Train(train::TrainCommand),
+    /// Measure how good models are
+    #[clap(subcommand)]
+    Eval(eval::EvalCommand),
+    /// Inspect model files
+    #[clap(subcommand)]
+    Model(model::ModelCommand),
+    /// Print a completion script, e.g. handy completions bash > /etc/bash_completion.d/handy
+    Completions {
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
+}
+
+pub fn run(args: HandyArgs) -> Result<(), String> {
+    let global = args.global;
+    let paths = Paths::resolve(&global)?;
+
+    match args.command {
+        HandyCommand::Solve(args) => solve::run(&args, &global, &paths),
+        HandyCommand::SolveBatch(args) => solve_batch::run(&args, &global, &paths),
+        HandyCommand::Survey(args) => survey::run(&args, &global, &paths),
+        HandyCommand::Play(args) => play::run(&args, &global, &paths),
+        HandyCommand::Search(args) => search::run(&args, &global),
+        HandyCommand::Puzzle(command) => puzzle::run(&command, &global, &paths),
+        HandyCommand::Render(args) => render::run(&args, &global),
+        HandyCommand::Generate(command) => generate::run(&command, &global, &paths),
+        HandyCommand::Train(command) => train::run(&command, &global, &paths),
+        HandyCommand::Eval(command) => eval::run(&command, &global, &paths),
+        HandyCommand::Model(command) => model::run(&command, &global, &paths),
+        HandyCommand::Completions { shell } => {
+            clap_complete::generate(shell, &mut HandyArgs::command(), "handy", &mut io::stdout());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_tree() {
+        HandyArgs::command().debug_assert();
+    }
+
+    #[test]
+    fn test_global_args_anywhere() {
+        let args = HandyArgs::try_parse_from([
+            "handy",
+            "--seed",
+            "abc",
+            "solve",
+            "--classes",
+            "warrior",
+            "ogre",
+            "--threads",
+            "3",
+        ])
+        .unwrap();
+        assert_eq!(args.global.seed.as_deref(), Some("abc"));
+        assert_eq!(args.global.num_threads(), 3);
+        assert!(matches!(args.command, HandyCommand::Solve(_)));
+
+        let args =
+            HandyArgs::try_parse_from(["handy", "train", "linear", "--all", "--format", "json"])
+                .unwrap();
+        assert_eq!(args.global.format, OutputFormat::Json);
+        assert!(args.global.require_text_format("train").is_err());
+    }
+
+    #[test]
+    fn test_paths_from_flags() {
+        let args = HandyArgs::try_parse_from([
+            "handy",
+            "solve",
+            "--classes",
+            "warrior",
+            "ogre",
+            "--data-dir",
+            "/tmp/handy-data",
+            "--models-dir",
+            "/tmp/handy-models",
+        ])
+        .unwrap();
+        let paths = Paths::resolve(&args.global).unwrap();
+        assert_eq!(paths.data_dir, PathBuf::from("/tmp/handy-data"));
+        assert_eq!(paths.model_dirs[0], PathBuf::from("/tmp/handy-models"));
+        assert_eq!(
+            paths.model_dirs.last(),
+            Some(&PathBuf::from("/tmp/handy-data/models"))
+        );
+    }
+}
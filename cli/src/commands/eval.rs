@@ -0,0 +1,29 @@
+pub mod losses;
+pub mod rate;
+pub mod review;
+pub mod suite;
+
+use crate::commands::GlobalArgs;
+use crate::paths::Paths;
+use clap::Subcommand;
+
+#[derive(Subcommand, Debug)]
+pub enum EvalCommand {
+    /// Prediction and solver metrics for each matchup's model, written to data/evaluations
+    Suite(suite::EvaluateArgs),
+    /// Time how long a model takes to find a first solution, written to data/model_charts
+    Rate(rate::RateArgs),
+    /// Mean squared error of every matchup model on its training examples
+    Losses(losses::LossesArgs),
+    /// Grade each activation of a recorded game
+    Review(review::ReviewArgs),
+}
+
+pub fn run(command: &EvalCommand, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    match command {
+        EvalCommand::Suite(args) => suite::run(args, global, paths),
+        EvalCommand::Rate(args) => rate::run(args, global, paths),
+        EvalCommand::Losses(args) => losses::run(args, global, paths),
+        EvalCommand::Review(args) => review::run(args, global, paths),
+    }
+}
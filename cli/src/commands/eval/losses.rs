@@ -0,0 +1,62 @@
+use crate::commands::GlobalArgs;
+use crate::paths::Paths;
+use crate::read_examples_for_matchup;
+use crate::state_eval_to_score;
+use crate::try_read_model_for_matchup;
+use crate::DepthModeTrainingExample;
+use crate::StateEval;
+use clap::Args;
+use handy_core::game::{HEROS, MONSTERS};
+use handy_core::solver::Matchup;
+
+#[derive(Args, Debug)]
+pub struct LossesArgs {}
+
+pub fn run(_args: &LossesArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("eval losses")?;
+    let mut all_results: Vec<(Matchup, f32)> = Vec::new();
+    for hero in HEROS {
+        for monster in MONSTERS {
+            let matchup = (hero, monster);
+            let Ok(struct_model) = try_read_model_for_matchup(paths, matchup) else {
+                println!("Could not load model for: {matchup:?}");
+                all_results.push((matchup, 1000000.0));
+                continue;
+            };
+            let Ok(examples) = read_examples_for_matchup(paths, matchup) else {
+                continue;
+            };
+
+            // Loss examples are for the loss head, not the depth estimate
+            let Ok(win_examples) = examples
+                .filter(|ex| !matches!(ex, Ok(ex) if ex.eval == StateEval::Loss))
+                .collect::<Result<Vec<DepthModeTrainingExample>, _>>()
+            else {
+                continue;
+            };
+
+            let total_loss: f32 = win_examples
+                .iter()
+                .map(|ex| {
+                    let model_score = struct_model.score_pile(&ex.pile);
+                    let expected = state_eval_to_score(ex.eval) as f32;
+                    let diff = model_score - expected;
+                    (diff * diff) / win_examples.len() as f32
+                })
+                .sum();
+
+            println!("{matchup:?} {total_loss}");
+
+            all_results.push((matchup, total_loss));
+        }
+    }
+
+    all_results.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+    for (matchup, score) in all_results {
+        println!("{: <40} {score}", format!("{:?}", matchup));
+    }
+    Ok(())
+}
+
+// handy eval losses
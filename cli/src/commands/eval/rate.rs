@@ -0,0 +1,168 @@
+use crate::commands::GlobalArgs;
+use crate::*;
+use clap::Args;
+use csv::Writer;
+use handy_core::game::*;
+use handy_core::solver::a_star::*;
+use handy_core::solver::*;
+use handy_core::utils::*;
+use rand_pcg::Pcg64;
+use rand_seeder::Seeder;
+use serde;
+use std::fmt::Debug;
+use std::time::{Duration, SystemTime};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum ModelType {
+    Linear,
+    Mlp,
+    Semantic,
+    Max,
+    Blended,
+}
+
+#[derive(Args, Debug)]
+pub struct RateArgs {
+    #[clap(long, value_parser=parse_dot_separated_matchup)]
+    pub matchup: Matchup,
+
+    #[clap(short, long, default_value_t = 1.0)]
+    pub g_bias: f32,
+
+    #[clap(short, long, default_value_t = 10)]
+    pub num_trials: usize,
+
+    #[clap(short, long, default_value_t = 30)]
+    pub trial_s: usize,
+
+    #[clap(short, long, default_value = "default")]
+    pub prefix: String,
+
+    #[clap(long, value_enum, default_value_t = ModelType::Linear)]
+    pub model_type: ModelType,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct SolutionRow {
+    trial: usize,
+    duration_ms: f32,
+    iters: usize,
+    depth: DepthType,
+}
+
+type Solutions = Vec<SolutionRow>;
+
+fn rate_model(
+    start_pile: Pile,
+    model: Box<dyn ModelT>,
+    g_bias: f32,
+    trial_duration: Duration,
+    trial: usize,
+) -> Solutions {
+    let now = SystemTime::now();
+    let mut a_star_solver = AStarSolver::<TinyPile, TinyPileConverter>::new(&[start_pile], model);
+    a_star_solver.set_g_bias(g_bias);
+
+    let mut solutions: Solutions = Vec::new();
+    let mut iters: usize = 0;
+
+    loop {
+        let iter_result = a_star_solver.single_iter();
+        match iter_result {
+            AStarIterResult::Done(_) => {
+                // println!("Done early");
+                break;
+            }
+            AStarIterResult::NewBest(_) => {
+                iters += 1;
+                let duration = now.elapsed().unwrap();
+                // println!(
+                //     "{:?} {}: New best solution: {}",
+                //     duration, iters, a_star_solver.max_depth
+                // );
+                solutions.push(SolutionRow {
+                    depth: a_star_solver.max_depth,
+                    duration_ms: duration.as_millis() as f32,
+                    iters,
+                    trial,
+                });
+                break;
+            }
+            AStarIterResult::Continue(_) => {
+                iters += 1;
+                if now.elapsed().unwrap() > trial_duration {
+                    break;
+                }
+            }
+        }
+    }
+
+    solutions
+}
+
+pub fn run(args: &RateArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("eval rate")?;
+    println!("{:?}", args);
+
+    // Chart file names include the seed, so there always is one
+    let seed = global.seed_or("0");
+    let (hero, monster) = args.matchup;
+    let file_name = format!(
+        "{:?}.{:?}-{}-{}-{}-{}{}.csv",
+        hero,
+        monster,
+        args.prefix,
+        &seed,
+        args.trial_s,
+        args.g_bias,
+        // Linear model files keep their original names for render_charts.py
+        match args.model_type {
+            ModelType::Linear => "",
+            ModelType::Mlp => "-mlp",
+            ModelType::Semantic => "-semantic",
+            ModelType::Max => "-max",
+            ModelType::Blended => "-blended",
+        }
+    );
+    let output_path = paths.data_dir.join("model_charts").join(file_name);
+    println!("Writing to {}", output_path.display());
+    let mut writer = Writer::from_path(&output_path).map_err(|err| format!("{err}"))?;
+
+    let mlp_model = match args.model_type {
+        ModelType::Mlp => Some(try_read_mlp_model_for_matchup(paths, args.matchup)?),
+        _ => None,
+    };
+    let semantic_model = match args.model_type {
+        ModelType::Semantic => Some(try_read_semantic_model(paths)?),
+        _ => None,
+    };
+
+    let trial_duration = Duration::new(args.trial_s as u64, 0);
+    let mut rng = Seeder::from(&seed).make_rng::<Pcg64>();
+    for i in 0..args.num_trials {
+        let start_pile = get_start_from_classes(hero, monster, &mut rng);
+        let model: Box<dyn ModelT> = match args.model_type {
+            ModelType::Linear => Box::new(try_get_model_for_pile(paths, &start_pile)?),
+            ModelType::Mlp => Box::new(mlp_model.clone().unwrap()),
+            ModelType::Semantic => Box::new(semantic_model.clone().unwrap()),
+            ModelType::Max => {
+                try_get_heuristic_for_pile_with_kind(paths, &start_pile, HeuristicKind::Max)?
+            }
+            ModelType::Blended => {
+                try_get_heuristic_for_pile_with_kind(paths, &start_pile, HeuristicKind::Blended)?
+            }
+        };
+
+        println!("{}: {:?}", i, &start_pile);
+        let solutions = rate_model(start_pile, model, args.g_bias, trial_duration.clone(), i);
+
+        for row in solutions {
+            writer.serialize(row).map_err(|err| format!("{err}"))?;
+        }
+        // writer.flush().unwrap();
+    }
+    Ok(())
+}
+
+// handy eval rate --matchup warrior.ogre --seed abc
+// handy eval rate --matchup warrior.ogre --model-type blended -n 20
@@ -0,0 +1,32 @@
+use crate::commands::GlobalArgs;
+use crate::paths::Paths;
+use crate::{read_game_record, try_get_model_for_pile};
+use clap::Args;
+use handy_core::game::end_game::GameEndCheckType;
+use handy_core::solver::*;
+use std::path::Path;
+
+#[derive(Args, Debug)]
+pub struct ReviewArgs {
+    /// File with one pile per line: the start pile, followed by the pile after each activation
+    #[clap(long)]
+    pub record: String,
+
+    #[clap(long, default_value_t = DEFAULT_ANALYSIS_MAX_ITERS)]
+    pub iters: usize,
+
+    #[clap(long, default_value = "standard")]
+    pub game_end_check_type: GameEndCheckType,
+}
+
+pub fn run(args: &ReviewArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("eval review")?;
+    let game_record = read_game_record(Path::new(&args.record))?;
+
+    let model = try_get_model_for_pile(paths, &game_record.start_pile)?;
+    let report = build_game_report(&game_record, &model, args.game_end_check_type, args.iters);
+    print_game_report(&report);
+    Ok(())
+}
+
+// handy eval review --record game.txt
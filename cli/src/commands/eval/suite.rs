@@ -0,0 +1,207 @@
+use crate::commands::{GlobalArgs, OutputFormat};
+use crate::*;
+use clap::Args;
+use handy_core::game::*;
+use handy_core::solver::*;
+use rand::seq::SliceRandom;
+use rand_pcg::Pcg64;
+use rand_seeder::Seeder;
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct EvaluateArgs {
+    /// Defaults to every matchup with a model and training data
+    #[clap(long, value_parser=parse_dot_separated_matchup)]
+    pub matchups: Vec<Matchup>,
+    /// Defaults to the models in the data dir
+    #[clap(long)]
+    pub models_dir: Option<PathBuf>,
+    /// A second models directory, evaluated on the same examples and seeds
+    #[clap(long)]
+    pub compare_dir: Option<PathBuf>,
+    #[clap(long, default_value_t = 20_000)]
+    pub max_examples: usize,
+    #[clap(long, default_value_t = 10)]
+    pub num_seeds: usize,
+    #[clap(long, default_value_t = 1_000_000)]
+    pub max_iters: usize,
+    #[clap(long, action)]
+    pub skip_solver: bool,
+    /// Reports are written to data/evaluations/{name}.json, and {name}.compare.json
+    #[clap(long, default_value = "latest")]
+    pub name: String,
+}
+
+fn read_win_examples(
+    paths: &Paths,
+    matchup: Matchup,
+    max_examples: usize,
+    seed: &str,
+) -> Vec<(Pile, usize)> {
+    let Ok(examples) = read_examples_for_matchup(paths, matchup) else {
+        return vec![];
+    };
+    let mut wins: Vec<(Pile, usize)> = examples
+        .filter_map(|ex| {
+            let ex = ex.ok()?;
+            // Metrics by depth are only meaningful against the true depth, not a bound on it
+            let (StateEval::Win(depth), true) = (ex.eval, ex.confidence.is_exact()) else {
+                return None;
+            };
+            Some((ex.pile, depth))
+        })
+        .collect();
+
+    // Same subset for every model being compared
+    let mut rng = Seeder::from(seed).make_rng::<Pcg64>();
+    wins.shuffle(&mut rng);
+    wins.truncate(max_examples);
+    wins
+}
+
+fn evaluate_matchup(
+    matchup: Matchup,
+    model: &Model,
+    examples: &[(Pile, usize)],
+    args: &EvaluateArgs,
+    seed: &str,
+) -> MatchupEvaluation {
+    let predictions = if examples.is_empty() {
+        None
+    } else {
+        let predicted: Vec<(f32, usize)> = examples
+            .iter()
+            .map(|(pile, depth)| (model.score_pile(pile), *depth))
+            .collect();
+        Some(prediction_metrics(&predicted))
+    };
+
+    let solver = if args.skip_solver {
+        None
+    } else {
+        let seeds = benchmark_seed_piles(matchup, args.num_seeds, seed)
+            .iter()
+            .map(|pile| benchmark_seed(pile, Box::new(model.clone()), args.max_iters))
+            .collect();
+        Some(solver_metrics(seeds))
+    };
+
+    MatchupEvaluation {
+        matchup: matchup_to_str(matchup),
+        predictions,
+        solver,
+    }
+}
+
+fn format_option(value: Option<f32>) -> String {
+    value.map_or("-".to_owned(), |v| format!("{v:.2}"))
+}
+
+fn print_evaluation(label: &str, evaluation: &MatchupEvaluation) {
+    if let Some(predictions) = &evaluation.predictions {
+        println!(
+            "{: <30} {: <8} mse {:.3} / rank corr {:.3} / overestimates {:.3}",
+            evaluation.matchup,
+            label,
+            predictions.mse,
+            predictions.rank_correlation,
+            predictions.overestimate_fraction,
+        );
+        let buckets: Vec<String> = predictions
+            .mse_by_depth
+            .iter()
+            .map(|b| format!("{}:{:.2}", b.depth, b.mse))
+            .collect();
+        println!("{: <39} mse by depth {}", "", buckets.join(" "));
+    }
+    if let Some(solver) = &evaluation.solver {
+        println!(
+            "{: <30} {: <8} solved {}/{} / first {} iters {} ms / completed best {} iters {} ms / depth {}",
+            evaluation.matchup,
+            label,
+            solver.num_solved,
+            solver.num_seeds,
+            format_option(solver.mean_iters_to_first),
+            format_option(solver.mean_ms_to_first),
+            format_option(solver.mean_iters_to_best_completed),
+            format_option(solver.mean_ms_to_best_completed),
+            format_option(solver.mean_best_depth),
+        );
+    }
+}
+
+pub fn run(args: &EvaluateArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    let is_text = global.format == OutputFormat::Text;
+    let seed = global.seed_or(DEFAULT_BENCHMARK_SEED);
+    let models_dir = args
+        .models_dir
+        .clone()
+        .unwrap_or_else(|| paths.models_dir());
+    let matchups: Vec<Matchup> = if args.matchups.is_empty() {
+        HEROS
+            .iter()
+            .flat_map(|hero| MONSTERS.iter().map(move |monster| (*hero, *monster)))
+            .filter(|matchup| {
+                try_read_model_for_matchup_in_dir(&models_dir, *matchup).is_ok()
+                    && has_examples_for_matchup(paths, *matchup)
+            })
+            .collect()
+    } else {
+        args.matchups.clone()
+    };
+
+    let mut report = EvaluationReport {
+        models_dir: models_dir.to_string_lossy().into_owned(),
+        benchmark_seed: seed.clone(),
+        matchups: vec![],
+    };
+    let mut compare_report = args.compare_dir.as_ref().map(|dir| EvaluationReport {
+        models_dir: dir.to_string_lossy().into_owned(),
+        benchmark_seed: seed.clone(),
+        matchups: vec![],
+    });
+
+    for matchup in matchups {
+        let model = try_read_model_for_matchup_in_dir(&models_dir, matchup)
+            .map_err(|err| format!("{matchup:?}: {err}"))?;
+        let examples = read_win_examples(paths, matchup, args.max_examples, &seed);
+
+        let evaluation = evaluate_matchup(matchup, &model, &examples, args, &seed);
+        if is_text {
+            print_evaluation("base", &evaluation);
+        }
+        report.matchups.push(evaluation);
+
+        if let (Some(compare_dir), Some(compare_report)) = (&args.compare_dir, &mut compare_report)
+        {
+            let compare_model = try_read_model_for_matchup_in_dir(compare_dir, matchup)
+                .map_err(|err| format!("{matchup:?}: no model to compare against: {err}"))?;
+            let evaluation = evaluate_matchup(matchup, &compare_model, &examples, args, &seed);
+            if is_text {
+                print_evaluation("compare", &evaluation);
+            }
+            compare_report.matchups.push(evaluation);
+        }
+    }
+
+    let report_path = write_evaluation_report(paths, &report, &args.name);
+    let compare_path = compare_report.as_ref().map(|compare_report| {
+        write_evaluation_report(paths, compare_report, &format!("{}.compare", args.name))
+    });
+    match global.format {
+        OutputFormat::Text => {
+            println!("Wrote {report_path}");
+            if let Some(compare_path) = compare_path {
+                println!("Wrote {compare_path}");
+            }
+        }
+        OutputFormat::Json => {
+            print_json(&serde_json::json!({ "report": report, "compare": compare_report }))?
+        }
+    }
+    Ok(())
+}
+
+// handy eval suite --matchups warrior.ogre
+// handy eval suite --compare-dir ./data/models/old_models --name old_vs_new
+// handy eval suite --matchups warrior.ogre --skip-solver --format json
@@ -0,0 +1,29 @@
+pub mod a_star;
+pub mod batch;
+pub mod convert;
+pub mod self_play;
+
+use crate::commands::GlobalArgs;
+use crate::paths::Paths;
+use clap::Subcommand;
+
+#[derive(Subcommand, Debug)]
+pub enum GenerateCommand {
+    /// Solve random piles with A* and write every pile on the solution path
+    AStar(a_star::GenerateAStarArgs),
+    /// Breadth first search from random piles, keeping the best states at each level
+    Batch(batch::GenerateBatchArgs),
+    /// Solve sampled start piles, labelling examples exact or as upper bounds
+    SelfPlay(self_play::SelfPlayArgs),
+    /// Move jsonl training data into binary shards
+    Convert(convert::ConvertTrainingDataArgs),
+}
+
+pub fn run(command: &GenerateCommand, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    match command {
+        GenerateCommand::AStar(args) => a_star::run(args, global, paths),
+        GenerateCommand::Batch(args) => batch::run(args, global, paths),
+        GenerateCommand::SelfPlay(args) => self_play::run(args, global, paths),
+        GenerateCommand::Convert(args) => convert::run(args, global, paths),
+    }
+}
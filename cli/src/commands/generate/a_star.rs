@@ -0,0 +1,114 @@
+use crate::commands::GlobalArgs;
+use crate::run_a_star::{run_a_star_solver, AStarOptions, LossCaches};
+use crate::*;
+use clap::Args;
+use handy_core::game::end_game::GameEndCheckType;
+use handy_core::game::*;
+// use handy_core::utils::string_to_pile;
+use rand::Rng;
+
+#[derive(Args, Debug)]
+pub struct GenerateAStarArgs {
+    #[clap(flatten)]
+    pub matchup_args: TrainArgs,
+}
+
+// const ROOT_PILE_SOLVE_NUM_ITERS_FOR_DEPTH_MODE: usize = 5_000_000;
+// const ROOT_PILE_SOLVE_NUM_ITERS_FOR_DEPTH_MODE: usize = 2_000_000;
+const ROOT_PILE_SOLVE_NUM_ITERS_FOR_DEPTH_MODE: usize = 5_000_000;
+// The solve runs out of iterations, so depths are only exact once proven
+const PROVE_ITERS: usize = 200_000;
+
+fn generate_example<R: Rng>(
+    paths: &Paths,
+    loss_caches: &LossCaches,
+    hero: Class,
+    monster: Class,
+    extra_card_sets: &[Vec<CardPtr>],
+    rng: &mut R,
+) -> Result<(), String> {
+    let mut start_pile = get_random_pile_matching_stats(hero, monster, 30, 30, rng);
+
+    if extra_card_sets.len() > 0 {
+        let extra_card_set_idx = rng.gen_range(0..extra_card_sets.len());
+        let extra_cards = &extra_card_sets[extra_card_set_idx];
+
+        for card in extra_cards {
+            let insert_index = rng.gen_range(0..start_pile.len() + 1);
+            start_pile.insert(insert_index, card.clone())
+        }
+    }
+
+    println!(
+        "{}, Starting new pile {hero:?} v {monster:?}: {:?}",
+        get_datetime_stamp(),
+        start_pile
+    );
+
+    let options = AStarOptions {
+        loss_cache: loss_caches.get(paths, &start_pile, GameEndCheckType::Standard),
+        ..AStarOptions::default()
+    };
+    let root_res = run_a_star_solver(
+        paths,
+        start_pile.into(),
+        &options,
+        Some(ROOT_PILE_SOLVE_NUM_ITERS_FOR_DEPTH_MODE),
+        false,
+    )?;
+    if root_res.len() == 0 {
+        return Ok(());
+    }
+
+    let examples = label_solution(&root_res, PROVE_ITERS);
+
+    match write_examples_for_matchup(paths, (hero, monster), examples.into_iter()) {
+        Ok(num_written) => println!("Wrote {num_written} new examples"),
+        Err(err) => eprintln!("Couldn't write examples: {err}"),
+    }
+    Ok(())
+}
+
+pub fn run(args: &GenerateAStarArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("generate a-star")?;
+    let mut rng = global.rng();
+    let extra_cards = vec![
+        vec![CardPtr::new_from_id(103, FaceKey::A)],
+        vec![CardPtr::new_from_id(103, FaceKey::B)],
+        vec![CardPtr::new_from_id(103, FaceKey::D)],
+    ];
+
+    let loss_caches = LossCaches::default();
+    let all_matchups = matchups_from_train_args(args.matchup_args.clone());
+    if all_matchups.is_empty() {
+        return Err("No matchups, pass --all, --classes or --matchups".to_owned());
+    }
+    if all_matchups.len() == 1 {
+        let matchup = all_matchups.iter().next().unwrap();
+        loop {
+            generate_example(
+                paths,
+                &loss_caches,
+                matchup.0,
+                matchup.1,
+                &extra_cards,
+                &mut rng,
+            )?;
+        }
+    }
+
+    loop {
+        let matchup = find_least_used_matchup(paths, all_matchups.iter());
+        generate_example(
+            paths,
+            &loss_caches,
+            matchup.0,
+            matchup.1,
+            &extra_cards,
+            &mut rng,
+        )?;
+    }
+}
+
+// handy generate a-star --matchups warrior.ogre
+// handy generate a-star --all
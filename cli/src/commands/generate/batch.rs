@@ -1,17 +1,24 @@
-use cli::*;
+use crate::commands::GlobalArgs;
+use crate::*;
+use clap::Args;
+use handy_core::game::end_game::{standard_check_is_game_winner, GameEndCheckType};
 use handy_core::game::*;
 use handy_core::solver::*;
 use priq::PriorityQueue;
-use rand::thread_rng;
 use rand::Rng;
 use std::cmp;
 use std::cmp::Reverse;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
-use handy_core::game::end_game::standard_check_is_game_winner;
 
 pub type ScoreMap = BTreeMap<Pile, usize>;
 
+#[derive(Args, Debug)]
+pub struct GenerateBatchArgs {
+    #[clap(flatten)]
+    pub matchup_args: TrainArgs,
+}
+
 fn per_level_keep_states(hero: Class, monster: Class) -> usize {
     if hero == Class::Beastmaster && monster == Class::Flora {
         return 80000;
@@ -59,7 +66,8 @@ pub fn generate_examples<M: ModelT>(
     keep_states: usize,
     extra_levels: usize,
     model: &M,
-) -> ScoreMap {
+    loss_cache: &mut LossCache,
+) -> (ScoreMap, Vec<Pile>) {
     println!("{:?}", start_pile);
 
     let mut level: usize = 0;
@@ -67,6 +75,7 @@ pub fn generate_examples<M: ModelT>(
     let mut extra_levels_count: usize = 0;
     let mut parents_per_level: Vec<HashMap<Pile, Vec<Pile>>> = Vec::new();
     let mut winners_per_level: Vec<Vec<Pile>> = Vec::new();
+    let mut new_losses: Vec<Pile> = Vec::new();
 
     loop {
         println!("Starting level {}: {}", level, current_level.len());
@@ -75,12 +84,23 @@ pub fn generate_examples<M: ModelT>(
         let mut is_queue_full = false;
         let mut in_queue: HashMap<Pile, Vec<Pile>> = HashMap::with_capacity(keep_states + 1); // (child pile, parents)
 
+        let mut num_new_losses: usize = 0;
         for pile in current_level {
+            // Proven losses are cached along with the piles proving them, which prunes them on
+            // later runs. Every newly proven pile is a loss example, not just the beam's.
+            if loss_cache.prove_loss_collecting(&pile, DEFAULT_LOSS_PROOF_TURNS, &mut new_losses) {
+                num_new_losses += 1;
+                continue;
+            }
+
             let cur_state = GameStateWithEventLog::new(pile.clone());
             let child_states = resolve_top_card(&cur_state);
 
             for child_state in child_states {
                 let child_pile = child_state.pile;
+                if loss_cache.contains(&child_pile) {
+                    continue;
+                }
 
                 let resolution = standard_check_is_game_winner(&child_pile);
                 if resolution == WinType::Win {
@@ -115,7 +135,12 @@ pub fn generate_examples<M: ModelT>(
         }
 
         let total_parents: usize = in_queue.values().map(|p| p.len()).sum();
-        println!("queue_len: {} / parent: {}", in_queue.len(), total_parents);
+        println!(
+            "queue_len: {} / parent: {} / new losses: {}",
+            in_queue.len(),
+            total_parents,
+            num_new_losses
+        );
 
         let num_winners = winners.len();
 
@@ -186,47 +211,83 @@ pub fn generate_examples<M: ModelT>(
 
     print_examples_per_depth_histo(&known_winners);
 
-    known_winners
+    (known_winners, new_losses)
 }
 
-fn write_examples<R: Rng>(hero: Class, monster: Class, rng: &mut R) {
-    let model = try_read_model_for_matchup((hero, monster)).expect("Could not read model");
-    let training_examples_path = training_path_for_matchup((hero, monster));
+fn write_examples<R: Rng>(
+    paths: &Paths,
+    hero: Class,
+    monster: Class,
+    rng: &mut R,
+) -> Result<(), String> {
+    let model = try_read_model_for_matchup(paths, (hero, monster)).expect("Could not read model");
 
     // let pile = get_start_from_classes(hero, monster, &mut rng);
     let pile = get_random_pile_matching_stats(hero, monster, 20, 20, rng);
     let per_level_keep_states = per_level_keep_states(hero, monster);
     let extra_levels = extra_levels(hero, monster);
 
-    let examples = generate_examples(pile, per_level_keep_states, extra_levels, &model);
+    let mut loss_cache = try_read_loss_cache_for_pile(paths, &pile, GameEndCheckType::Standard)?;
+    let (examples, new_losses) = generate_examples(
+        pile.clone(),
+        per_level_keep_states,
+        extra_levels,
+        &model,
+        &mut loss_cache,
+    );
+    if let Err(err) = write_loss_cache_for_pile(paths, &loss_cache, &pile) {
+        eprintln!("Couldn't write the loss cache: {err}");
+    }
 
+    // The beam drops states, so a shorter win might have been cut. Losses are proven.
     let example_iter = examples.into_iter().filter_map(|(pile, depth)| {
         return if depth > 0 {
             Some(DepthModeTrainingExample {
                 pile: pile.clone(),
                 eval: StateEval::Win(depth),
+                confidence: LabelConfidence::UpperBound,
             })
         } else {
             None
         };
     });
+    let loss_example_iter = new_losses.into_iter().map(|pile| DepthModeTrainingExample {
+        pile,
+        eval: StateEval::Loss,
+        confidence: LabelConfidence::Exact,
+    });
 
-    write_examples_to_file(&training_examples_path, example_iter);
+    match write_examples_for_matchup(
+        paths,
+        (hero, monster),
+        example_iter.chain(loss_example_iter),
+    ) {
+        Ok(num_written) => println!("Wrote {num_written} new examples"),
+        Err(err) => eprintln!("Couldn't write examples: {err}"),
+    }
+    Ok(())
 }
 
-fn main() {
-    let all_matchups = get_training_matchups_from_args();
-    let mut rng = thread_rng();
+pub fn run(args: &GenerateBatchArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("generate batch")?;
+    let all_matchups = matchups_from_train_args(args.matchup_args.clone());
+    if all_matchups.is_empty() {
+        return Err("No matchups, pass --all, --classes or --matchups".to_owned());
+    }
+    let mut rng = global.rng();
     if all_matchups.len() == 1 {
         let matchup = all_matchups.iter().next().unwrap();
         loop {
-            write_examples(matchup.0, matchup.1, &mut rng);
+            write_examples(paths, matchup.0, matchup.1, &mut rng)?;
         }
     } else {
         loop {
-            let matchup = find_least_used_matchup(all_matchups.iter());
+            let matchup = find_least_used_matchup(paths, all_matchups.iter());
             println!("{:?}", matchup);
-            write_examples(matchup.0, matchup.1, &mut rng);
+            write_examples(paths, matchup.0, matchup.1, &mut rng)?;
         }
     }
 }
+
+// handy generate batch --matchups warrior.ogre
+// handy generate batch --classes warrior --seed abc
@@ -0,0 +1,83 @@
+use crate::commands::GlobalArgs;
+use crate::*;
+use clap::Args;
+use handy_core::game::*;
+use handy_core::solver::Matchup;
+use std::fs;
+use std::path::Path;
+
+#[derive(Args, Debug)]
+pub struct ConvertTrainingDataArgs {
+    /// Defaults to every matchup with a jsonl file
+    #[clap(long, value_parser=parse_dot_separated_matchup)]
+    pub matchups: Vec<Matchup>,
+    /// Keep the jsonl file around as {path}.bak instead of deleting it
+    #[clap(long, action)]
+    pub keep_jsonl: bool,
+}
+
+fn convert_matchup(
+    paths: &Paths,
+    matchup: Matchup,
+    keep_jsonl: bool,
+) -> Result<(usize, usize), String> {
+    let jsonl_path = training_path_for_matchup(paths, matchup);
+    let mut writer = ShardedExampleWriter::open(&training_shards_dir_for_matchup(paths, matchup))?;
+
+    let mut num_read = 0;
+    let mut read_error = None;
+    let examples = read_jsonl_examples(&jsonl_path)?.map_while(|example| match example {
+        Ok(example) => {
+            num_read += 1;
+            Some(example)
+        }
+        Err(err) => {
+            read_error = Some(err);
+            None
+        }
+    });
+    let num_written = writer.write_examples(examples)?;
+    if let Some(err) = read_error {
+        return Err(format!("Stopped after {num_read} examples: {err}"));
+    }
+
+    // Readers chain shards and jsonl, so the jsonl has to go to avoid counting examples twice
+    if keep_jsonl {
+        fs::rename(&jsonl_path, format!("{jsonl_path}.bak")).map_err(|err| format!("{err}"))?;
+    } else {
+        fs::remove_file(&jsonl_path).map_err(|err| format!("{err}"))?;
+    }
+
+    Ok((num_read, num_written))
+}
+
+pub fn run(
+    args: &ConvertTrainingDataArgs,
+    global: &GlobalArgs,
+    paths: &Paths,
+) -> Result<(), String> {
+    global.require_text_format("generate convert")?;
+    let matchups: Vec<Matchup> = if args.matchups.is_empty() {
+        HEROS
+            .iter()
+            .flat_map(|hero| MONSTERS.iter().map(move |monster| (*hero, *monster)))
+            .filter(|matchup| Path::new(&training_path_for_matchup(paths, *matchup)).exists())
+            .collect()
+    } else {
+        args.matchups.clone()
+    };
+
+    for matchup in matchups {
+        match convert_matchup(paths, matchup, args.keep_jsonl) {
+            Ok((num_read, num_written)) => println!(
+                "{matchup:?}: read {num_read}, wrote {num_written}, skipped {} duplicates",
+                num_read - num_written
+            ),
+            Err(err) => println!("{matchup:?}: couldn't convert: {err}"),
+        }
+    }
+    Ok(())
+}
+
+// handy generate convert
+// handy generate convert --matchups warrior.ogre --keep-jsonl
@@ -0,0 +1,245 @@
+use crate::commands::GlobalArgs;
+use crate::*;
+use clap::Args;
+use handy_core::solver::Matchup;
+use rand::thread_rng;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Args, Debug)]
+pub struct SelfPlayArgs {
+    #[clap(flatten)]
+    pub matchup_args: TrainArgs,
+    #[clap(long, value_enum, default_value_t = StartPileSampler::Mixed)]
+    pub sampler: StartPileSampler,
+    #[clap(long, default_value_t = 2_000_000)]
+    pub max_iters: usize,
+    /// Budget for proving each label exact, 0 labels everything as an upper bound
+    #[clap(long, default_value_t = 200_000)]
+    pub prove_iters: usize,
+    /// Skip examples at depths that already have this many times the average count
+    #[clap(long, default_value_t = 2.0)]
+    pub max_depth_ratio: f32,
+    #[clap(long)]
+    pub max_start_piles: Option<usize>,
+    #[clap(long)]
+    pub time_limit_mins: Option<u64>,
+    #[clap(long, default_value_t = 20)]
+    pub report_every: usize,
+}
+
+struct MatchupGenerator {
+    writer: ShardedExampleWriter,
+    balancer: DepthBalancer,
+    stats: SelfPlayStats,
+}
+
+#[derive(Serialize)]
+struct MatchupCoverage {
+    matchup: String,
+    total_examples: usize,
+    examples_by_depth: BTreeMap<usize, usize>,
+    session: SelfPlayStats,
+}
+
+fn open_generator(
+    paths: &Paths,
+    matchup: Matchup,
+    max_depth_ratio: f32,
+) -> Result<MatchupGenerator, String> {
+    let writer = ShardedExampleWriter::open(&training_shards_dir_for_matchup(paths, matchup))?;
+    let mut balancer = DepthBalancer::new(max_depth_ratio);
+    if has_examples_for_matchup(paths, matchup) {
+        for example in read_examples_for_matchup(paths, matchup)? {
+            balancer.record(example?.eval);
+        }
+    }
+
+    Ok(MatchupGenerator {
+        writer,
+        balancer,
+        stats: SelfPlayStats::default(),
+    })
+}
+
+fn least_covered_matchup(generators: &HashMap<Matchup, Mutex<MatchupGenerator>>) -> Matchup {
+    *generators
+        .iter()
+        .min_by_key(|(_, generator)| generator.lock().unwrap().balancer.total())
+        .unwrap()
+        .0
+}
+
+fn run_start_pile(
+    paths: &Paths,
+    matchup: Matchup,
+    generator: &Mutex<MatchupGenerator>,
+    args: &SelfPlayArgs,
+) -> Result<(), String> {
+    let pile = sample_start_pile(args.sampler, matchup, &mut thread_rng());
+    let mut stats = SelfPlayStats {
+        num_start_piles: 1,
+        ..SelfPlayStats::default()
+    };
+
+    let model = try_get_heuristic_for_pile(paths, &pile)?;
+    let examples = match solve_start_pile(&pile, model, args.max_iters) {
+        SelfPlayOutcome::Solved(path) => {
+            stats.num_solved += 1;
+            label_solution(&path, args.prove_iters)
+        }
+        SelfPlayOutcome::ProvenLoss => {
+            stats.num_proven_losses += 1;
+            vec![DepthModeTrainingExample {
+                pile,
+                eval: StateEval::Loss,
+                confidence: LabelConfidence::Exact,
+            }]
+        }
+        SelfPlayOutcome::Unknown => {
+            stats.num_unknown += 1;
+            vec![]
+        }
+    };
+
+    let mut generator = generator.lock().unwrap();
+    let mut kept = Vec::new();
+    for example in examples {
+        if !generator.balancer.should_keep(example.eval) {
+            stats.num_skipped_by_balance += 1;
+            continue;
+        }
+        match example.confidence {
+            LabelConfidence::Exact => stats.num_exact_labels += 1,
+            LabelConfidence::UpperBound => stats.num_bounded_labels += 1,
+        }
+        generator.balancer.record(example.eval);
+        kept.push(example);
+    }
+    stats.num_written = generator.writer.write_examples(kept.into_iter())?;
+    generator.stats.add(&stats);
+
+    Ok(())
+}
+
+fn report(paths: &Paths, generators: &HashMap<Matchup, Mutex<MatchupGenerator>>) {
+    println!(
+        "{} {: <30} {: >8} {: >8} {: >8} {: >8} {: >8} {: >8} {: >8} {: >8} {: >10}",
+        get_datetime_stamp(),
+        "matchup",
+        "piles",
+        "solved",
+        "losses",
+        "unknown",
+        "exact",
+        "bounded",
+        "skipped",
+        "written",
+        "total"
+    );
+
+    let mut coverage: Vec<MatchupCoverage> = Vec::new();
+    for (matchup, generator) in generators {
+        let generator = generator.lock().unwrap();
+        let stats = &generator.stats;
+        println!(
+            "{} {: <30} {: >8} {: >8} {: >8} {: >8} {: >8} {: >8} {: >8} {: >8} {: >10}",
+            get_datetime_stamp(),
+            format!("{matchup:?}"),
+            stats.num_start_piles,
+            stats.num_solved,
+            stats.num_proven_losses,
+            stats.num_unknown,
+            stats.num_exact_labels,
+            stats.num_bounded_labels,
+            stats.num_skipped_by_balance,
+            stats.num_written,
+            generator.balancer.total(),
+        );
+        coverage.push(MatchupCoverage {
+            matchup: matchup_to_str(*matchup),
+            total_examples: generator.balancer.total(),
+            examples_by_depth: generator.balancer.counts().clone(),
+            session: stats.clone(),
+        });
+    }
+    coverage.sort_by(|a, b| a.matchup.cmp(&b.matchup));
+
+    let path = self_play_coverage_path(paths);
+    let result = File::create(&path)
+        .map_err(|err| format!("{err}"))
+        .and_then(|file| {
+            serde_json::to_writer_pretty(BufWriter::new(file), &coverage)
+                .map_err(|err| format!("{err}"))
+        });
+    if let Err(err) = result {
+        eprintln!("Couldn't write {}: {err}", path.display());
+    }
+}
+
+pub fn run(args: &SelfPlayArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("generate self-play")?;
+    let matchups = matchups_from_train_args(args.matchup_args.clone());
+    if matchups.is_empty() {
+        return Err("No matchups, pass --all, --classes or --matchups".to_owned());
+    }
+
+    let mut generators: HashMap<Matchup, Mutex<MatchupGenerator>> = HashMap::new();
+    for matchup in matchups {
+        // Without a model every solve would fail, so stop before starting any
+        try_get_heuristic_for_pile(
+            paths,
+            &sample_start_pile(args.sampler, matchup, &mut thread_rng()),
+        )
+        .map_err(|err| format!("{matchup:?}: {err}"))?;
+        match open_generator(paths, matchup, args.max_depth_ratio) {
+            Ok(generator) => {
+                generators.insert(matchup, Mutex::new(generator));
+            }
+            Err(err) => println!("Skipping {matchup:?}: {err}"),
+        }
+    }
+    if generators.is_empty() {
+        return Ok(());
+    }
+
+    let num_threads = global.num_threads();
+    let start_time = Instant::now();
+    let time_limit = args
+        .time_limit_mins
+        .map(|mins| Duration::from_secs(mins * 60));
+    let num_started = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for _ in 0..num_threads {
+            scope.spawn(|| loop {
+                let pile_num = num_started.fetch_add(1, Ordering::SeqCst);
+                if args.max_start_piles.map_or(false, |max| pile_num >= max)
+                    || time_limit.map_or(false, |limit| start_time.elapsed() > limit)
+                {
+                    break;
+                }
+
+                let matchup = least_covered_matchup(&generators);
+                if let Err(err) = run_start_pile(paths, matchup, &generators[&matchup], args) {
+                    eprintln!("{matchup:?}: {err}");
+                }
+                if (pile_num + 1) % args.report_every.max(1) == 0 {
+                    report(paths, &generators);
+                }
+            });
+        }
+    });
+
+    report(paths, &generators);
+    Ok(())
+}
+
+// handy generate self-play --all
+// handy generate self-play --matchups warrior.ogre --threads 4 --time-limit-mins 120
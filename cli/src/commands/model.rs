@@ -0,0 +1,21 @@
+pub mod explain;
+pub mod info;
+
+use crate::commands::GlobalArgs;
+use crate::paths::Paths;
+use clap::Subcommand;
+
+#[derive(Subcommand, Debug)]
+pub enum ModelCommand {
+    /// Format version, metadata and cards of model files
+    Info(info::ModelInfoArgs),
+    /// Break a pile's score down into the model's terms
+    Explain(explain::ExplainArgs),
+}
+
+pub fn run(command: &ModelCommand, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    match command {
+        ModelCommand::Info(args) => info::run(args, global, paths),
+        ModelCommand::Explain(args) => explain::run(args, global, paths),
+    }
+}
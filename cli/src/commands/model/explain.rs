@@ -0,0 +1,58 @@
+use crate::commands::GlobalArgs;
+use crate::paths::Paths;
+use crate::{get_starting_pile_from_args, try_get_model_for_pile, StandardArgs};
+use clap::Args;
+use handy_core::game::Pile;
+use handy_core::solver::ScoreContribution;
+
+#[derive(Args, Debug)]
+pub struct ExplainArgs {
+    #[clap(flatten)]
+    pub standard_args: StandardArgs,
+
+    // How many of the largest terms to print
+    #[clap(long, default_value_t = 15)]
+    pub top: usize,
+}
+
+fn term_source(pile: &Pile, contribution: &ScoreContribution) -> String {
+    match contribution.position {
+        Some(position) => format!("{position}: {:?}", pile[position]),
+        None => "-".to_owned(),
+    }
+}
+
+pub fn run(args: &ExplainArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("model explain")?;
+    let pile = get_starting_pile_from_args(&args.standard_args, global.seed.as_deref());
+    println!("{pile:?}");
+
+    let model = try_get_model_for_pile(paths, &pile)?;
+    let explanation = model.explain_score(&pile);
+    println!("Score: {:.3}", explanation.total);
+
+    println!();
+    println!("{: <10} {: >10}", "card", "total");
+    for (position, total) in explanation.card_totals(pile.len()).iter().enumerate() {
+        println!(
+            "{: <10} {: >10.3}",
+            format!("{position}: {:?}", pile[position]),
+            total
+        );
+    }
+
+    println!();
+    println!("{: <10} {: <36} {: >10}", "card", "feature", "value");
+    for contribution in explanation.top_contributions(args.top) {
+        println!(
+            "{: <10} {: <36} {: >10.3}",
+            term_source(&pile, &contribution),
+            contribution.feature.to_string(),
+            contribution.contribution
+        );
+    }
+    Ok(())
+}
+
+// handy model explain --pile "3B 102A 2B 101C 6B 8B 4B 1B 9B 5B 7B"
+// handy model explain --classes warrior ogre --top 30
@@ -0,0 +1,153 @@
+use crate::commands::{GlobalArgs, OutputFormat};
+use crate::*;
+use clap::Args;
+use handy_core::solver::*;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct ModelInfoArgs {
+    /// Defaults to every matchup and quest model in the model dirs
+    pub paths: Vec<PathBuf>,
+    /// Rewrite models that needed migrating in the current format
+    #[clap(long, action)]
+    pub migrate: bool,
+}
+
+#[derive(Serialize)]
+struct ModelInfo {
+    path: String,
+    file_format_version: u32,
+    file_schema_hash: String,
+    metadata: ModelMetadata,
+    card_ids: Vec<CardId>,
+    has_loss_head: bool,
+    migrations: Vec<String>,
+}
+
+impl ModelInfo {
+    fn new(path: &str, loaded: &LoadedModel) -> Self {
+        Self {
+            path: path.to_owned(),
+            file_format_version: loaded.file_format_version,
+            file_schema_hash: loaded.file_schema_hash.clone(),
+            metadata: loaded.envelope.metadata.clone(),
+            card_ids: loaded.envelope.model.card_ids(),
+            has_loss_head: loaded.envelope.model.loss_head().is_some(),
+            migrations: loaded.migrations.clone(),
+        }
+    }
+}
+
+fn default_model_paths(paths: &Paths) -> Vec<PathBuf> {
+    let mut model_paths: Vec<PathBuf> = paths
+        .model_dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            // Only Model files, not MLP or semantic ones
+            name.ends_with(".yaml")
+                && !name.ends_with(".mlp.yaml")
+                && !name.ends_with(".blend.yaml")
+                && name != "Semantic.yaml"
+                && !name.starts_with("swap.")
+        })
+        .collect();
+    model_paths.sort();
+    model_paths
+}
+
+fn print_model_info(path: &str, loaded: &LoadedModel) {
+    let metadata = &loaded.envelope.metadata;
+    let model = &loaded.envelope.model;
+    println!("{path}");
+    println!(
+        "  format version:   {} (current {MODEL_FORMAT_VERSION})",
+        loaded.file_format_version
+    );
+    println!(
+        "  feature schema:   {} (current {})",
+        loaded.file_schema_hash, metadata.feature_schema_hash
+    );
+    println!(
+        "  matchup:          {}",
+        metadata.matchup.as_deref().unwrap_or("-")
+    );
+    println!(
+        "  created at:       {}",
+        metadata.created_at.as_deref().unwrap_or("-")
+    );
+    println!(
+        "  validation loss:  {}",
+        metadata
+            .validation_loss
+            .map_or("-".to_owned(), |loss| format!("{loss:.4}"))
+    );
+    if let Some(stats) = &metadata.training_data {
+        println!(
+            "  examples:         {} train / {} validation / {} loss head",
+            stats.num_train_examples,
+            stats.num_validation_examples,
+            stats
+                .num_loss_head_examples
+                .map_or("-".to_owned(), |n| n.to_string())
+        );
+    }
+    println!("  cards:            {:?}", model.card_ids());
+    println!("  loss head:        {}", model.loss_head().is_some());
+    for migration in &loaded.migrations {
+        println!("  migration:        {migration}");
+    }
+}
+
+pub fn run(args: &ModelInfoArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    let mut infos = Vec::new();
+    let paths = if args.paths.is_empty() {
+        default_model_paths(paths)
+    } else {
+        args.paths.clone()
+    };
+
+    for path in paths {
+        let path_str = path.to_string_lossy().into_owned();
+        let loaded = match try_read_model_envelope_from_full_path(&path_str) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                eprintln!("{err}");
+                continue;
+            }
+        };
+        match global.format {
+            OutputFormat::Text => print_model_info(&path_str, &loaded),
+            OutputFormat::Json => infos.push(ModelInfo::new(&path_str, &loaded)),
+        }
+
+        if args.migrate && loaded.is_migrated() {
+            let mut envelope = loaded.envelope;
+            if envelope.metadata.matchup.is_none() {
+                // Matchup models are named Hero.Monster.yaml
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                envelope.metadata.matchup = parse_dot_separated_matchup(&stem.to_lowercase())
+                    .ok()
+                    .map(matchup_to_str);
+            }
+            write_model_envelope_to_full_path(&envelope, &path_str);
+            if global.format == OutputFormat::Text {
+                println!("  migrated in place");
+            }
+        }
+    }
+
+    if global.format == OutputFormat::Json {
+        print_json(&infos)?;
+    }
+    Ok(())
+}
+
+// handy model info
+// handy model info data/models/Warrior.Ogre.yaml --migrate
+// handy model info --format json
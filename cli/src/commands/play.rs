@@ -0,0 +1,341 @@
+use crate::commands::GlobalArgs;
+use crate::paths::Paths;
+use crate::{
+    format_card_faces, get_starting_pile_from_args, try_get_model_for_pile, PlaySession,
+    StandardArgs,
+};
+use clap::Args;
+use handy_core::game::end_game::{is_game_winner, GameEndCheckType};
+use handy_core::game::*;
+use handy_core::solver::*;
+use handy_core::utils::*;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct PlayArgs {
+    #[clap(flatten)]
+    pub standard_args: StandardArgs,
+
+    /// Pick from every final outcome of an activation, rather than one choice at a time
+    #[clap(long)]
+    pub all_outcomes: bool,
+
+    /// Solver iterations used to grade each outcome for hints, all outcomes mode and review
+    #[clap(long, default_value_t = DEFAULT_ANALYSIS_MAX_ITERS)]
+    pub analysis_iters: usize,
+
+    /// Print a report comparing each move against the solver once the game is over
+    #[clap(long)]
+    pub review: bool,
+
+    #[clap(long, default_value = "standard")]
+    pub game_end_check_type: GameEndCheckType,
+}
+
+const REPL_HELP: &str = "\
+<n>            Pick option n
+undo [n]       Take back the last n choices
+redo [n]       Make the last n undone choices again
+hint           The option the solver would pick
+eval           Activations needed to win from here
+show <card>    Every face of a card in the pile, e.g. show 6
+save <file>    Save the finished activations, one pile per line
+load <file>    Continue a saved game
+help           Print this
+quit           Stop playing";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ReplCommand {
+    Choose(usize),
+    Undo(usize),
+    Redo(usize),
+    Hint,
+    Eval,
+    Show(CardId),
+    Save(PathBuf),
+    Load(PathBuf),
+    Help,
+    Quit,
+}
+
+fn parse_count(arg: Option<&str>) -> Result<usize, String> {
+    arg.map_or(Ok(1), |arg| {
+        arg.parse().map_err(|_| format!("Not a number: {arg}"))
+    })
+}
+
+fn parse_repl_command(line: &str) -> Result<Option<ReplCommand>, String> {
+    let mut words = line.split_whitespace();
+    let Some(command) = words.next() else {
+        return Ok(None);
+    };
+    let arg = words.next();
+    let command = match command {
+        "undo" | "u" => ReplCommand::Undo(parse_count(arg)?),
+        "redo" | "r" => ReplCommand::Redo(parse_count(arg)?),
+        "hint" => ReplCommand::Hint,
+        "eval" => ReplCommand::Eval,
+        "show" => {
+            let arg = arg.ok_or("Usage: show <card id>")?;
+            // Allow a face, like 6B, so cards can be copied from the pile
+            let id = arg.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+            ReplCommand::Show(id.parse().map_err(|_| format!("Not a card id: {arg}"))?)
+        }
+        "save" => ReplCommand::Save(arg.ok_or("Usage: save <file>")?.into()),
+        "load" => ReplCommand::Load(arg.ok_or("Usage: load <file>")?.into()),
+        "help" | "?" => ReplCommand::Help,
+        "quit" | "exit" | "q" => ReplCommand::Quit,
+        _ => ReplCommand::Choose(
+            command
+                .parse()
+                .map_err(|_| format!("Unknown command {command}, try help"))?,
+        ),
+    };
+    Ok(Some(command))
+}
+
+fn format_prefix_result(prefix_result: &PrefixResult) -> String {
+    match prefix_result {
+        PrefixResult::Event(event, pile) => {
+            format!("{} {:?}", format_event_for_cli(event), pile)
+        }
+        PrefixResult::Pile(pile) => format!("Done Activation: {:?}", pile),
+    }
+}
+
+fn print_session(session: &PlaySession) {
+    if session.is_over() {
+        println!("{:?}", session.current_pile());
+        println!("You {:?}. Undo, save or quit", session.win_type());
+        return;
+    }
+    println!("Current State: {:?}", session.current_pile());
+    for (i, option) in session.options().iter().enumerate() {
+        println!("{}: {}", i, format_prefix_result(option));
+    }
+}
+
+fn run_repl_command(
+    paths: &Paths,
+    session: &mut PlaySession,
+    command: ReplCommand,
+    analysis_iters: usize,
+) -> Result<(), String> {
+    match command {
+        ReplCommand::Choose(choice) => session.choose(choice)?,
+        ReplCommand::Undo(count) => {
+            for _ in 0..count {
+                session.undo()?;
+            }
+        }
+        ReplCommand::Redo(count) => {
+            for _ in 0..count {
+                session.redo()?;
+            }
+        }
+        ReplCommand::Hint => {
+            let model = try_get_model_for_pile(paths, session.activation_pile())?;
+            let (choice, eval) = session
+                .hint(&model, analysis_iters)
+                .ok_or("No hint, the game is over")?;
+            println!("Hint: {choice} ({eval})");
+            return Ok(());
+        }
+        ReplCommand::Eval => {
+            let model = try_get_model_for_pile(paths, session.activation_pile())?;
+            println!("{}", session.eval(&model, analysis_iters));
+            return Ok(());
+        }
+        ReplCommand::Show(card_id) => {
+            println!("{}", format_card_faces(session.current_pile(), card_id)?);
+            return Ok(());
+        }
+        ReplCommand::Save(path) => {
+            session.save(&path)?;
+            println!("Saved {}", path.display());
+            return Ok(());
+        }
+        ReplCommand::Load(path) => {
+            *session = PlaySession::load(&path, session.game_end_check_type)?;
+        }
+        ReplCommand::Help => {
+            println!("{REPL_HELP}");
+            return Ok(());
+        }
+        ReplCommand::Quit => unreachable!("Handled by the repl loop"),
+    }
+    print_session(session);
+    Ok(())
+}
+
+/// Plays one choice at a time until the player quits
+pub fn start_repl_game(
+    paths: &Paths,
+    start_pile: Pile,
+    game_end_check_type: GameEndCheckType,
+    analysis_iters: usize,
+) -> Result<GameRecord, String> {
+    let mut session = PlaySession::new(start_pile, game_end_check_type);
+    let mut editor = DefaultEditor::new().map_err(|err| format!("{err}"))?;
+    // There's no history the first time
+    let _ = editor.load_history(&paths.play_history_path());
+
+    println!("Type help for commands");
+    print_session(&session);
+    loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(err) => return Err(format!("{err}")),
+        };
+        let _ = editor.add_history_entry(line.as_str());
+
+        match parse_repl_command(&line) {
+            Ok(None) => (),
+            Ok(Some(ReplCommand::Quit)) => break,
+            Ok(Some(command)) => {
+                if let Err(err) = run_repl_command(paths, &mut session, command, analysis_iters) {
+                    println!("{err}");
+                }
+            }
+            Err(err) => println!("{err}"),
+        }
+    }
+
+    let _ = editor.save_history(&paths.play_history_path());
+    Ok(session.record())
+}
+
+fn card_activation_result_via_all_outcomes(
+    paths: &Paths,
+    pile: &Pile,
+    game_end_check_type: GameEndCheckType,
+    analysis_iters: usize,
+) -> Result<Pile, String> {
+    let state = GameStateWithEventLog::new(pile.clone());
+    //let options = collapse_states(resolve_top_card(&state));
+    let options = resolve_top_card(&state);
+    let model = try_get_model_for_pile(paths, pile)?;
+    let analysis = analyze_moves(pile, &model, game_end_check_type, analysis_iters);
+
+    loop {
+        println!("Current State: {:?}", pile);
+        for (i, option) in options.iter().enumerate() {
+            let move_analysis = analysis.iter().find(|a| a.pile == option.pile).unwrap();
+            println!(
+                "{}: {:?} via {} ({}) ({}, {})",
+                i,
+                option.pile,
+                format_multiple_events(&option.events),
+                i,
+                move_analysis.eval,
+                format_grade(move_analysis.grade, move_analysis.is_grade_bounded),
+            );
+        }
+        let unique_choices: HashSet<Pile> =
+            options.iter().map(|option| option.pile.clone()).collect();
+        println!("Unique Choices: {}", unique_choices.len());
+
+        if options.len() == 1 {
+            println!("Making only choice");
+            return Ok(options[0].pile.clone());
+        }
+
+        let maybe_choice: Result<usize, _> = text_io::try_read!();
+        if let Ok(choice) = maybe_choice {
+            if choice < options.len() {
+                return Ok(options[choice].pile.clone());
+            }
+        }
+        println!("Invalid row number. Try again");
+    }
+}
+
+pub fn start_all_outcomes_game(
+    paths: &Paths,
+    mut active_pile: Pile,
+    game_end_check_type: GameEndCheckType,
+    analysis_iters: usize,
+) -> Result<GameRecord, String> {
+    let mut game_record = GameRecord::new(active_pile.clone());
+    loop {
+        let resolution = is_game_winner(&active_pile, game_end_check_type);
+        if resolution.is_over() {
+            println!("You {:?}", resolution);
+            break;
+        }
+
+        active_pile = card_activation_result_via_all_outcomes(
+            paths,
+            &active_pile,
+            game_end_check_type,
+            analysis_iters,
+        )?;
+        game_record.push(active_pile.clone());
+    }
+
+    Ok(game_record)
+}
+
+pub fn run(args: &PlayArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("play")?;
+    let start_pile = get_starting_pile_from_args(&args.standard_args, global.seed.as_deref());
+    let game_record = if args.all_outcomes {
+        start_all_outcomes_game(
+            paths,
+            start_pile,
+            args.game_end_check_type,
+            args.analysis_iters,
+        )?
+    } else {
+        start_repl_game(
+            paths,
+            start_pile,
+            args.game_end_check_type,
+            args.analysis_iters,
+        )?
+    };
+
+    if args.review {
+        println!("Reviewing game...");
+        let model = try_get_model_for_pile(paths, &game_record.start_pile)?;
+        let report = build_game_report(
+            &game_record,
+            &model,
+            args.game_end_check_type,
+            args.analysis_iters,
+        );
+        print_game_report(&report);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repl_command() {
+        assert_eq!(parse_repl_command("  "), Ok(None));
+        assert_eq!(parse_repl_command("3"), Ok(Some(ReplCommand::Choose(3))));
+        assert_eq!(parse_repl_command("undo"), Ok(Some(ReplCommand::Undo(1))));
+        assert_eq!(parse_repl_command("redo 2"), Ok(Some(ReplCommand::Redo(2))));
+        assert_eq!(
+            parse_repl_command("show 6B"),
+            Ok(Some(ReplCommand::Show(6)))
+        );
+        assert_eq!(
+            parse_repl_command("save game.txt"),
+            Ok(Some(ReplCommand::Save("game.txt".into())))
+        );
+        assert!(parse_repl_command("save").is_err());
+        assert!(parse_repl_command("undo x").is_err());
+        assert!(parse_repl_command("dance").is_err());
+    }
+}
+
+// handy play --classes warrior ogre --review
+// handy play --pile "1A 2A 6A 7A" --game-end-check-type per_hero_class
@@ -0,0 +1,21 @@
+pub mod find;
+pub mod generate;
+
+use crate::commands::GlobalArgs;
+use crate::paths::Paths;
+use clap::Subcommand;
+
+#[derive(Subcommand, Debug)]
+pub enum PuzzleCommand {
+    /// Look for start piles with a single long solution
+    Find(find::PuzzleFindArgs),
+    /// Make small puzzles with a unique solution, rated by difficulty
+    Generate(generate::PuzzleGenerateArgs),
+}
+
+pub fn run(command: &PuzzleCommand, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    match command {
+        PuzzleCommand::Find(args) => find::run(args, global, paths),
+        PuzzleCommand::Generate(args) => generate::run(args, global),
+    }
+}
@@ -1,13 +1,36 @@
-use std::collections::{HashMap};
+use std::collections::HashMap;
 
-use cli::get_fully_random_pile;
+use crate::commands::GlobalArgs;
+use crate::paths::Paths;
+use crate::{get_fully_random_pile, try_read_loss_cache_for_pile, write_loss_cache_for_pile};
+use clap::Args;
 use handy_core::{
     game::{
-        end_game::{is_game_winner, GameEndCheckType}, resolve_top_card, Allegiance, CardPtrT, Class, EngineGameState, GameStateNoEventLog, Pile, WinType
+        end_game::{is_game_winner, GameEndCheckType},
+        resolve_top_card, Allegiance, CardPtrT, Class, EngineGameState, GameStateNoEventLog, Pile,
+        WinType,
     },
+    solver::LossCache,
     utils::get_start_from_classes,
 };
-use rand::thread_rng;
+
+#[derive(Args, Debug)]
+pub struct PuzzleFindArgs {
+    #[clap(short, long, num_args = 2, required = true)]
+    pub classes: Vec<Class>,
+    /// How many turns deep to look for solutions
+    #[clap(long, default_value_t = 9)]
+    pub max_depth: usize,
+    /// Only print single solutions at least this long
+    #[clap(long, default_value_t = 5)]
+    pub min_depth: usize,
+    /// Start from fully random piles rather than regular starting piles
+    #[clap(long)]
+    pub fully_random: bool,
+    /// Defaults to searching until stopped
+    #[clap(long)]
+    pub max_piles: Option<usize>,
+}
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 enum RecursiveResult {
@@ -16,14 +39,14 @@ enum RecursiveResult {
     ManySolutions(usize),
 }
 
-const LOG_SOLN_FOR_DEPTH: usize = 5;
-
 type VisitedCache = HashMap<Pile, (usize, RecursiveResult)>;
 
 fn _inner(
     visited_cache: &mut VisitedCache,
+    loss_cache: &mut LossCache,
     source_pile: &Pile,
     rem_depth: usize,
+    min_logged_depth: usize,
 ) -> RecursiveResult {
     match is_game_winner(source_pile, GameEndCheckType::Standard) {
         WinType::Win => return RecursiveResult::SingleSolution(0),
@@ -31,7 +54,7 @@ fn _inner(
         WinType::Unresolved => (),
     }
 
-    if rem_depth == 0 {
+    if rem_depth == 0 || loss_cache.contains(source_pile) {
         return RecursiveResult::NoSolutions;
     }
 
@@ -47,11 +70,21 @@ fn _inner(
     );
 
     let child_states = resolve_top_card(&GameStateNoEventLog::new(source_pile.clone()));
+    if !child_states.is_empty() && child_states.iter().all(|c| loss_cache.is_lost(&c.pile)) {
+        loss_cache.insert(source_pile.clone());
+        return RecursiveResult::NoSolutions;
+    }
 
     let mut current_outcome = RecursiveResult::NoSolutions;
 
     for child_state in &child_states {
-        let child_result = _inner(visited_cache, &child_state.pile, rem_depth - 1);
+        let child_result = _inner(
+            visited_cache,
+            loss_cache,
+            &child_state.pile,
+            rem_depth - 1,
+            min_logged_depth,
+        );
 
         match child_result {
             RecursiveResult::NoSolutions => (),
@@ -79,10 +112,10 @@ fn _inner(
     }
 
     if let RecursiveResult::SingleSolution(win_depth) = current_outcome {
-        if win_depth >= LOG_SOLN_FOR_DEPTH {
-            if source_pile[0].get_active_face().allegiance == Allegiance::Hero {
-                println!("Depth {} only soln: {:?}", win_depth, source_pile);
-            }
+        if win_depth >= min_logged_depth
+            && source_pile[0].get_active_face().allegiance == Allegiance::Hero
+        {
+            println!("Depth {} only soln: {:?}", win_depth, source_pile);
         }
     }
 
@@ -93,13 +126,13 @@ fn _inner(
     current_outcome
 }
 
-fn main() {
-    let mut rng = thread_rng();
-    let hero = Class::Assassin;
-    let monster = Class::Vampire;
+pub fn run(args: &PuzzleFindArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("puzzle find")?;
+    let mut rng = global.rng();
+    let (hero, monster) = (args.classes[0], args.classes[1]);
 
-    for i in 1.. {
-        let root = if false {
+    for i in 1..=args.max_piles.unwrap_or(usize::MAX) {
+        let root = if args.fully_random {
             get_fully_random_pile(hero, monster, &mut rng)
         } else {
             get_start_from_classes(hero, monster, &mut rng)
@@ -107,6 +140,21 @@ fn main() {
         println!("{i}: investigating source {:?}", root);
 
         let mut visited_set = VisitedCache::new();
-        _inner(&mut visited_set, &root, 9);
+        let mut loss_cache =
+            try_read_loss_cache_for_pile(paths, &root, GameEndCheckType::Standard)?;
+        _inner(
+            &mut visited_set,
+            &mut loss_cache,
+            &root,
+            args.max_depth,
+            args.min_depth,
+        );
+        if let Err(err) = write_loss_cache_for_pile(paths, &loss_cache, &root) {
+            eprintln!("Couldn't write the loss cache: {err}");
+        }
     }
+    Ok(())
 }
+
+// handy puzzle find --classes assassin vampire
+// handy puzzle find --classes warrior ogre --max-depth 7 --fully-random --seed abc
@@ -0,0 +1,94 @@
+use crate::commands::{GlobalArgs, OutputFormat};
+use crate::{generate_puzzles, print_json, ObjectiveKind, Puzzle, PuzzleSpec};
+use clap::Args;
+use handy_core::game::Class;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct PuzzleGenerateArgs {
+    #[clap(short, long, num_args = 2, required = true)]
+    pub classes: Vec<Class>,
+    /// Cards in each puzzle
+    #[clap(long, default_value_t = 5)]
+    pub num_cards: usize,
+    /// Each puzzle is solved in exactly this many turns, and not in fewer
+    #[clap(long, default_value_t = 3)]
+    pub turns: usize,
+    /// Each puzzle gets one of these, at random
+    #[clap(long, value_enum, num_args = 1.., default_values = ["win", "exhaust", "survive-until-top"])]
+    pub objectives: Vec<ObjectiveKind>,
+    /// Pick cards from fully random piles rather than regular starting piles
+    #[clap(long)]
+    pub fully_random: bool,
+    #[clap(long, default_value_t = 10)]
+    pub count: usize,
+    /// Random piles to try before giving up
+    #[clap(long, default_value_t = 10_000)]
+    pub max_attempts: usize,
+    /// Only keep puzzles at least this hard
+    #[clap(long)]
+    pub min_score: Option<f32>,
+    /// Also write the puzzles to a JSON file
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+fn print_puzzle(puzzle: &Puzzle) {
+    let difficulty = &puzzle.difficulty;
+    println!(
+        "{}: {} (difficulty {:.1}, branching {:.1}, {} near misses)",
+        puzzle.bgg_notation,
+        puzzle.description,
+        difficulty.score,
+        difficulty.branching_factor,
+        difficulty.near_misses,
+    );
+}
+
+pub fn run(args: &PuzzleGenerateArgs, global: &GlobalArgs) -> Result<(), String> {
+    let spec = PuzzleSpec {
+        hero: args.classes[0],
+        monster: args.classes[1],
+        num_cards: args.num_cards,
+        turns: args.turns,
+        objective_kinds: args.objectives.clone(),
+        fully_random: args.fully_random,
+        min_score: args.min_score,
+    };
+    let is_text = global.format == OutputFormat::Text;
+
+    let mut puzzles = generate_puzzles(
+        &spec,
+        args.count,
+        args.max_attempts,
+        &mut global.rng(),
+        &mut |puzzle| {
+            if is_text {
+                print_puzzle(puzzle);
+            }
+        },
+    );
+    puzzles.sort_by(|a, b| a.difficulty.score.total_cmp(&b.difficulty.score));
+
+    if let Some(output) = &args.output {
+        let file = File::create(output).map_err(|err| format!("{err}"))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &puzzles)
+            .map_err(|err| format!("{err}"))?;
+    }
+    if is_text {
+        println!(
+            "Found {} of {} puzzles in up to {} attempts",
+            puzzles.len(),
+            args.count,
+            args.max_attempts
+        );
+    } else {
+        print_json(&puzzles)?;
+    }
+    Ok(())
+}
+
+// handy puzzle generate --classes warrior ogre --turns 3 --objectives exhaust --seed abc
+// handy puzzle generate --classes pyro vampire --num-cards 6 --min-score 8 --output puzzles.json
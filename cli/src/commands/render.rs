@@ -0,0 +1,70 @@
+use crate::commands::GlobalArgs;
+use crate::*;
+use clap::Args;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct RenderArgs {
+    /// The output of solve --format json, or a game saved by play, one pile per line
+    #[clap(long)]
+    pub input: PathBuf,
+    /// Printed to stdout if not set
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+    /// Defaults to the output's extension (.md, .html or .svg), then markdown
+    #[clap(long, value_enum)]
+    pub document_format: Option<DocumentFormat>,
+    /// Defaults to the input file's name
+    #[clap(long)]
+    pub title: Option<String>,
+}
+
+fn resolve_document_format(args: &RenderArgs) -> Result<DocumentFormat, String> {
+    if let Some(format) = args.document_format {
+        return Ok(format);
+    }
+    let Some(output) = &args.output else {
+        return Ok(DocumentFormat::Markdown);
+    };
+    let extension = output
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    DocumentFormat::from_extension(extension).ok_or_else(|| {
+        format!(
+            "Can't tell the format of {}, pass --document-format",
+            output.display()
+        )
+    })
+}
+
+pub fn run(args: &RenderArgs, global: &GlobalArgs) -> Result<(), String> {
+    global.require_text_format("render")?;
+    let format = resolve_document_format(args)?;
+    let contents = fs::read_to_string(&args.input)
+        .map_err(|err| format!("Could not read {}: {err}", args.input.display()))?;
+    let path = parse_rendered_path(&contents)?;
+    let title = args.title.clone().unwrap_or_else(|| {
+        args.input
+            .file_stem()
+            .map_or("Solution".to_owned(), |stem| {
+                stem.to_string_lossy().into_owned()
+            })
+    });
+
+    let document = render_document(format, &title, &path);
+    match &args.output {
+        Some(output) => {
+            fs::write(output, document)
+                .map_err(|err| format!("Could not write {}: {err}", output.display()))?;
+            println!("Wrote {}", output.display());
+        }
+        None => print!("{document}"),
+    }
+    Ok(())
+}
+
+// handy solve --pile "1A 2A 6A 7A" --format json > solution.json
+// handy render --input solution.json --output solution.html
+// handy render --input game.txt --document-format markdown --title "Warrior vs Ogre"
@@ -0,0 +1,237 @@
+use crate::commands::{GlobalArgs, OutputFormat};
+use crate::{
+    enumerate_arrangements, find_solutions, parse_card_specs, pile_from_notation, print_json,
+    solution_steps, ChildrenMemo, SearchObjective, SolutionStep, VictoryConditionChecker,
+    VictoryConditionResult, DEFAULT_MAX_ARRANGEMENTS,
+};
+use clap::{ArgGroup, Args};
+use handy_core::game::*;
+use handy_core::utils::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Args, Debug)]
+#[clap(group(
+            ArgGroup::new("win_type")
+                .required(true)
+                .args(&["win", "survive_until_top", "exhaust"]),
+        ))]
+#[clap(group(
+            ArgGroup::new("start")
+                .required(true)
+                .args(&["pile", "cards"]),
+        ))]
+pub struct SearchArgs {
+    #[clap(long, value_parser=string_to_pile_result)]
+    pub pile: Option<Pile>,
+
+    /// Search every arrangement of these cards, like "15? 18 5B 2B@0". A card without a face
+    /// is face A, ? is any face, and @n fixes a card n from the top
+    #[clap(long)]
+    pub cards: Option<String>,
+
+    /// --cards refuses to search more piles than this
+    #[clap(long, default_value_t = DEFAULT_MAX_ARRANGEMENTS, requires = "cards")]
+    pub max_arrangements: usize,
+
+    #[clap(long)]
+    pub turns: Option<usize>,
+
+    /// How --pile is written. Piles from the puzzle thread are reversed, with the top card last
+    #[clap(long, default_value = "spaced", conflicts_with = "cards")]
+    pub notation: PileDialect,
+
+    #[clap(long)]
+    pub win: bool,
+    #[clap(long, value_parser=string_to_card_id_result)]
+    pub survive_until_top: Option<CardId>,
+    #[clap(long, value_parser=string_to_card_id_result)]
+    pub exhaust: Option<CardId>,
+}
+
+#[derive(Serialize)]
+struct SearchReport {
+    start_pile: String,
+    // None if there are no solutions
+    turns: Option<usize>,
+    solutions: Vec<Vec<SolutionStep>>,
+}
+
+#[derive(Serialize)]
+struct ArrangementResult {
+    pile: String,
+    turns: Option<usize>,
+    num_solutions: usize,
+    // Only for piles with a unique solution
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    steps: Vec<SolutionStep>,
+}
+
+#[derive(Serialize, Default)]
+struct ArrangementsReport {
+    num_arrangements: usize,
+    // Piles that meet or fail the objective before the first turn
+    already_decided: Vec<String>,
+    no_solution: Vec<ArrangementResult>,
+    unique: Vec<ArrangementResult>,
+    multiple: Vec<ArrangementResult>,
+}
+
+fn resolve_objective(args: &SearchArgs) -> SearchObjective {
+    if args.win {
+        SearchObjective::Win
+    } else if let Some(card_id) = args.survive_until_top {
+        SearchObjective::SurviveUntilTop { card_id }
+    } else if let Some(card_id) = args.exhaust {
+        SearchObjective::Exhaust { card_id }
+    } else {
+        panic!("Could not result condition checker from these args");
+    }
+}
+
+fn print_solution(solution: &[Pile]) {
+    let mut last_pile = solution[0].clone();
+    println!("{:?}", last_pile);
+
+    for pile in solution[1..].iter().cloned() {
+        print_steps_between_piles(&last_pile, &pile, &|x| println!("{}", x));
+        println!("{:?}", pile);
+        last_pile = pile;
+    }
+}
+
+fn run_pile(
+    pile: Pile,
+    args: &SearchArgs,
+    condition_checker: &dyn VictoryConditionChecker,
+    global: &GlobalArgs,
+) -> Result<(), String> {
+    let pile = pile_from_notation(&pile, args.notation);
+
+    let solutions = find_solutions(&pile, condition_checker, args.turns, &mut HashMap::new());
+
+    if global.format == OutputFormat::Json {
+        return print_json(&SearchReport {
+            start_pile: pile_to_string(&pile),
+            turns: solutions.first().map(|solution| solution.len() - 1),
+            solutions: solutions
+                .iter()
+                .map(|solution| solution_steps(solution))
+                .collect(),
+        });
+    }
+
+    if solutions.len() == 0 {
+        println!("No solutions");
+    } else {
+        println!(
+            "Found {} solution after {} turns",
+            solutions.len(),
+            solutions[0].len() - 1
+        );
+
+        for solution in solutions {
+            println!("~~~");
+            print_solution(&solution);
+        }
+    }
+    Ok(())
+}
+
+fn print_arrangement_group(name: &str, results: &[ArrangementResult]) {
+    println!("{name}: {}", results.len());
+    for result in results {
+        match result.turns {
+            Some(turns) => println!(
+                "  {} ({} solutions after {turns} turns)",
+                result.pile, result.num_solutions
+            ),
+            None => println!("  {}", result.pile),
+        }
+    }
+}
+
+fn run_arrangements(
+    cards: &str,
+    args: &SearchArgs,
+    condition_checker: &dyn VictoryConditionChecker,
+    global: &GlobalArgs,
+) -> Result<(), String> {
+    let specs = parse_card_specs(cards)?;
+    let objective_card = match resolve_objective(args) {
+        SearchObjective::Win => None,
+        SearchObjective::SurviveUntilTop { card_id } => Some(("--survive-until-top", card_id)),
+        SearchObjective::Exhaust { card_id } => Some(("--exhaust", card_id)),
+    };
+    if let Some((flag, card_id)) = objective_card {
+        if specs.iter().all(|spec| spec.card_id != card_id) {
+            return Err(format!("Card {card_id} from {flag} isn't in --cards"));
+        }
+    }
+    let piles = enumerate_arrangements(&specs, args.max_arrangements)
+        .map_err(|err| format!("{err}, pass a higher --max-arrangements to search them all"))?;
+    let mut memo: ChildrenMemo = HashMap::new();
+    let mut unique_solutions: Vec<Vec<Pile>> = vec![];
+    let mut report = ArrangementsReport {
+        num_arrangements: piles.len(),
+        ..ArrangementsReport::default()
+    };
+
+    for pile in piles {
+        if !matches!(
+            condition_checker.condition_result(&pile),
+            VictoryConditionResult::Continue
+        ) {
+            report.already_decided.push(pile_to_string(&pile));
+            continue;
+        }
+
+        let solutions = find_solutions(&pile, condition_checker, args.turns, &mut memo);
+        let result = ArrangementResult {
+            pile: pile_to_string(&pile),
+            turns: solutions.first().map(|solution| solution.len() - 1),
+            num_solutions: solutions.len(),
+            steps: match solutions.as_slice() {
+                [solution] => solution_steps(solution),
+                _ => vec![],
+            },
+        };
+        match solutions.len() {
+            0 => report.no_solution.push(result),
+            1 => {
+                report.unique.push(result);
+                unique_solutions.extend(solutions);
+            }
+            _ => report.multiple.push(result),
+        }
+    }
+
+    if global.format == OutputFormat::Json {
+        return print_json(&report);
+    }
+
+    println!("{} arrangements", report.num_arrangements);
+    println!("Already decided: {}", report.already_decided.len());
+    print_arrangement_group("No solution", &report.no_solution);
+    print_arrangement_group("Multiple solutions", &report.multiple);
+    print_arrangement_group("Unique solution", &report.unique);
+    for solution in unique_solutions {
+        println!("~~~");
+        print_solution(&solution);
+    }
+    Ok(())
+}
+
+pub fn run(args: &SearchArgs, global: &GlobalArgs) -> Result<(), String> {
+    let condition_checker = resolve_objective(args).checker();
+    match (&args.pile, &args.cards) {
+        (Some(pile), _) => run_pile(pile.clone(), args, condition_checker.as_ref(), global),
+        (None, Some(cards)) => run_arrangements(cards, args, condition_checker.as_ref(), global),
+        (None, None) => Err("Pass --pile or --cards".to_owned()),
+    }
+}
+
+// handy search --pile "5C > 6D > 2B > 9B" --survive-until-top 5 --notation reversed
+// handy search --pile "15A > 18A > 5A > 2B > 4A" --exhaust 15 --turns 3 --notation reversed
+// handy search --pile "9D > 11B > 10A > 8D > 20D" --win --turns 3 --notation reversed --format json
+// handy search --cards "15 18 5 2B 4" --exhaust 15 --turns 3
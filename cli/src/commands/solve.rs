@@ -0,0 +1,203 @@
+use crate::commands::{GlobalArgs, OutputFormat};
+use crate::paths::Paths;
+use crate::run_a_star::*;
+use crate::{
+    get_heuristic_for_pile, get_starting_pile_from_args, print_json, solution_steps,
+    try_get_heuristic_for_pile_with_kind, HeuristicKind, SolveReport, StandardArgs,
+};
+use clap::{Args, ValueEnum};
+use handy_core::game::end_game::GameEndCheckType;
+use handy_core::game::*;
+use handy_core::solver::a_star::{DepthType, LossPruning, SolverBudget};
+use handy_core::solver::ida::IdaSolver;
+use handy_core::solver::*;
+use handy_core::utils::*;
+use std::time::{Duration, Instant};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SolverType {
+    // Finds the shortest win, given enough time and memory
+    #[default]
+    AStar,
+    Ida,
+    // Fast, but the win it finds may not be the shortest
+    Beam,
+}
+
+#[derive(Args, Debug)]
+pub struct SolveArgs {
+    #[clap(flatten)]
+    pub standard_args: StandardArgs,
+
+    #[clap(long, value_enum, default_value_t = SolverType::AStar)]
+    pub solver: SolverType,
+
+    #[clap(long, default_value = "standard")]
+    pub game_end_check_type: GameEndCheckType,
+
+    #[clap(long, value_enum, default_value_t = HeuristicKind::Default)]
+    pub heuristic: HeuristicKind,
+
+    // Approximate memory used by the solver, before it starts dropping states
+    #[clap(long)]
+    pub max_memory_mb: Option<usize>,
+
+    #[clap(long)]
+    pub time_limit_secs: Option<f32>,
+
+    // Added to the fscore, scaled by the model's loss probability
+    #[clap(long, default_value_t = 0.0)]
+    pub loss_penalty: f32,
+
+    // Drop states the model thinks are at least this likely to lose
+    #[clap(long)]
+    pub loss_prune_threshold: Option<f32>,
+
+    #[clap(long, default_value_t = DEFAULT_BEAM_WIDTH)]
+    pub beam_width: usize,
+
+    #[clap(long, default_value_t = DEFAULT_BEAM_MAX_DEPTH)]
+    pub beam_max_depth: DepthType,
+}
+
+fn elapsed_ms(start_time: Instant) -> f32 {
+    start_time.elapsed().as_secs_f32() * 1000.0
+}
+
+fn new_report(
+    solver: SolverType,
+    start_pile: &Pile,
+    path: &[Pile],
+    start_time: Instant,
+) -> SolveReport {
+    SolveReport {
+        // Same name as --solver takes
+        solver: solver.to_possible_value().unwrap().get_name().to_owned(),
+        start_pile: pile_to_string(start_pile),
+        done_reason: None,
+        iterations: None,
+        elapsed_ms: elapsed_ms(start_time),
+        depth: path.len().checked_sub(1),
+        is_optimality_lost: false,
+        steps: solution_steps(path),
+    }
+}
+
+fn run_a_star(
+    paths: &Paths,
+    start_pile: Pile,
+    args: &SolveArgs,
+    format: OutputFormat,
+) -> Result<SolveReport, String> {
+    let options = AStarOptions {
+        g_bias: args.standard_args.g_bias,
+        game_end_check_type: Some(args.game_end_check_type),
+        budget: SolverBudget {
+            max_memory_bytes: args.max_memory_mb.map(|mb| mb << 20),
+            time_limit: args.time_limit_secs.map(Duration::from_secs_f32),
+        },
+        loss_pruning: LossPruning {
+            penalty: args.loss_penalty,
+            prune_threshold: args.loss_prune_threshold,
+        },
+        loss_cache: load_loss_cache(paths, &start_pile, args.game_end_check_type),
+        ..AStarOptions::default()
+    };
+    let model = try_get_heuristic_for_pile_with_kind(paths, &start_pile, args.heuristic)
+        .map_err(|err| format!("Could not build the {:?} heuristic: {err}", args.heuristic))?;
+    let mut a_star_solver = new_a_star_solver_with_model(start_pile.clone(), model, &options);
+
+    let start_time = Instant::now();
+    let result =
+        run_a_star_solver_with_observer(
+            &mut a_star_solver,
+            None,
+            &mut |solver, event| match format {
+                OutputFormat::Text => print_solver_event(solver, &event, true),
+                OutputFormat::Json => write_solver_event_progress(solver, &event, start_time),
+            },
+        );
+
+    Ok(SolveReport {
+        done_reason: Some(result.done_reason),
+        iterations: Some(a_star_solver.total_iters),
+        is_optimality_lost: result.is_optimality_lost,
+        ..new_report(SolverType::AStar, &start_pile, &result.path, start_time)
+    })
+}
+
+fn run_ida(paths: &Paths, start_pile: Pile) -> Result<SolveReport, String> {
+    let matchup = try_get_matchup_from_pile(&start_pile)
+        .ok_or_else(|| format!("No matchup for {start_pile:?}"))?;
+    let model = crate::try_read_model_for_matchup(paths, matchup)?;
+    let mut solver = IdaSolver::new(model);
+
+    let start_time = Instant::now();
+    let path = solver.solve(start_pile.clone()).unwrap_or_default();
+    Ok(SolveReport {
+        iterations: Some(solver.total_iters),
+        is_optimality_lost: solver.is_optimality_lost,
+        ..new_report(SolverType::Ida, &start_pile, &path, start_time)
+    })
+}
+
+fn run_beam(paths: &Paths, start_pile: Pile, args: &SolveArgs) -> Result<SolveReport, String> {
+    let mut beam_solver = BeamSolver::new(get_heuristic_for_pile(paths, &start_pile));
+    beam_solver.set_beam_width(args.beam_width);
+    beam_solver.set_max_depth(args.beam_max_depth);
+    beam_solver.set_game_end_check_type(args.game_end_check_type);
+
+    let start_time = Instant::now();
+    let path = beam_solver
+        .solve(&[start_pile.clone()])
+        .map_or(vec![], |solution| solution.piles);
+    Ok(SolveReport {
+        // The beam drops every state outside it
+        is_optimality_lost: true,
+        ..new_report(SolverType::Beam, &start_pile, &path, start_time)
+    })
+}
+
+fn print_report(report: &SolveReport) {
+    let Some(depth) = report.depth else {
+        println!("No solution found in {:.0}ms", report.elapsed_ms);
+        return;
+    };
+    for step in &report.steps {
+        for event in &step.events {
+            println!("\t{event}");
+        }
+        println!("{:?}", string_to_pile(&step.pile));
+    }
+    println!(
+        "Found solution of length {depth} in {:.0}ms",
+        report.elapsed_ms
+    );
+}
+
+pub fn run(args: &SolveArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    let start_pile = get_starting_pile_from_args(&args.standard_args, global.seed.as_deref());
+    if global.format == OutputFormat::Text {
+        println!("{start_pile:?}");
+    }
+
+    let report = match args.solver {
+        SolverType::AStar => run_a_star(paths, start_pile, args, global.format),
+        SolverType::Ida => run_ida(paths, start_pile),
+        SolverType::Beam => run_beam(paths, start_pile, args),
+    }?;
+
+    match global.format {
+        // A* already printed each new best as it found them
+        OutputFormat::Text if args.solver != SolverType::AStar => print_report(&report),
+        OutputFormat::Text => {}
+        OutputFormat::Json => print_json(&report)?,
+    }
+    Ok(())
+}
+
+// handy solve --pile "3B 102A 2B 101C 6B 8B 4B 1B 9B 5B 7B" --heuristic gated
+// handy solve --classes warrior ogre --max-memory-mb 512 --time-limit-secs 30
+// handy solve --classes warrior ogre --loss-penalty 5 --loss-prune-threshold 0.99
+// handy solve --classes warrior ogre --solver beam --beam-width 100
+// handy solve --classes warrior ogre --format json 2> progress.jsonl
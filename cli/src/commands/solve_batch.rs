@@ -0,0 +1,138 @@
+use crate::commands::{GlobalArgs, OutputFormat};
+use crate::run_a_star::LossCaches;
+use crate::*;
+use clap::Args;
+use csv::Writer;
+use handy_core::game::end_game::GameEndCheckType;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+#[derive(Args, Debug)]
+pub struct SolveBatchArgs {
+    /// One pile per line, or JSON lines like {"pile": "1A 2A", "id": "a", "objective": "standard",
+    /// "max_iters": 1000, "time_limit_secs": 5, "max_memory_mb": 512, "expected_depth": 7}
+    #[clap(long)]
+    pub input: PathBuf,
+    /// Summary file, CSV if it ends in .csv and JSON otherwise
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+    /// For entries without an objective
+    #[clap(long, default_value = "standard")]
+    pub game_end_check_type: GameEndCheckType,
+    #[clap(long, value_enum, default_value_t = HeuristicKind::Default)]
+    pub heuristic: HeuristicKind,
+    /// Per pile, for entries without their own limit
+    #[clap(long, default_value_t = 1_000_000)]
+    pub max_iters: usize,
+    #[clap(long)]
+    pub time_limit_secs: Option<f32>,
+    /// Each thread solves its own pile, so the total can be this times --threads
+    #[clap(long)]
+    pub max_memory_mb: Option<usize>,
+}
+
+fn print_result(result: &BatchResult) {
+    let name = result.id.clone().unwrap_or_else(|| result.pile.clone());
+    if let Some(err) = &result.error {
+        println!("{: >5} {name}: {err}", result.line);
+        return;
+    }
+    println!(
+        "{: >5} {name}: depth {} / completed {} / proven optimal {} / {:?} / {} iters / {:.0}ms{}",
+        result.line,
+        result
+            .depth
+            .map_or("-".to_owned(), |depth| depth.to_string()),
+        result.search_completed,
+        result.proven_optimal,
+        result.done_reason.unwrap(),
+        result.iterations,
+        result.elapsed_ms,
+        match result.matches_expected {
+            Some(false) => format!(" / expected {}", result.expected_depth.unwrap()),
+            _ => "".to_owned(),
+        }
+    );
+}
+
+fn write_csv(results: &[BatchResult], path: &Path) -> Result<(), String> {
+    let mut writer = Writer::from_path(path).map_err(|err| format!("{err}"))?;
+    for result in results {
+        writer.serialize(result).map_err(|err| format!("{err}"))?;
+    }
+    writer.flush().map_err(|err| format!("{err}"))
+}
+
+fn write_summary(results: &[BatchResult], path: &Path) -> Result<(), String> {
+    if path.extension().map_or(false, |ext| ext == "csv") {
+        return write_csv(results, path);
+    }
+    let file = File::create(path).map_err(|err| format!("{err}"))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), results).map_err(|err| format!("{err}"))
+}
+
+pub fn run(args: &SolveBatchArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    let contents = fs::read_to_string(&args.input)
+        .map_err(|err| format!("Could not read {}: {err}", args.input.display()))?;
+    let entries = parse_batch_file(&contents)?;
+    let defaults = BatchDefaults {
+        objective: args.game_end_check_type,
+        max_iters: Some(args.max_iters),
+        time_limit_secs: args.time_limit_secs,
+        max_memory_mb: args.max_memory_mb,
+        heuristic: args.heuristic,
+    };
+    let loss_caches = LossCaches::default();
+
+    let next_entry = AtomicUsize::new(0);
+    let results: Mutex<Vec<BatchResult>> = Mutex::new(Vec::with_capacity(entries.len()));
+    thread::scope(|scope| {
+        for _ in 0..global.num_threads().min(entries.len()) {
+            scope.spawn(|| loop {
+                let Some((line, entry)) = entries.get(next_entry.fetch_add(1, Ordering::SeqCst))
+                else {
+                    break;
+                };
+                let result = solve_batch_entry(paths, &loss_caches, *line, entry, &defaults);
+                if global.format == OutputFormat::Text {
+                    print_result(&result);
+                }
+                results.lock().unwrap().push(result);
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|result| result.line);
+
+    if global.format == OutputFormat::Text {
+        let num_solved = results.iter().filter(|r| r.depth.is_some()).count();
+        let num_completed = results.iter().filter(|r| r.search_completed).count();
+        let num_errors = results.iter().filter(|r| r.error.is_some()).count();
+        let num_mismatches = results
+            .iter()
+            .filter(|r| r.matches_expected == Some(false))
+            .count();
+        println!(
+            "{} piles: {num_solved} solved, {num_completed} searches completed, {num_errors} errors, {num_mismatches} unexpected depths",
+            results.len()
+        );
+    }
+    if let Some(output) = &args.output {
+        write_summary(&results, output)?;
+        if global.format == OutputFormat::Text {
+            println!("Wrote {}", output.display());
+        }
+    }
+    if global.format == OutputFormat::Json {
+        print_json(&results)?;
+    }
+    Ok(())
+}
+
+// handy solve-batch --input puzzles.txt --output summary.csv --threads 8
+// handy solve-batch --input regressions.jsonl --time-limit-secs 30 --format json
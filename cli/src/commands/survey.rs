@@ -0,0 +1,161 @@
+use crate::commands::{GlobalArgs, OutputFormat};
+use crate::run_a_star::LossCaches;
+use crate::*;
+use clap::Args;
+use handy_core::game::end_game::GameEndCheckType;
+use handy_core::game::*;
+use handy_core::solver::model::Matchup;
+use handy_core::utils::pile_to_string;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+#[derive(Args, Debug)]
+pub struct SurveyArgs {
+    /// Defaults to every hero against every monster
+    #[clap(long, value_parser=parse_dot_separated_matchup)]
+    pub matchups: Vec<Matchup>,
+    /// Start piles sampled per matchup. The same seed gives the same piles
+    #[clap(long, default_value_t = 20)]
+    pub piles_per_matchup: usize,
+    /// Per pile. Piles that run out count as unsolved
+    #[clap(long, default_value_t = 200_000)]
+    pub max_iters: usize,
+    #[clap(long)]
+    pub time_limit_secs: Option<f32>,
+    /// Each thread solves its own pile, so the total can be this times --threads
+    #[clap(long)]
+    pub max_memory_mb: Option<usize>,
+    #[clap(long, value_enum, default_value_t = HeuristicKind::Default)]
+    pub heuristic: HeuristicKind,
+    #[clap(long, default_value = "standard")]
+    pub game_end_check_type: GameEndCheckType,
+    /// Defaults to data/evaluations/matchup_survey.json
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+fn format_depth(depth: Option<usize>) -> String {
+    depth.map_or("-".to_owned(), |depth| depth.to_string())
+}
+
+fn print_table(surveys: &[MatchupSurvey]) {
+    println!(
+        "{: <24} {: >6} {: >9} {: >5} {: >6} {: >5} {: >9} {: >11} {: >10}",
+        "matchup", "piles", "win rate", "p10", "median", "p90", "unsolved", "mean iters", "mean ms"
+    );
+    for survey in surveys {
+        println!(
+            "{: <24} {: >6} {: >8.0}% {: >5} {: >6} {: >5} {: >8.0}% {: >11.0} {: >10.0}",
+            survey.matchup,
+            survey.num_piles,
+            survey.win_rate * 100.0,
+            format_depth(survey.p10_depth),
+            format_depth(survey.median_depth),
+            format_depth(survey.p90_depth),
+            survey.unsolved_fraction * 100.0,
+            survey.mean_iterations,
+            survey.mean_elapsed_ms,
+        );
+    }
+}
+
+pub fn run(args: &SurveyArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    let is_text = global.format == OutputFormat::Text;
+    let seed = global.seed_or(DEFAULT_BENCHMARK_SEED);
+    let matchups: Vec<Matchup> = if args.matchups.is_empty() {
+        HEROS
+            .iter()
+            .flat_map(|hero| MONSTERS.iter().map(move |monster| (*hero, *monster)))
+            .collect()
+    } else {
+        args.matchups.clone()
+    };
+
+    // Every pile of every matchup shares the thread pool, so slow matchups don't hold up the rest
+    let jobs: Vec<(usize, BatchEntry)> = matchups
+        .iter()
+        .enumerate()
+        .flat_map(|(matchup_index, matchup)| {
+            benchmark_seed_piles(*matchup, args.piles_per_matchup, &seed)
+                .into_iter()
+                .map(move |pile| {
+                    let entry = BatchEntry {
+                        pile: pile_to_string(&pile),
+                        ..BatchEntry::default()
+                    };
+                    (matchup_index, entry)
+                })
+        })
+        .collect();
+    let defaults = BatchDefaults {
+        objective: args.game_end_check_type,
+        max_iters: Some(args.max_iters),
+        time_limit_secs: args.time_limit_secs,
+        max_memory_mb: args.max_memory_mb,
+        heuristic: args.heuristic,
+    };
+    let loss_caches = LossCaches::default();
+    if is_text {
+        println!(
+            "Solving {} piles from {} matchups",
+            jobs.len(),
+            matchups.len()
+        );
+    }
+
+    let next_job = AtomicUsize::new(0);
+    let results: Mutex<Vec<Vec<BatchResult>>> = Mutex::new(vec![vec![]; matchups.len()]);
+    thread::scope(|scope| {
+        for _ in 0..global.num_threads().min(jobs.len()) {
+            scope.spawn(|| loop {
+                let job_index = next_job.fetch_add(1, Ordering::SeqCst);
+                let Some((matchup_index, entry)) = jobs.get(job_index) else {
+                    break;
+                };
+                let result =
+                    solve_batch_entry(paths, &loss_caches, job_index + 1, entry, &defaults);
+                results.lock().unwrap()[*matchup_index].push(result);
+            });
+        }
+    });
+
+    let mut surveys: Vec<MatchupSurvey> = matchups
+        .iter()
+        .zip(results.into_inner().unwrap())
+        .map(|(matchup, results)| summarize_matchup(*matchup, &results))
+        .collect();
+    sort_hardest_first(&mut surveys);
+    let report = MatchupSurveyReport {
+        seed,
+        piles_per_matchup: args.piles_per_matchup,
+        max_iters: args.max_iters,
+        game_end_check_type: format!("{:?}", args.game_end_check_type),
+        matchups: surveys,
+    };
+
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| paths.matchup_survey_path());
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|err| format!("{err}"))?;
+    }
+    let file = File::create(&output).map_err(|err| format!("{err}"))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), &report).map_err(|err| format!("{err}"))?;
+
+    match global.format {
+        OutputFormat::Text => {
+            print_table(&report.matchups);
+            println!("Wrote {}", output.display());
+        }
+        OutputFormat::Json => print_json(&report)?,
+    }
+    Ok(())
+}
+
+// handy survey --piles-per-matchup 50 --max-iters 1000000 --seed survey
+// handy survey --matchups pyro.demon --matchups monk.ooze --time-limit-secs 10 --format json
@@ -0,0 +1,29 @@
+pub mod blender;
+pub mod linear;
+pub mod mlp;
+pub mod semantic;
+
+use crate::commands::GlobalArgs;
+use crate::paths::Paths;
+use clap::Subcommand;
+
+#[derive(Subcommand, Debug)]
+pub enum TrainCommand {
+    /// Per matchup linear models, optionally with a loss head
+    Linear(linear::TrainLinearArgs),
+    /// Per matchup MLP models
+    Mlp(mlp::TrainMlpArgs),
+    /// One model over semantic features, shared by every matchup
+    Semantic(semantic::TrainSemanticArgs),
+    /// Fit blend weights for the models a matchup already has
+    Blender(blender::TrainBlenderArgs),
+}
+
+pub fn run(command: &TrainCommand, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    match command {
+        TrainCommand::Linear(args) => linear::run(args, global, paths),
+        TrainCommand::Mlp(args) => mlp::run(args, global, paths),
+        TrainCommand::Semantic(args) => semantic::run(args, global, paths),
+        TrainCommand::Blender(args) => blender::run(args, global, paths),
+    }
+}
@@ -0,0 +1,170 @@
+use crate::commands::GlobalArgs;
+use crate::*;
+use clap::Args;
+use handy_core::game::*;
+use handy_core::solver::*;
+use rand_pcg::Pcg64;
+use rand_seeder::Seeder;
+
+#[derive(Args, Debug)]
+pub struct TrainBlenderArgs {
+    #[clap(flatten)]
+    pub matchup_args: TrainArgs,
+    /// Defaults to every member with a model for the matchup
+    #[clap(long, num_args = 0..)]
+    pub members: Vec<String>,
+    #[clap(long, default_value_t = 20_000)]
+    pub max_examples: usize,
+    /// Must match the members' --validation-fraction and --seed. The blend is only fit on the
+    /// piles they held out, since the members' scores on their own training piles are overfit.
+    #[clap(long, default_value_t = 0.1, value_parser = parse_split_fraction)]
+    pub validation_fraction: f32,
+    /// Fraction of those held out piles kept back to report the blend's loss on
+    #[clap(long, default_value_t = 0.2, value_parser = parse_split_fraction)]
+    pub holdout_fraction: f32,
+    #[clap(long, default_value_t = 1.0)]
+    pub l2: f32,
+    /// How much wins that are only an upper bound count, compared to exact ones. 0 leaves them
+    /// out, of the holdout too
+    #[clap(long, default_value_t = DEFAULT_UPPER_BOUND_WEIGHT)]
+    pub upper_bound_weight: f32,
+}
+
+/// Each win with its depth and training weight
+fn read_validation_win_examples(
+    paths: &Paths,
+    matchup: Matchup,
+    seed: &str,
+    validation_fraction: f32,
+    upper_bound_weight: f32,
+) -> Result<Vec<(Pile, f32, f32)>, String> {
+    let mut wins = Vec::new();
+    for example in read_examples_for_matchup(paths, matchup)? {
+        let example = example?;
+        if !is_validation_pile(&example.pile, seed, validation_fraction) {
+            continue;
+        }
+        let weight = label_weight(example.confidence, upper_bound_weight);
+        if let (StateEval::Win(depth), true) = (example.eval, weight > 0.0) {
+            wins.push((example.pile, depth as f32, weight));
+        }
+    }
+    Ok(wins)
+}
+
+fn mean_squared_error(predicted: &[f32], targets: &[f32]) -> f32 {
+    let total: f32 = predicted
+        .iter()
+        .zip(targets)
+        .map(|(p, t)| (p - t) * (p - t))
+        .sum();
+    total / targets.len() as f32
+}
+
+fn train_blender(
+    paths: &Paths,
+    matchup: Matchup,
+    args: &TrainBlenderArgs,
+    seed: &str,
+) -> Result<(), String> {
+    let names: Vec<String> = if args.members.is_empty() {
+        BLEND_MEMBER_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    } else {
+        args.members.clone()
+    };
+    let mut member_names = Vec::new();
+    let mut members = Vec::new();
+    for name in names {
+        match try_get_blend_member(paths, &name, matchup) {
+            Ok(model) => {
+                member_names.push(name);
+                members.push(model);
+            }
+            Err(err) => println!("{matchup:?}: skipping {name}: {err}"),
+        }
+    }
+    if members.is_empty() {
+        return Err("No members to blend".to_owned());
+    }
+
+    let examples = read_validation_win_examples(
+        paths,
+        matchup,
+        seed,
+        args.validation_fraction,
+        args.upper_bound_weight,
+    )?;
+    let mut rng = Seeder::from(seed).make_rng::<Pcg64>();
+    let (mut fit_examples, mut holdout_examples) =
+        split_validation(examples, args.holdout_fraction, &mut rng);
+    fit_examples.truncate(args.max_examples);
+    holdout_examples.truncate(args.max_examples);
+    if fit_examples.is_empty() || holdout_examples.is_empty() {
+        return Err("Not enough examples".to_owned());
+    }
+
+    let score_rows = |examples: &[(Pile, f32, f32)]| -> Vec<Vec<f32>> {
+        examples
+            .iter()
+            .map(|(pile, _, _)| members.iter().map(|m| m.score_pile(pile)).collect())
+            .collect()
+    };
+    let fit_scores = score_rows(&fit_examples);
+    let fit_targets: Vec<f32> = fit_examples.iter().map(|ex| ex.1).collect();
+    let fit_weights: Vec<f32> = fit_examples.iter().map(|ex| ex.2).collect();
+    let (weights, bias) = fit_blend_weights(&fit_scores, &fit_targets, &fit_weights, args.l2)?;
+
+    let holdout_scores = score_rows(&holdout_examples);
+    let holdout_targets: Vec<f32> = holdout_examples.iter().map(|ex| ex.1).collect();
+    for (i, name) in member_names.iter().enumerate() {
+        let predicted: Vec<f32> = holdout_scores.iter().map(|row| row[i]).collect();
+        println!(
+            "{matchup:?}: {name} holdout loss {:.4}",
+            mean_squared_error(&predicted, &holdout_targets)
+        );
+    }
+    let predicted: Vec<f32> = holdout_scores
+        .iter()
+        .map(|row| bias + row.iter().zip(&weights).map(|(s, w)| s * w).sum::<f32>())
+        .collect();
+    let validation_loss = mean_squared_error(&predicted, &holdout_targets);
+    println!(
+        "{matchup:?}: blend holdout loss {validation_loss:.4}, weights {weights:?}, bias {bias:.4}"
+    );
+
+    let blend = BlendWeights {
+        member_names,
+        weights,
+        bias,
+        validation_loss: Some(validation_loss),
+    };
+    write_blend_weights_for_matchup(paths, &blend, matchup);
+    println!("Wrote {}", blend_weights_path_for_matchup(paths, matchup));
+    Ok(())
+}
+
+pub fn run(args: &TrainBlenderArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("train blender")?;
+    let matchups = matchups_from_train_args(args.matchup_args.clone());
+    if matchups.is_empty() {
+        return Err("No matchups, pass --all, --classes or --matchups".to_owned());
+    }
+    // The same default seed as the members, so the held out piles line up
+    let seed = global.seed_or(DEFAULT_VALIDATION_SEED);
+
+    for matchup in matchups {
+        if !has_examples_for_matchup(paths, matchup) {
+            continue;
+        }
+        if let Err(err) = train_blender(paths, matchup, args, &seed) {
+            println!("{matchup:?}: {err}");
+        }
+    }
+    Ok(())
+}
+
+// handy train blender --matchups warrior.ogre
+// handy train blender --all --members linear semantic
@@ -0,0 +1,423 @@
+use crate::commands::GlobalArgs;
+use crate::*;
+use clap::Args;
+use handy_core::game::*;
+use handy_core::solver::*;
+use rand::Rng;
+
+#[derive(Args, Debug)]
+pub struct TrainLinearArgs {
+    #[clap(short, long, num_args = 2)]
+    pub classes: Vec<Class>,
+    /// Retrain every matchup that has training data
+    #[clap(long, action)]
+    pub all: bool,
+    #[clap(long, action)]
+    pub cont: bool,
+    #[clap(short, long)]
+    pub rate: Option<f32>,
+    #[clap(long)]
+    pub suffix: Option<String>,
+    #[clap(long, value_enum, default_value_t = OptimizerType::Adam)]
+    pub optimizer: OptimizerType,
+    #[clap(long, default_value_t = 0.0)]
+    pub l2: f32,
+    #[clap(long, default_value_t = 0.95)]
+    pub lr_decay: f32,
+    #[clap(long, default_value_t = 5000)]
+    pub batch_size: usize,
+    #[clap(long, default_value_t = 200)]
+    pub max_epochs: usize,
+    #[clap(long, default_value_t = 10)]
+    pub patience: usize,
+    /// Piles are held out by a hash seeded with --seed, so the blender can fit on the same ones
+    #[clap(long, default_value_t = 0.1, value_parser = parse_split_fraction)]
+    pub validation_fraction: f32,
+    #[clap(long, action)]
+    pub depth_balanced: bool,
+    /// How much wins that are only an upper bound count, compared to exact ones. 0 leaves them
+    /// out, of validation too
+    #[clap(long, default_value_t = DEFAULT_UPPER_BOUND_WEIGHT)]
+    pub upper_bound_weight: f32,
+    /// Also train a loss head, that classifies lost piles against won ones
+    #[clap(long, action)]
+    pub with_losses: bool,
+}
+
+struct MatchupResult {
+    matchup: Matchup,
+    summary: TrainSummary,
+    existing_validation_loss: Option<f32>,
+    loss_head_summary: Option<TrainSummary>,
+    existing_loss_head_loss: Option<f32>,
+    is_written: bool,
+}
+
+// What every matchup's training shares, built once in run
+struct TrainContext<'a, R: Rng> {
+    args: &'a TrainLinearArgs,
+    config: TrainConfig,
+    seed: String,
+    rng: R,
+}
+
+fn has_all_cards(model: &Model, piles: &[&Pile]) -> bool {
+    piles.iter().all(|pile| {
+        pile.iter().all(|c| {
+            model
+                .try_get_face_features(c.get_card_id() as usize)
+                .is_some()
+        })
+    })
+}
+
+fn train_loss_head<R: Rng>(
+    matchup: Matchup,
+    ctx: &mut TrainContext<R>,
+    relevant_cards: &[CardId],
+    existing_model: Option<&Model>,
+    examples: Vec<(Pile, bool)>,
+) -> Option<(Model, TrainSummary, Option<f32>)> {
+    let num_losses = examples.iter().filter(|(_, is_lost)| *is_lost).count();
+    if num_losses == 0 {
+        println!("{matchup:?}: no loss examples, skipping loss head");
+        return None;
+    }
+    let loss_weight = get_loss_class_weight(examples.len() - num_losses, num_losses);
+
+    let (train_piles, validation_piles) = split_validation_by_pile(
+        examples,
+        &ctx.seed,
+        ctx.config.validation_fraction,
+        |(pile, _)| pile,
+    );
+    let to_training_example = |(pile, is_lost): &(Pile, bool)| TrainingExample {
+        features: training_ex_to_model(pile).vectorize(),
+        target: if *is_lost { 1.0 } else { 0.0 },
+        weight: if *is_lost { loss_weight } else { 1.0 },
+    };
+    let mut train_examples: Vec<TrainingExample> =
+        train_piles.iter().map(to_training_example).collect();
+    let validation_examples: Vec<TrainingExample> =
+        validation_piles.iter().map(to_training_example).collect();
+    if train_examples.is_empty() || validation_examples.is_empty() {
+        println!("{matchup:?}: not enough examples to split, skipping loss head");
+        return None;
+    }
+
+    let existing_head = existing_model.and_then(|model| model.loss_head());
+    let existing_loss_head_loss = existing_head
+        .filter(|head| {
+            has_all_cards(
+                head,
+                &validation_piles.iter().map(|(p, _)| p).collect::<Vec<_>>(),
+            )
+        })
+        .map(|head| {
+            validation_piles
+                .iter()
+                .map(|(pile, is_lost)| {
+                    let target = if *is_lost { 1.0 } else { 0.0 };
+                    binary_cross_entropy(sigmoid(head.score_pile(pile)), target)
+                })
+                .sum::<f32>()
+                / validation_piles.len().max(1) as f32
+        });
+
+    let init_params = match existing_head {
+        Some(head) if ctx.args.cont => head.vectorize(),
+        _ => Model::new_empty_for_cards(relevant_cards).vectorize(),
+    };
+    let (params, summary) = train_logistic(
+        init_params,
+        &mut train_examples,
+        &validation_examples,
+        &ctx.config,
+        &mut ctx.rng,
+        &mut |epoch, train_loss, validation_loss| {
+            println!(
+                "{matchup:?} loss head epoch {epoch}: train {train_loss}, validation {validation_loss}"
+            );
+        },
+    );
+
+    Some((
+        vec_to_model(&params, relevant_cards),
+        summary,
+        existing_loss_head_loss,
+    ))
+}
+
+fn train_matchup<R: Rng>(
+    paths: &Paths,
+    matchup: Matchup,
+    ctx: &mut TrainContext<R>,
+    suffix_str: &str,
+) -> Result<MatchupResult, String> {
+    let mut relevant_cards = get_relevant_cards_for_matchup(matchup);
+    relevant_cards.push(103);
+
+    let existing_model = try_read_model_for_matchup(paths, matchup).ok();
+    let struct_model: Model = if ctx.args.cont {
+        println!("Using existing Model");
+        existing_model
+            .clone()
+            .ok_or("Couldn't load existing model".to_owned())?
+    } else {
+        println!("Using Zeros");
+        Model::new_empty_for_cards(&relevant_cards)
+    };
+
+    println!("Reading examples for {matchup:?}");
+    let all_examples_base = read_all_examples_for_matchup(paths, matchup)?;
+
+    let weights_by_depth = get_weights_by_depth(&all_examples_base);
+    let loss_head_examples: Vec<(Pile, bool)> = all_examples_base
+        .iter()
+        .map(|ex| (ex.pile.clone(), ex.eval == StateEval::Loss))
+        .collect();
+    let win_examples: Vec<(Pile, usize, f32)> = all_examples_base
+        .into_iter()
+        .filter_map(|ex| {
+            let StateEval::Win(depth) = ex.eval else {
+                return None;
+            };
+            let weight = label_weight(ex.confidence, ctx.args.upper_bound_weight);
+            (weight > 0.0).then_some((ex.pile, depth, weight))
+        })
+        .collect();
+    if win_examples.len() < 2 {
+        return Err("Not enough examples".to_owned());
+    }
+
+    let (train_wins, validation_wins) = split_validation_by_pile(
+        win_examples,
+        &ctx.seed,
+        ctx.config.validation_fraction,
+        |ex| &ex.0,
+    );
+    let to_training_example = |(pile, depth, weight): &(Pile, usize, f32)| TrainingExample {
+        features: training_ex_to_model(pile).vectorize(),
+        target: *depth as f32,
+        weight: if ctx.config.is_depth_balanced {
+            weights_by_depth[*depth] * weight
+        } else {
+            *weight
+        },
+    };
+    let mut train_examples: Vec<TrainingExample> =
+        train_wins.iter().map(to_training_example).collect();
+    let validation_examples: Vec<TrainingExample> =
+        validation_wins.iter().map(to_training_example).collect();
+    if train_examples.is_empty() || validation_examples.is_empty() {
+        return Err("Not enough examples".to_owned());
+    }
+
+    // Models are only written if they beat what's already on disk, on the same validation set
+    let existing_validation_loss = existing_model
+        .as_ref()
+        .filter(|model| {
+            has_all_cards(
+                model,
+                &validation_wins
+                    .iter()
+                    .map(|(p, _, _)| p)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .map(|model| {
+            validation_wins
+                .iter()
+                .map(|(pile, depth, _)| {
+                    let diff = model.score_pile(pile) - *depth as f32;
+                    diff * diff
+                })
+                .sum::<f32>()
+                / validation_wins.len() as f32
+        });
+
+    let (params, summary) = train_linear(
+        struct_model.vectorize(),
+        &mut train_examples,
+        &validation_examples,
+        &ctx.config,
+        &mut ctx.rng,
+        &mut |epoch, train_loss, validation_loss| {
+            println!("{matchup:?} epoch {epoch}: train {train_loss}, validation {validation_loss}");
+        },
+    );
+
+    let loss_head_result = if ctx.args.with_losses {
+        train_loss_head(
+            matchup,
+            ctx,
+            &relevant_cards,
+            existing_model.as_ref(),
+            loss_head_examples,
+        )
+    } else {
+        None
+    };
+
+    let is_forced = !suffix_str.is_empty();
+    let is_depth_improved = is_forced
+        || existing_validation_loss.map_or(true, |loss| summary.best_validation_loss < loss);
+    let is_loss_head_improved =
+        loss_head_result
+            .as_ref()
+            .map_or(false, |(_, head_summary, existing_loss)| {
+                is_forced
+                    || existing_loss.map_or(true, |loss| head_summary.best_validation_loss < loss)
+            });
+
+    let is_written = is_depth_improved || is_loss_head_improved;
+    if is_written {
+        let mut result_model = if is_depth_improved {
+            let mut new_model = vec_to_model(&params, &relevant_cards);
+            new_model.set_loss_head(
+                existing_model
+                    .as_ref()
+                    .and_then(|model| model.loss_head())
+                    .cloned(),
+            );
+            new_model
+        } else {
+            existing_model.clone().unwrap()
+        };
+        if is_loss_head_improved {
+            let (loss_head, _, _) = loss_head_result.as_ref().unwrap();
+            result_model.set_loss_head(Some(loss_head.clone()));
+        }
+        let metadata = ModelMetadata {
+            matchup: Some(matchup_to_str(matchup)),
+            training_data: Some(TrainingDataStats {
+                num_train_examples: summary.num_train_examples,
+                num_validation_examples: summary.num_validation_examples,
+                num_loss_head_examples: loss_head_result.as_ref().map(|(_, head_summary, _)| {
+                    head_summary.num_train_examples + head_summary.num_validation_examples
+                }),
+            }),
+            validation_loss: if is_depth_improved {
+                Some(summary.best_validation_loss)
+            } else {
+                existing_validation_loss
+            },
+            created_at: Some(get_datetime_stamp()),
+            ..ModelMetadata::default()
+        };
+        write_model_envelope_for_matchup_with_custom_suffix(
+            paths,
+            &ModelEnvelope::new(result_model, metadata),
+            matchup,
+            suffix_str,
+        );
+    }
+
+    let (loss_head_summary, existing_loss_head_loss) = match loss_head_result {
+        Some((_, head_summary, existing_loss)) => (Some(head_summary), existing_loss),
+        None => (None, None),
+    };
+
+    Ok(MatchupResult {
+        matchup,
+        summary,
+        existing_validation_loss,
+        loss_head_summary,
+        existing_loss_head_loss,
+        is_written,
+    })
+}
+
+fn print_results(results: &[MatchupResult]) {
+    println!(
+        "{: <30} {: >8} {: >12} {: >12} {: >12} {: >7} {: >12} {: >12} {: >8}",
+        "matchup",
+        "examples",
+        "existing",
+        "init",
+        "best",
+        "epochs",
+        "head exist",
+        "head best",
+        "written"
+    );
+    for result in results {
+        let summary = &result.summary;
+        println!(
+            "{: <30} {: >8} {: >12} {: >12.4} {: >12.4} {: >7} {: >12} {: >12} {: >8}",
+            format!("{:?}", result.matchup),
+            summary.num_train_examples + summary.num_validation_examples,
+            result
+                .existing_validation_loss
+                .map_or("-".to_owned(), |loss| format!("{loss:.4}")),
+            summary.init_validation_loss,
+            summary.best_validation_loss,
+            summary.epochs_run,
+            result
+                .existing_loss_head_loss
+                .map_or("-".to_owned(), |loss| format!("{loss:.4}")),
+            result
+                .loss_head_summary
+                .as_ref()
+                .map_or("-".to_owned(), |head| format!(
+                    "{:.4}",
+                    head.best_validation_loss
+                )),
+            result.is_written,
+        );
+    }
+}
+
+pub fn run(args: &TrainLinearArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("train linear")?;
+    let matchups: Vec<Matchup> = if args.all {
+        HEROS
+            .iter()
+            .flat_map(|hero| MONSTERS.iter().map(move |monster| (*hero, *monster)))
+            .collect()
+    } else {
+        vec![try_get_matchup_from_classes(&args.classes)
+            .ok_or("Pass --all, or --classes with a hero and a monster")?]
+    };
+
+    let config = TrainConfig {
+        optimizer_type: args.optimizer,
+        learning_rate: args.rate.unwrap_or(args.optimizer.default_learning_rate()),
+        learning_rate_decay: args.lr_decay,
+        l2: args.l2,
+        batch_size: args.batch_size,
+        max_epochs: args.max_epochs,
+        patience: args.patience,
+        validation_fraction: args.validation_fraction,
+        is_depth_balanced: args.depth_balanced,
+    };
+    println!("{config:?}");
+
+    let suffix_str: String = args.suffix.clone().unwrap_or_default();
+    let mut ctx = TrainContext {
+        args,
+        config,
+        seed: global.seed_or(DEFAULT_VALIDATION_SEED),
+        rng: global.rng(),
+    };
+
+    let mut results = Vec::new();
+    for matchup in matchups {
+        // Continuing from zeros would silently throw away the old model
+        if args.cont && has_examples_for_matchup(paths, matchup) {
+            try_read_model_for_matchup(paths, matchup)
+                .map_err(|err| format!("{matchup:?}: --cont needs the existing model: {err}"))?;
+        }
+        match train_matchup(paths, matchup, &mut ctx, &suffix_str) {
+            Ok(result) => results.push(result),
+            Err(err) => println!("Skipping {matchup:?}: {err}"),
+        }
+    }
+
+    print_results(&results);
+    Ok(())
+}
+
+// handy train linear --classes warrior ogre --depth-balanced
+// handy train linear --all
+// handy train linear --classes warrior ogre --with-losses
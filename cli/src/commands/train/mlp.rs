@@ -0,0 +1,112 @@
+use crate::commands::GlobalArgs;
+use crate::*;
+use clap::Args;
+use handy_core::game::*;
+use handy_core::solver::*;
+use rand::seq::SliceRandom;
+
+// The same as train linear's default, so the blender fits both on the same held out piles
+const VALIDATION_FRACTION: f32 = 0.1;
+
+#[derive(Args, Debug)]
+pub struct TrainMlpArgs {
+    #[clap(short, long, num_args = 2)]
+    pub classes: Vec<Class>,
+    #[clap(long, action)]
+    pub cont: bool,
+    #[clap(short, long, default_value_t = 0.001)]
+    pub rate: f32,
+    #[clap(long, default_value_t = DEFAULT_MLP_HIDDEN_SIZE)]
+    pub hidden_size: usize,
+    #[clap(long, default_value_t = 20)]
+    pub epochs: usize,
+    /// How much wins that are only an upper bound count, compared to exact ones. 0 leaves them
+    /// out, of validation too
+    #[clap(long, default_value_t = DEFAULT_UPPER_BOUND_WEIGHT)]
+    pub upper_bound_weight: f32,
+}
+
+// Unweighted, so it's comparable between models
+fn mean_squared_error(model: &dyn ModelT, examples: &[(Pile, f32, f32)]) -> f32 {
+    let total: f32 = examples
+        .iter()
+        .map(|(pile, target, _)| {
+            let diff = model.score_pile(pile) - target;
+            diff * diff
+        })
+        .sum();
+    total / examples.len() as f32
+}
+
+pub fn run(args: &TrainMlpArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("train mlp")?;
+    let matchup = try_get_matchup_from_classes(&args.classes)
+        .ok_or("Pass --classes with a hero and a monster")?;
+    let mut relevant_cards = get_relevant_cards_for_matchup(matchup);
+    relevant_cards.push(103);
+
+    println!("Reading examples for {matchup:?}");
+    let mut examples: Vec<(Pile, f32, f32)> = Vec::new();
+    for ex in read_examples_for_matchup(paths, matchup)? {
+        let ex = ex?;
+        let weight = label_weight(ex.confidence, args.upper_bound_weight);
+        if let (StateEval::Win(depth), true) = (ex.eval, weight > 0.0) {
+            examples.push((ex.pile, depth as f32, weight));
+        }
+    }
+
+    let mut rng = global.rng();
+    let seed = global.seed_or(DEFAULT_VALIDATION_SEED);
+    let (mut train_examples, validation_examples) =
+        split_validation_by_pile(examples, &seed, VALIDATION_FRACTION, |ex| &ex.0);
+    if validation_examples.is_empty() || train_examples.is_empty() {
+        return Err("Not enough examples".to_owned());
+    }
+    println!(
+        "Training on {} examples, validating on {}",
+        train_examples.len(),
+        validation_examples.len()
+    );
+
+    let mut model = if args.cont {
+        println!("Using existing MLP model");
+        try_read_mlp_model_for_matchup(paths, matchup)
+            .map_err(|err| format!("Couldn't load existing model: {err}"))?
+    } else {
+        let mut model = MlpModel::new_random_for_cards(&relevant_cards, args.hidden_size, &mut rng);
+        model.output_bias =
+            train_examples.iter().map(|ex| ex.1).sum::<f32>() / train_examples.len() as f32;
+        model
+    };
+
+    if let Ok(linear_model) = try_read_model_for_matchup(paths, matchup) {
+        println!(
+            "Linear model validation loss: {}",
+            mean_squared_error(&linear_model, &validation_examples)
+        );
+    }
+
+    let mut best_validation_loss = mean_squared_error(&model, &validation_examples);
+    println!("Init validation loss: {best_validation_loss}");
+
+    for epoch in 0..args.epochs {
+        train_examples.shuffle(&mut rng);
+        let train_loss: f32 = train_examples
+            .iter()
+            .map(|(pile, target, weight)| model.train_example(pile, *target, args.rate * weight))
+            .sum::<f32>()
+            / train_examples.len() as f32;
+
+        let validation_loss = mean_squared_error(&model, &validation_examples);
+        println!("Epoch {epoch}: train loss {train_loss}, validation loss {validation_loss}");
+
+        if validation_loss < best_validation_loss {
+            best_validation_loss = validation_loss;
+            println!("Writing out model with validation loss: {matchup:?} {validation_loss}");
+            write_mlp_model_for_matchup(paths, &model, matchup);
+        }
+    }
+    Ok(())
+}
+
+// handy train mlp --classes warrior ogre
@@ -0,0 +1,219 @@
+use crate::commands::GlobalArgs;
+use crate::*;
+use clap::Args;
+use handy_core::game::*;
+use handy_core::solver::*;
+use rand::seq::SliceRandom;
+use rand_pcg::Pcg64;
+use rand_seeder::Seeder;
+
+#[derive(Args, Debug)]
+pub struct TrainSemanticArgs {
+    /// Matchups that are only evaluated, to check how well the model generalizes
+    #[clap(long, value_parser=parse_dot_separated_matchup)]
+    pub holdout: Vec<Matchup>,
+    /// Keeps matchups with lots of data from drowning out the others
+    #[clap(long, default_value_t = 50_000)]
+    pub max_examples_per_matchup: usize,
+    #[clap(long, action)]
+    pub cont: bool,
+    /// Write the model even if it's worse than the existing one
+    #[clap(long, action)]
+    pub force: bool,
+    #[clap(short, long)]
+    pub rate: Option<f32>,
+    #[clap(long, value_enum, default_value_t = OptimizerType::Adam)]
+    pub optimizer: OptimizerType,
+    #[clap(long, default_value_t = 0.0)]
+    pub l2: f32,
+    #[clap(long, default_value_t = 5000)]
+    pub batch_size: usize,
+    #[clap(long, default_value_t = 200)]
+    pub max_epochs: usize,
+    #[clap(long, default_value_t = 10)]
+    pub patience: usize,
+    #[clap(long, default_value_t = 0.1, value_parser = parse_split_fraction)]
+    pub validation_fraction: f32,
+    /// How much wins that are only an upper bound count, compared to exact ones. 0 leaves them
+    /// out, of validation too
+    #[clap(long, default_value_t = DEFAULT_UPPER_BOUND_WEIGHT)]
+    pub upper_bound_weight: f32,
+}
+
+struct MatchupData {
+    matchup: Matchup,
+    is_holdout: bool,
+    validation_wins: Vec<(Pile, usize, f32)>,
+}
+
+fn read_win_examples(
+    paths: &Paths,
+    matchup: Matchup,
+    upper_bound_weight: f32,
+) -> Option<Vec<(Pile, usize, f32)>> {
+    let examples = read_all_examples_for_matchup(paths, matchup).ok()?;
+
+    Some(
+        examples
+            .into_iter()
+            .filter_map(|ex| {
+                let StateEval::Win(depth) = ex.eval else {
+                    return None;
+                };
+                let weight = label_weight(ex.confidence, upper_bound_weight);
+                (weight > 0.0).then_some((ex.pile, depth, weight))
+            })
+            .collect(),
+    )
+}
+
+fn to_training_example((pile, depth, weight): &(Pile, usize, f32)) -> TrainingExample {
+    TrainingExample {
+        features: semantic_features(pile),
+        target: *depth as f32,
+        weight: *weight,
+    }
+}
+
+fn mean_squared_error(model: &dyn ModelT, examples: &[(Pile, usize, f32)]) -> f32 {
+    let total: f32 = examples
+        .iter()
+        .map(|(pile, depth, _)| {
+            let diff = model.score_pile(pile) - *depth as f32;
+            diff * diff
+        })
+        .sum();
+    total / examples.len().max(1) as f32
+}
+
+pub fn run(args: &TrainSemanticArgs, global: &GlobalArgs, paths: &Paths) -> Result<(), String> {
+    global.require_text_format("train semantic")?;
+    let mut rng = global.rng();
+    let seed = global.seed_or(DEFAULT_VALIDATION_SEED);
+
+    let mut all_data: Vec<MatchupData> = Vec::new();
+    let mut train_examples: Vec<TrainingExample> = Vec::new();
+    let mut validation_examples: Vec<TrainingExample> = Vec::new();
+    for hero in HEROS {
+        for monster in MONSTERS {
+            let matchup = (hero, monster);
+            let Some(mut wins) = read_win_examples(paths, matchup, args.upper_bound_weight) else {
+                continue;
+            };
+            if wins.len() < 2 {
+                continue;
+            }
+
+            // Seeded, so every run with the same data keeps the same subset
+            let mut subset_rng =
+                Seeder::from((seed.as_str(), matchup_to_str(matchup))).make_rng::<Pcg64>();
+            wins.shuffle(&mut subset_rng);
+            wins.truncate(args.max_examples_per_matchup);
+            let is_holdout = args.holdout.contains(&matchup);
+            println!(
+                "{matchup:?}: {} examples, holdout: {is_holdout}",
+                wins.len()
+            );
+
+            let (train_wins, validation_wins) = if is_holdout {
+                (vec![], wins)
+            } else {
+                split_validation_by_pile(wins, &seed, args.validation_fraction, |ex| &ex.0)
+            };
+
+            train_examples.extend(train_wins.iter().map(to_training_example));
+            if !is_holdout {
+                validation_examples.extend(validation_wins.iter().map(to_training_example));
+            }
+            all_data.push(MatchupData {
+                matchup,
+                is_holdout,
+                validation_wins,
+            });
+        }
+    }
+
+    if train_examples.is_empty() || validation_examples.is_empty() {
+        return Err("Not enough examples".to_owned());
+    }
+
+    let existing_model = if args.cont {
+        Some(
+            try_read_semantic_model(paths)
+                .map_err(|err| format!("--cont needs the model: {err}"))?,
+        )
+    } else {
+        try_read_semantic_model(paths).ok()
+    };
+    let init_params = match &existing_model {
+        Some(model) if args.cont => model.weights.clone(),
+        _ => SemanticModel::new_empty().weights,
+    };
+
+    let config = TrainConfig {
+        optimizer_type: args.optimizer,
+        learning_rate: args.rate.unwrap_or(args.optimizer.default_learning_rate()),
+        l2: args.l2,
+        batch_size: args.batch_size,
+        max_epochs: args.max_epochs,
+        patience: args.patience,
+        validation_fraction: args.validation_fraction,
+        ..TrainConfig::default()
+    };
+    println!("{config:?}");
+
+    let (params, summary) = train_linear(
+        init_params,
+        &mut train_examples,
+        &validation_examples,
+        &config,
+        &mut rng,
+        &mut |epoch, train_loss, validation_loss| {
+            println!("epoch {epoch}: train {train_loss}, validation {validation_loss}");
+        },
+    );
+    let model = SemanticModel::from_weights(params).unwrap();
+    println!("{summary:?}");
+
+    println!(
+        "{: <30} {: >8} {: >12} {: >12} {: >12}",
+        "matchup", "holdout", "semantic", "existing", "matchup"
+    );
+    for data in &all_data {
+        let matchup_model_loss = try_read_model_for_matchup(paths, data.matchup)
+            .ok()
+            .map(|model| mean_squared_error(&model, &data.validation_wins));
+        println!(
+            "{: <30} {: >8} {: >12.4} {: >12} {: >12}",
+            format!("{:?}", data.matchup),
+            data.is_holdout,
+            mean_squared_error(&model, &data.validation_wins),
+            existing_model
+                .as_ref()
+                .map_or("-".to_owned(), |existing| format!(
+                    "{:.4}",
+                    mean_squared_error(existing, &data.validation_wins)
+                )),
+            matchup_model_loss.map_or("-".to_owned(), |loss| format!("{loss:.4}")),
+        );
+    }
+
+    let existing_validation_loss = existing_model
+        .as_ref()
+        .map(|existing| linear_loss(&existing.weights, &validation_examples));
+    let is_better =
+        existing_validation_loss.map_or(true, |loss| summary.best_validation_loss < loss);
+    if is_better || args.force {
+        println!("Writing {}", semantic_model_path(paths));
+        write_semantic_model(paths, &model);
+    } else {
+        println!(
+            "Not writing, existing model is better: {:?}",
+            existing_validation_loss
+        );
+    }
+    Ok(())
+}
+
+// handy train semantic
+// handy train semantic --holdout warrior.ogre
@@ -0,0 +1,147 @@
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Overrides ./data, see handy --data-dir
+pub const DATA_DIR_ENV_VAR: &str = "HANDY_DATA_DIR";
+// Model directories searched before the data dir's, separated like $PATH. See handy --models-dir
+pub const MODEL_PATH_ENV_VAR: &str = "HANDY_MODEL_PATH";
+// Overrides ./handy.yaml, see handy --config
+pub const CONFIG_ENV_VAR: &str = "HANDY_CONFIG";
+
+pub const DEFAULT_CONFIG_PATH: &str = "./handy.yaml";
+pub const DEFAULT_DATA_DIR: &str = "./data";
+
+/// Settings read from handy.yaml. Flags and environment variables take precedence.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HandyConfig {
+    #[serde(default)]
+    pub data_dir: Option<PathBuf>,
+    // Searched in order, before the data dir's models
+    #[serde(default)]
+    pub model_dirs: Vec<PathBuf>,
+}
+
+impl HandyConfig {
+    /// Relative paths in a config file are relative to the file, not to where handy runs
+    fn relative_to(mut self, config_dir: &Path) -> Self {
+        self.data_dir = self.data_dir.map(|dir| config_dir.join(dir));
+        self.model_dirs = self
+            .model_dirs
+            .into_iter()
+            .map(|dir| config_dir.join(dir))
+            .collect();
+        self
+    }
+}
+
+pub fn try_read_config(path: &Path) -> Result<HandyConfig, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Could not read config {}: {err}", path.display()))?;
+    let config: HandyConfig = serde_yaml::from_str(&contents)
+        .map_err(|err| format!("Could not parse config {}: {err}", path.display()))?;
+    Ok(config.relative_to(path.parent().unwrap_or(Path::new("."))))
+}
+
+/// The --config flag, then $HANDY_CONFIG, then ./handy.yaml. A named config has to exist, the
+/// default one doesn't.
+pub fn try_read_config_from_flag_or_env(flag: Option<&Path>) -> Result<HandyConfig, String> {
+    let named = flag
+        .map(PathBuf::from)
+        .or_else(|| env::var_os(CONFIG_ENV_VAR).map(PathBuf::from));
+    match named {
+        Some(path) => try_read_config(&path),
+        None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+            try_read_config(Path::new(DEFAULT_CONFIG_PATH))
+        }
+        None => Ok(HandyConfig::default()),
+    }
+}
+
+/// The flag or environment variable, then the config file, then ./data
+pub fn resolve_data_dir(override_dir: Option<PathBuf>, config: &HandyConfig) -> PathBuf {
+    match (override_dir, &config.data_dir) {
+        (Some(dir), _) => dir,
+        (None, Some(dir)) => dir.clone(),
+        (None, None) => DEFAULT_DATA_DIR.into(),
+    }
+}
+
+/// The flag or environment variable's dirs, then the config file's, then the data dir's models
+pub fn resolve_model_dirs(
+    override_dirs: Vec<PathBuf>,
+    config: &HandyConfig,
+    default_models_dir: &Path,
+) -> Vec<PathBuf> {
+    let mut dirs = override_dirs;
+    dirs.extend(config.model_dirs.iter().cloned());
+    dirs.push(default_models_dir.to_owned());
+    dirs.dedup();
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_config() {
+        let dir = env::temp_dir().join("handy_config_test");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("handy.yaml");
+        fs::write(&path, "data_dir: data\nmodel_dirs: [/opt/models, extra]\n").unwrap();
+        let config = try_read_config(&path).unwrap();
+        assert_eq!(config.data_dir, Some(dir.join("data")));
+        assert_eq!(
+            config.model_dirs,
+            vec![PathBuf::from("/opt/models"), dir.join("extra")]
+        );
+
+        fs::write(&path, "models_dir: typo\n").unwrap();
+        let err = try_read_config(&path).unwrap_err();
+        assert!(err.starts_with("Could not parse config"), "{err}");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(try_read_config(&dir.join("missing.yaml")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_dirs() {
+        let config = HandyConfig {
+            data_dir: Some("/config/data".into()),
+            model_dirs: vec!["/config/models".into()],
+        };
+        assert_eq!(
+            resolve_data_dir(Some("/env/data".into()), &config),
+            PathBuf::from("/env/data")
+        );
+        assert_eq!(
+            resolve_data_dir(None, &config),
+            PathBuf::from("/config/data")
+        );
+        assert_eq!(
+            resolve_data_dir(None, &HandyConfig::default()),
+            PathBuf::from(DEFAULT_DATA_DIR)
+        );
+
+        assert_eq!(
+            resolve_model_dirs(
+                vec!["/a".into(), "/b".into()],
+                &config,
+                Path::new("/data/models")
+            ),
+            vec![
+                PathBuf::from("/a"),
+                PathBuf::from("/b"),
+                PathBuf::from("/config/models"),
+                PathBuf::from("/data/models"),
+            ]
+        );
+        assert_eq!(
+            resolve_model_dirs(vec![], &HandyConfig::default(), Path::new("/data/models")),
+            vec![PathBuf::from("/data/models")]
+        );
+    }
+}
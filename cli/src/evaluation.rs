@@ -0,0 +1,319 @@
+use handy_core::game::*;
+use handy_core::solver::a_star::*;
+use handy_core::solver::tiny_pile::NoopPileStorageConverter;
+use handy_core::solver::ModelT;
+use handy_core::utils::get_start_from_classes;
+use rand_pcg::Pcg64;
+use rand_seeder::Seeder;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+pub const DEFAULT_BENCHMARK_SEED: &str = "benchmark";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DepthBucketError {
+    pub depth: usize,
+    pub num_examples: usize,
+    pub mse: f32,
+    // Positive when the model overestimates on average
+    pub mean_error: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PredictionMetrics {
+    pub num_examples: usize,
+    pub mse: f32,
+    pub mse_by_depth: Vec<DepthBucketError>,
+    // Spearman correlation between the predictions and the true depths
+    pub rank_correlation: f32,
+    pub overestimate_fraction: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SeedSolveResult {
+    pub first_depth: Option<DepthType>,
+    pub iters_to_first: Option<usize>,
+    pub ms_to_first: Option<f32>,
+    pub best_depth: Option<DepthType>,
+    pub iters_to_best: Option<usize>,
+    pub ms_to_best: Option<f32>,
+    // The search ran out of states without dropping any. The best depth is only optimal if the
+    // model never overestimates, which trained models don't promise.
+    pub search_completed: bool,
+    pub total_iters: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SolverMetrics {
+    pub num_seeds: usize,
+    pub num_solved: usize,
+    pub num_search_completed: usize,
+    pub mean_iters_to_first: Option<f32>,
+    pub mean_ms_to_first: Option<f32>,
+    // Only over seeds where the search completed, so the best solution is the last one found
+    pub mean_iters_to_best_completed: Option<f32>,
+    pub mean_ms_to_best_completed: Option<f32>,
+    pub mean_best_depth: Option<f32>,
+    pub seeds: Vec<SeedSolveResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MatchupEvaluation {
+    pub matchup: String,
+    pub predictions: Option<PredictionMetrics>,
+    pub solver: Option<SolverMetrics>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EvaluationReport {
+    pub models_dir: String,
+    pub benchmark_seed: String,
+    pub matchups: Vec<MatchupEvaluation>,
+}
+
+fn mean(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        None
+    } else {
+        Some(sum / count as f32)
+    }
+}
+
+// Ties share the average of the ranks they span
+fn average_ranks(values: &[f32]) -> Vec<f32> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end - 1) as f32 / 2.0;
+        for idx in &order[start..end] {
+            ranks[*idx] = rank;
+        }
+        start = end;
+    }
+
+    ranks
+}
+
+fn pearson_correlation(xs: &[f32], ys: &[f32]) -> f32 {
+    let (Some(x_mean), Some(y_mean)) = (mean(xs.iter().copied()), mean(ys.iter().copied())) else {
+        return 0.0;
+    };
+
+    let mut covariance = 0.0;
+    let mut x_variance = 0.0;
+    let mut y_variance = 0.0;
+    for (x, y) in xs.iter().zip(ys) {
+        covariance += (x - x_mean) * (y - y_mean);
+        x_variance += (x - x_mean) * (x - x_mean);
+        y_variance += (y - y_mean) * (y - y_mean);
+    }
+
+    if x_variance == 0.0 || y_variance == 0.0 {
+        0.0
+    } else {
+        covariance / (x_variance * y_variance).sqrt()
+    }
+}
+
+pub fn rank_correlation(xs: &[f32], ys: &[f32]) -> f32 {
+    assert_eq!(xs.len(), ys.len());
+    pearson_correlation(&average_ranks(xs), &average_ranks(ys))
+}
+
+/// Takes (prediction, true depth) pairs
+pub fn prediction_metrics(predictions: &[(f32, usize)]) -> PredictionMetrics {
+    let mut buckets: Vec<(usize, f32, f32)> = Vec::new();
+    for (prediction, depth) in predictions {
+        while *depth >= buckets.len() {
+            buckets.push((0, 0.0, 0.0));
+        }
+        let error = prediction - *depth as f32;
+        let bucket = &mut buckets[*depth];
+        bucket.0 += 1;
+        bucket.1 += error * error;
+        bucket.2 += error;
+    }
+
+    let mse_by_depth = buckets
+        .iter()
+        .enumerate()
+        .filter(|(_, (count, _, _))| *count > 0)
+        .map(|(depth, (count, squared_error, error))| DepthBucketError {
+            depth,
+            num_examples: *count,
+            mse: squared_error / *count as f32,
+            mean_error: error / *count as f32,
+        })
+        .collect();
+
+    let predicted: Vec<f32> = predictions.iter().map(|(p, _)| *p).collect();
+    let actual: Vec<f32> = predictions.iter().map(|(_, d)| *d as f32).collect();
+
+    PredictionMetrics {
+        num_examples: predictions.len(),
+        mse: mean(
+            predictions
+                .iter()
+                .map(|(p, d)| (p - *d as f32) * (p - *d as f32)),
+        )
+        .unwrap_or(0.0),
+        mse_by_depth,
+        rank_correlation: rank_correlation(&predicted, &actual),
+        overestimate_fraction: mean(
+            predictions
+                .iter()
+                .map(|(p, d)| if *p > *d as f32 { 1.0 } else { 0.0 }),
+        )
+        .unwrap_or(0.0),
+    }
+}
+
+/// The same start piles every run, so different models are compared on the same seeds
+pub fn benchmark_seed_piles(matchup: (Class, Class), num_seeds: usize, seed: &str) -> Vec<Pile> {
+    let mut rng = Seeder::from(seed).make_rng::<Pcg64>();
+    (0..num_seeds)
+        .map(|_| get_start_from_classes(matchup.0, matchup.1, &mut rng))
+        .collect()
+}
+
+pub fn benchmark_seed(pile: &Pile, model: Box<dyn ModelT>, max_iters: usize) -> SeedSolveResult {
+    let start_time = Instant::now();
+    let mut solver = AStarSolver::<Pile, NoopPileStorageConverter>::new(&[pile.clone()], model);
+    solver.set_max_iters(max_iters);
+
+    let mut result = SeedSolveResult {
+        first_depth: None,
+        iters_to_first: None,
+        ms_to_first: None,
+        best_depth: None,
+        iters_to_best: None,
+        ms_to_best: None,
+        search_completed: false,
+        total_iters: 0,
+    };
+
+    loop {
+        match solver.single_iter() {
+            AStarIterResult::Done(reason) => {
+                result.search_completed = !solver.is_optimality_lost
+                    && matches!(
+                        reason,
+                        AStarDoneReason::EmptyQueue | AStarDoneReason::FScoreCutoff
+                    );
+                break;
+            }
+            AStarIterResult::NewBest(_) => {
+                let ms = start_time.elapsed().as_secs_f32() * 1000.0;
+                if result.first_depth.is_none() {
+                    result.first_depth = Some(solver.max_depth);
+                    result.iters_to_first = Some(solver.total_iters);
+                    result.ms_to_first = Some(ms);
+                }
+                result.best_depth = Some(solver.max_depth);
+                result.iters_to_best = Some(solver.total_iters);
+                result.ms_to_best = Some(ms);
+            }
+            AStarIterResult::Continue(_) => (),
+        }
+    }
+    result.total_iters = solver.total_iters.min(max_iters);
+
+    result
+}
+
+pub fn solver_metrics(seeds: Vec<SeedSolveResult>) -> SolverMetrics {
+    let completed: Vec<&SeedSolveResult> = seeds
+        .iter()
+        .filter(|s| s.search_completed && s.best_depth.is_some())
+        .collect();
+
+    SolverMetrics {
+        num_seeds: seeds.len(),
+        num_solved: seeds.iter().filter(|s| s.best_depth.is_some()).count(),
+        num_search_completed: seeds.iter().filter(|s| s.search_completed).count(),
+        mean_iters_to_first: mean(
+            seeds
+                .iter()
+                .filter_map(|s| s.iters_to_first.map(|i| i as f32)),
+        ),
+        mean_ms_to_first: mean(seeds.iter().filter_map(|s| s.ms_to_first)),
+        mean_iters_to_best_completed: mean(
+            completed
+                .iter()
+                .filter_map(|s| s.iters_to_best.map(|i| i as f32)),
+        ),
+        mean_ms_to_best_completed: mean(completed.iter().filter_map(|s| s.ms_to_best)),
+        mean_best_depth: mean(seeds.iter().filter_map(|s| s.best_depth.map(|d| d as f32))),
+        seeds,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handy_core::solver::model::Model;
+    use handy_core::utils::string_to_pile;
+
+    #[test]
+    fn test_rank_correlation() {
+        let xs = [1.0, 2.0, 3.0, 4.0];
+        assert!((rank_correlation(&xs, &[10.0, 20.0, 30.0, 100.0]) - 1.0).abs() < 0.0001);
+        assert!((rank_correlation(&xs, &[4.0, 3.0, 2.0, 1.0]) + 1.0).abs() < 0.0001);
+        assert_eq!(rank_correlation(&xs, &[1.0, 1.0, 1.0, 1.0]), 0.0);
+        assert_eq!(average_ranks(&[5.0, 1.0, 5.0]), vec![1.5, 0.0, 1.5]);
+    }
+
+    #[test]
+    fn test_prediction_metrics() {
+        let metrics = prediction_metrics(&[(1.0, 1), (3.0, 1), (3.0, 3), (2.0, 3)]);
+
+        assert_eq!(metrics.num_examples, 4);
+        assert!((metrics.mse - 5.0 / 4.0).abs() < 0.0001);
+        assert_eq!(metrics.overestimate_fraction, 0.25);
+        assert_eq!(metrics.mse_by_depth.len(), 2);
+        assert_eq!(metrics.mse_by_depth[0].depth, 1);
+        assert_eq!(metrics.mse_by_depth[0].mse, 2.0);
+        assert_eq!(metrics.mse_by_depth[0].mean_error, 1.0);
+        assert_eq!(metrics.mse_by_depth[1].mean_error, -0.5);
+    }
+
+    #[test]
+    fn test_benchmark_seed() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let model = Model::new_empty_for_pile(&pile);
+
+        let result = benchmark_seed(&pile, Box::new(model.clone()), usize::MAX);
+        assert!(result.search_completed);
+        assert!(result.best_depth.is_some());
+        assert!(result.first_depth >= result.best_depth);
+        assert!(result.iters_to_first <= result.iters_to_best);
+
+        let metrics = solver_metrics(vec![result.clone(), result]);
+        assert_eq!(metrics.num_solved, 2);
+        assert_eq!(
+            metrics.mean_best_depth,
+            metrics.seeds[0].best_depth.map(|d| d as f32)
+        );
+
+        let cut_short = benchmark_seed(&pile, Box::new(model), 1);
+        assert!(!cut_short.search_completed);
+        assert_eq!(cut_short.total_iters, 1);
+    }
+
+    #[test]
+    fn test_benchmark_seeds_are_fixed() {
+        let matchup = (Class::Warrior, Class::Ogre);
+        assert_eq!(
+            benchmark_seed_piles(matchup, 3, DEFAULT_BENCHMARK_SEED),
+            benchmark_seed_piles(matchup, 3, DEFAULT_BENCHMARK_SEED)
+        );
+    }
+}
@@ -1,48 +1,23 @@
-use clap::{ArgGroup, Parser};
 use handy_core::game::end_game::{is_game_winner, GameEndCheckType};
 use handy_core::game::*;
-use handy_core::utils::*;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::fmt::Debug;
+use std::fmt;
 use std::mem::swap;
 
-#[derive(Parser, Debug)]
-#[clap(group(
-            ArgGroup::new("win_type")
-                .required(true)
-                .args(&["win", "survive_until_top", "exhaust"]),
-        ))]
-struct ExaustiveSearchArgs {
-    #[clap(long, value_parser=string_to_pile_result)]
-    pile: Pile,
-
-    #[clap(long)]
-    turns: Option<usize>,
-
-    #[clap(long)]
-    rev: bool,
-
-    #[clap(long)]
-    win: bool,
-    #[clap(long, value_parser=string_to_card_id_result)]
-    survive_until_top: Option<CardId>,
-    #[clap(long, value_parser=string_to_card_id_result)]
-    exhaust: Option<CardId>,
-}
-
 pub enum VictoryConditionResult {
     Win,
     Loss,
     Continue,
 }
 
-trait VictoryConditionChecker {
+pub trait VictoryConditionChecker {
     fn condition_result(&self, pile: &Pile) -> VictoryConditionResult;
 }
 
 #[derive(Clone, Debug)]
-struct WinChecker {}
+pub struct WinChecker {}
 impl VictoryConditionChecker for WinChecker {
     fn condition_result(&self, pile: &Pile) -> VictoryConditionResult {
         match is_game_winner(pile, GameEndCheckType::Standard) {
@@ -54,8 +29,8 @@ impl VictoryConditionChecker for WinChecker {
 }
 
 #[derive(Clone, Debug)]
-struct SurviveUntilTopChecker {
-    card_id: CardId,
+pub struct SurviveUntilTopChecker {
+    pub card_id: CardId,
 }
 impl VictoryConditionChecker for SurviveUntilTopChecker {
     fn condition_result(&self, pile: &Pile) -> VictoryConditionResult {
@@ -84,17 +59,17 @@ impl VictoryConditionChecker for SurviveUntilTopChecker {
 }
 
 #[derive(Clone, Debug)]
-struct ExhaustCardChecker {
-    card_id: CardId,
+pub struct ExhaustCardChecker {
+    pub card_id: CardId,
 }
 impl VictoryConditionChecker for ExhaustCardChecker {
     fn condition_result(&self, pile: &Pile) -> VictoryConditionResult {
         // False if the card is dead anywhere
         for card_ptr in pile {
-            if card_ptr.get_card_id() == self.card_id {
-                if card_ptr.get_active_face().health == Health::Empty {
-                    return VictoryConditionResult::Win;
-                }
+            if card_ptr.get_card_id() == self.card_id
+                && card_ptr.get_active_face().health == Health::Empty
+            {
+                return VictoryConditionResult::Win;
             }
         }
 
@@ -106,18 +81,42 @@ impl VictoryConditionChecker for ExhaustCardChecker {
     }
 }
 
-fn resolve_condition_checker(args: &ExaustiveSearchArgs) -> Box<dyn VictoryConditionChecker> {
-    if args.win {
-        Box::new(WinChecker {})
-    } else if let Some(card_id) = args.survive_until_top {
-        Box::new(SurviveUntilTopChecker { card_id })
-    } else if let Some(card_id) = args.exhaust {
-        Box::new(ExhaustCardChecker { card_id })
-    } else {
-        panic!("Could not result condition checker from these args");
+/// The objectives handy search takes
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchObjective {
+    Win,
+    SurviveUntilTop { card_id: CardId },
+    Exhaust { card_id: CardId },
+}
+
+impl SearchObjective {
+    pub fn checker(&self) -> Box<dyn VictoryConditionChecker> {
+        match *self {
+            SearchObjective::Win => Box::new(WinChecker {}),
+            SearchObjective::SurviveUntilTop { card_id } => {
+                Box::new(SurviveUntilTopChecker { card_id })
+            }
+            SearchObjective::Exhaust { card_id } => Box::new(ExhaustCardChecker { card_id }),
+        }
     }
 }
 
+impl fmt::Display for SearchObjective {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchObjective::Win => write!(f, "Defeat every enemy"),
+            SearchObjective::SurviveUntilTop { card_id } => {
+                write!(f, "Get {card_id} to the top without exhausting it")
+            }
+            SearchObjective::Exhaust { card_id } => write!(f, "Exhaust {card_id}"),
+        }
+    }
+}
+
+// The distinct piles after activating the top card, shared by every search from the same cards
+pub type ChildrenMemo = HashMap<Pile, Vec<Pile>>;
+
 type ParentStatesByLevel = Vec<HashMap<Pile, Vec<Pile>>>;
 fn unwrap_solutions(
     pile: &Pile,
@@ -141,13 +140,25 @@ fn unwrap_solutions(
         ));
     }
 
-    return res;
+    res
+}
+
+pub fn memoized_children<'a>(pile: &Pile, memo: &'a mut ChildrenMemo) -> &'a Vec<Pile> {
+    memo.entry(pile.clone()).or_insert_with(|| {
+        resolve_top_card(&GameStateNoEventLog::new(pile.clone()))
+            .into_iter()
+            .map(|child_state| child_state.pile)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect()
+    })
 }
 
 fn solve(
     init_pile: Pile,
-    condition_checker: Box<dyn VictoryConditionChecker>,
+    condition_checker: &dyn VictoryConditionChecker,
     turns: Option<usize>,
+    memo: &mut ChildrenMemo,
 ) -> Vec<Vec<Pile>> {
     let mut turn_number: usize = 0;
     let mut current_piles = vec![];
@@ -163,11 +174,7 @@ fn solve(
         let mut victories = vec![];
 
         while let Some(pile) = current_piles.pop() {
-            let child_states = resolve_top_card(&GameStateNoEventLog::new(pile.clone()));
-
-            for child_state in child_states {
-                let child_pile = child_state.pile;
-
+            for child_pile in memoized_children(&pile, memo).iter().cloned() {
                 if let Some(entry) = parent_states.get_mut(&child_pile) {
                     entry.push(pile.clone());
                 } else {
@@ -185,7 +192,7 @@ fn solve(
         parent_states_by_level.push(parent_states);
         turn_number += 1;
 
-        if victories.len() > 0 && turns.map_or(true, |turns| turn_number >= turns) {
+        if !victories.is_empty() && turns.map_or(true, |turns| turn_number >= turns) {
             let mut result: Vec<Vec<Pile>> = vec![];
             for victory in victories {
                 result.extend(unwrap_solutions(
@@ -199,49 +206,56 @@ fn solve(
             return result;
         }
 
-        if turns.map_or(false, |turns| turn_number >= turns) {
+        // Without a turn limit, a pile with no solution runs out of piles rather than turns
+        if turns.map_or(false, |turns| turn_number >= turns) || next_piles.is_empty() {
             return vec![];
         }
     }
 }
 
-fn main() {
-    let args = ExaustiveSearchArgs::parse();
-    let condition_checker = resolve_condition_checker(&args);
-
-    let mut pile = args.pile;
-    if args.rev {
-        pile.reverse();
-    }
-
-    let mut solutions = solve(pile, condition_checker, args.turns);
+/// Distinct solutions from the start pile, each starting with it
+pub fn find_solutions(
+    pile: &Pile,
+    condition_checker: &dyn VictoryConditionChecker,
+    turns: Option<usize>,
+    memo: &mut ChildrenMemo,
+) -> Vec<Vec<Pile>> {
     // Filter out duplicate ways to have the same path
-    solutions = solutions
+    solve(pile.clone(), condition_checker, turns, memo)
         .into_iter()
         .collect::<HashSet<_>>()
         .into_iter()
-        .collect();
-
-    if solutions.len() == 0 {
-        println!("No solutions");
-    } else {
-        println!(
-            "Found {} solution after {} turns",
-            solutions.len(),
-            solutions[0].len() - 1
-        );
-
-        for mut solution in solutions {
+        .map(|mut solution| {
             solution.reverse();
-            println!("~~~");
-            let mut last_pile = solution[0].clone();
-            println!("{:?}", last_pile);
-
-            for pile in solution[1..].iter().cloned() {
-                print_steps_between_piles(&last_pile, &pile, &|x| println!("{}", x));
-                println!("{:?}", pile);
-                last_pile = pile;
-            }
-        }
+            solution
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handy_core::utils::string_to_pile;
+
+    #[test]
+    fn test_shared_memo() {
+        let checker = ExhaustCardChecker { card_id: 15 };
+        let pile = string_to_pile("4A 2B 5A 18A 15A");
+        let fresh = find_solutions(&pile, &checker, Some(3), &mut HashMap::new());
+        assert_eq!(fresh.len(), 2);
+        assert!(fresh.iter().all(|solution| solution[0] == pile));
+
+        let mut memo: ChildrenMemo = HashMap::new();
+        find_solutions(
+            &string_to_pile("15A 18A 5A 2B 4A"),
+            &checker,
+            Some(3),
+            &mut memo,
+        );
+        let shared = find_solutions(&pile, &checker, Some(3), &mut memo);
+        assert_eq!(
+            shared.into_iter().collect::<HashSet<_>>(),
+            fresh.into_iter().collect::<HashSet<_>>()
+        );
     }
 }
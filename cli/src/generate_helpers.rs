@@ -1,5 +1,5 @@
 use crate::*;
-use clap::Parser;
+use clap::Args;
 use handy_core::game::*;
 use handy_core::solver::*;
 use serde_json;
@@ -35,36 +35,29 @@ pub fn write_examples_to_file(
     }
 }
 
-fn file_size_for_matchup(matchup: Matchup) -> u64 {
-    let path = training_path_for_matchup(matchup);
-
-    let Ok(file) = OpenOptions::new().read(true).open(path) else {
-        return 0;
-    };
-
-    file.metadata().map_or(0, |m| m.len())
-}
-
-pub fn find_least_used_matchup<'a>(matchups: impl Iterator<Item = &'a Matchup>) -> Matchup {
+pub fn find_least_used_matchup<'a>(
+    paths: &Paths,
+    matchups: impl Iterator<Item = &'a Matchup>,
+) -> Matchup {
     let mut result: Option<(Matchup, u64)> = None;
 
     for item in matchups {
         let matchup = item.clone();
-        let file_len = file_size_for_matchup(matchup);
+        let num_examples = approx_num_examples_for_matchup(paths, matchup);
 
         if let Some(known_result) = result {
-            if file_len < known_result.1 {
-                result = Some((matchup, file_len));
+            if num_examples < known_result.1 {
+                result = Some((matchup, num_examples));
             }
         } else {
-            result = Some((matchup, file_len));
+            result = Some((matchup, num_examples));
         }
     }
 
     result.unwrap().0
 }
 
-#[derive(Parser, Debug)]
+#[derive(Args, Debug, Clone)]
 pub struct TrainArgs {
     #[clap(long)]
     all: bool,
@@ -79,8 +72,7 @@ pub struct TrainArgs {
     pub q1: bool,
 }
 
-pub fn get_training_matchups_from_args() -> Vec<Matchup> {
-    let args = TrainArgs::parse();
+pub fn matchups_from_train_args(args: TrainArgs) -> Vec<Matchup> {
     let mut all_matchups = HashSet::new();
     for matchup in args.matchups {
         all_matchups.insert(matchup);
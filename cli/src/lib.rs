@@ -1,14 +1,41 @@
+pub mod batch_solve;
+pub mod commands;
+pub mod config;
+pub mod evaluation;
+pub mod exhaustive_search;
 pub mod generate_helpers;
+pub mod matchup_survey;
 pub mod parsers;
 pub mod paths;
+pub mod pile_arrangements;
 pub mod pile_randomizers;
+pub mod pile_render;
+pub mod play_session;
+pub mod puzzle_generator;
 pub mod run_a_star;
+pub mod self_play;
+pub mod solve_report;
+pub mod training;
+pub mod training_data;
 pub mod training_utils;
 
+pub use batch_solve::*;
+pub use config::*;
+pub use evaluation::*;
+pub use exhaustive_search::*;
 pub use generate_helpers::*;
+pub use matchup_survey::*;
 pub use parsers::*;
 pub use paths::*;
+pub use pile_arrangements::*;
 pub use pile_randomizers::*;
+pub use pile_render::*;
+pub use play_session::*;
+pub use puzzle_generator::*;
+pub use self_play::*;
+pub use solve_report::*;
+pub use training::*;
+pub use training_data::*;
 pub use training_utils::*;
 
 use chrono::offset::Utc;
@@ -38,20 +65,31 @@ pub fn get_datetime_stamp() -> String {
 pub struct StandardArgs {
     #[clap(short, long, value_parser=string_to_pile_result)]
     pub pile: Option<Pile>,
+    /// How --pile is written: spaced, compact, arrows, or reversed for top last piles
+    #[clap(long, default_value = "spaced")]
+    pub notation: PileDialect,
     #[clap(short, long, num_args = 2)]
     pub classes: Option<Vec<Class>>,
     #[clap(short, long)]
-    pub seed: Option<String>,
-    #[clap(short, long)]
     pub g_bias: Option<f32>,
 }
 
-pub fn get_starting_pile_from_args(args: &StandardArgs) -> Pile {
-    if let Some(pile) = args.pile.clone() {
-        pile
+/// --pile is parsed top first before the notation is known, this turns top last piles around
+pub fn pile_from_notation(pile: &Pile, notation: PileDialect) -> Pile {
+    let mut pile = pile.clone();
+    if notation.is_top_last() {
+        pile.reverse();
+    }
+    pile
+}
+
+/// The seed only matters for piles from classes
+pub fn get_starting_pile_from_args(args: &StandardArgs, seed: Option<&str>) -> Pile {
+    if let Some(pile) = &args.pile {
+        pile_from_notation(pile, args.notation)
     } else {
         let classes = args.classes.clone().unwrap();
-        let mut rng: Box<dyn RngCore> = if let Some(seed) = args.seed.clone() {
+        let mut rng: Box<dyn RngCore> = if let Some(seed) = seed {
             Box::new(Seeder::from(seed).make_rng::<Pcg64>())
         } else {
             Box::new(thread_rng())
@@ -61,30 +99,157 @@ pub fn get_starting_pile_from_args(args: &StandardArgs) -> Pile {
     }
 }
 
-pub fn get_starting_pile() -> Pile {
-    let args = StandardArgs::parse();
-    get_starting_pile_from_args(&args)
-}
-
-pub fn get_model_for_pile(pile: &Pile) -> Model {
-    let matchups = get_all_matchups_from_pile(pile);
-    let mut models: Vec<Model> = matchups
+/// Errors name every model file the pile needs that isn't in any model dir
+pub fn try_get_model_for_pile(paths: &Paths, pile: &Pile) -> Result<Model, String> {
+    let mut file_names: Vec<String> = get_all_matchups_from_pile(pile)
         .into_iter()
-        .map(|matchup| try_read_model_for_matchup(matchup).unwrap())
+        .map(matchup_to_yaml_str)
+        .collect();
+    if does_have_quest(pile) {
+        file_names.push(QUEST_MODEL_FILE_NAME.to_owned());
+    }
+
+    let missing: Vec<String> = file_names
+        .iter()
+        .filter(|name| find_model_file(paths, name).is_none())
+        .cloned()
         .collect();
+    if !missing.is_empty() {
+        return Err(missing_models_error(paths, &missing));
+    }
+    let models: Vec<Model> = file_names
+        .iter()
+        .map(|name| try_read_model_from_custom_name(paths, name))
+        .collect::<Result<_, _>>()?;
 
-    if does_have_quest(&pile) {
-        let quest_model = try_read_quest_model().unwrap();
-        models.push(quest_model);
+    let model = match models.len() {
+        0 => return Err(format!("No matchup models for {pile:?}")),
+        1 => models[0].clone(),
+        _ => merge_models_for_pile(pile, &models),
+    };
+
+    if pile.iter().any(|c| {
+        model
+            .try_get_face_features(c.get_card_id() as usize)
+            .is_none()
+    }) {
+        return Err(format!("Matchup models don't cover every card in {pile:?}"));
     }
+    Ok(model)
+}
 
-    if models.len() == 1 {
-        models[0].clone()
-    } else {
-        merge_models_for_pile(pile, &models)
+/// One member per matchup model and one for the quest model, each gated on its own classes.
+/// Members score the cards they don't have with the merged model, which is also the fallback.
+pub fn try_get_class_gated_model_for_pile(
+    paths: &Paths,
+    pile: &Pile,
+) -> Result<ClassGatedModel, String> {
+    let merged = try_get_model_for_pile(paths, pile)?;
+    let mut members: Vec<(Vec<Class>, Box<dyn ModelT>)> = Vec::new();
+    for matchup in get_all_matchups_from_pile(pile) {
+        let model = try_read_model_for_matchup(paths, matchup)?;
+        members.push((
+            vec![matchup.0, matchup.1],
+            Box::new(fill_missing_cards(&model, &merged)),
+        ));
+    }
+    if does_have_quest(pile) {
+        let model = try_read_quest_model(paths)?;
+        members.push((
+            vec![Class::Quest],
+            Box::new(fill_missing_cards(&model, &merged)),
+        ));
+    }
+    Ok(ClassGatedModel::new(members, Some(Box::new(merged))))
+}
+
+// Every model a blend can be fit over, see train_blender
+pub const BLEND_MEMBER_NAMES: [&str; 3] = ["linear", "semantic", "mlp"];
+
+pub fn try_get_blend_member(
+    paths: &Paths,
+    name: &str,
+    matchup: Matchup,
+) -> Result<Box<dyn ModelT>, String> {
+    match name {
+        "linear" => Ok(Box::new(try_read_model_for_matchup(paths, matchup)?)),
+        "semantic" => Ok(Box::new(try_read_semantic_model(paths)?)),
+        "mlp" => Ok(Box::new(try_read_mlp_model_for_matchup(paths, matchup)?)),
+        _ => Err(format!("Unknown blend member {name}")),
+    }
+}
+
+pub fn try_get_blended_model_for_matchup(
+    paths: &Paths,
+    matchup: Matchup,
+) -> Result<BlendedModel, String> {
+    let blend = try_read_blend_weights_for_matchup(paths, matchup)?;
+    let members = blend
+        .member_names
+        .iter()
+        .map(|name| try_get_blend_member(paths, name, matchup))
+        .collect::<Result<Vec<_>, _>>()?;
+    BlendedModel::new(blend, members)
+}
+
+/// The matchup model when there is one for every card, otherwise the semantic model,
+/// which handles any cards
+pub fn try_get_heuristic_for_pile(paths: &Paths, pile: &Pile) -> Result<Box<dyn ModelT>, String> {
+    match try_get_model_for_pile(paths, pile) {
+        Ok(model) => Ok(Box::new(model)),
+        Err(err) => {
+            let model = try_read_semantic_model(paths)
+                .map_err(|semantic_err| format!("{err}, and no semantic model: {semantic_err}"))?;
+            eprintln!("{err}, using the semantic model");
+            Ok(Box::new(model))
+        }
     }
 }
 
+/// Falls back to a model that scores every pile the same, so solves still work, just slower
+pub fn get_heuristic_for_pile(paths: &Paths, pile: &Pile) -> Box<dyn ModelT> {
+    try_get_heuristic_for_pile(paths, pile).unwrap_or_else(|err| {
+        eprintln!("{err}, using an empty model");
+        Box::new(SemanticModel::new_empty())
+    })
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum HeuristicKind {
+    // See get_heuristic_for_pile
+    #[default]
+    Default,
+    Gated,
+    // The larger of the linear and semantic scores
+    Max,
+    // Needs a blend from train_blender, so only for single matchup piles
+    Blended,
+}
+
+pub fn try_get_heuristic_for_pile_with_kind(
+    paths: &Paths,
+    pile: &Pile,
+    kind: HeuristicKind,
+) -> Result<Box<dyn ModelT>, String> {
+    Ok(match kind {
+        HeuristicKind::Default => get_heuristic_for_pile(paths, pile),
+        HeuristicKind::Gated => Box::new(try_get_class_gated_model_for_pile(paths, pile)?),
+        HeuristicKind::Max => Box::new(MaxOfModel::new(vec![
+            Box::new(try_get_model_for_pile(paths, pile)?),
+            Box::new(try_read_semantic_model(paths)?),
+        ])),
+        HeuristicKind::Blended => {
+            let matchups = get_all_matchups_from_pile(pile);
+            if matchups.len() != 1 || does_have_quest(pile) {
+                return Err(format!(
+                    "Blends are per matchup, {pile:?} needs several models"
+                ));
+            }
+            Box::new(try_get_blended_model_for_matchup(paths, matchups[0])?)
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,11 +273,20 @@ mod tests {
         assert_eq!(result.unwrap().pile.unwrap(), string_to_pile("1 2 3"))
     }
 
+    #[test]
+    fn test_heuristic_for_pile_scores_every_pile() {
+        // With or without model files
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let model = get_heuristic_for_pile(&Paths::from_data_dir("/nonexistent"), &pile);
+        assert!(model.score_pile(&pile).is_finite());
+        let model = get_heuristic_for_pile(&test_paths(), &pile);
+        assert!(model.score_pile(&pile).is_finite());
+    }
+
     #[test]
     fn test_classes_with_seed() {
-        let args =
-            StandardArgs::parse_from(["cmd", "--classes", "warrior", "ogre", "--seed", "abc"]);
-        let pile = get_starting_pile_from_args(&args);
+        let args = StandardArgs::parse_from(["cmd", "--classes", "warrior", "ogre"]);
+        let pile = get_starting_pile_from_args(&args, Some("abc"));
         assert_eq!(pile, string_to_pile("3A 9A 4A 1A 5A 7A 2A 8A 6A"));
     }
 }
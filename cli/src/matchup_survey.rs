@@ -0,0 +1,200 @@
+use crate::{matchup_to_str, BatchResult};
+use handy_core::solver::model::Matchup;
+use serde::Serialize;
+
+/// How one start pile of a matchup went
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurveyOutcome {
+    Won(usize),
+    // The search finished without a win, and without dropping any states
+    Lost,
+    // The budget ran out first, or states were dropped that might have led to a win
+    Unsolved,
+    Error,
+}
+
+pub fn survey_outcome(result: &BatchResult) -> SurveyOutcome {
+    match (result.depth, result.done_reason) {
+        (_, None) => SurveyOutcome::Error,
+        (Some(depth), _) => SurveyOutcome::Won(depth),
+        (None, Some(_)) if result.search_completed => SurveyOutcome::Lost,
+        (None, Some(_)) => SurveyOutcome::Unsolved,
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MatchupSurvey {
+    // Like Warrior.Ogre, as model files are named
+    pub matchup: String,
+    pub hero: String,
+    pub monster: String,
+    pub num_piles: usize,
+    pub num_won: usize,
+    pub num_lost: usize,
+    pub num_unsolved: usize,
+    pub num_errors: usize,
+    // Out of every pile, so unsolved piles count against it
+    pub win_rate: f32,
+    pub unsolved_fraction: f32,
+    // Depths are over the won piles. They can be longer than optimal if the budget ran out.
+    pub p10_depth: Option<usize>,
+    pub median_depth: Option<usize>,
+    pub p90_depth: Option<usize>,
+    pub num_search_completed: usize,
+    pub mean_iterations: f32,
+    pub median_iterations: usize,
+    pub mean_elapsed_ms: f32,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MatchupSurveyReport {
+    pub seed: String,
+    pub piles_per_matchup: usize,
+    pub max_iters: usize,
+    pub game_end_check_type: String,
+    // Hardest first
+    pub matchups: Vec<MatchupSurvey>,
+}
+
+/// Nearest rank percentile of sorted values, p from 0 to 100
+pub fn percentile(sorted: &[usize], p: f32) -> Option<usize> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f32).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+pub fn summarize_matchup(matchup: Matchup, results: &[BatchResult]) -> MatchupSurvey {
+    let outcomes: Vec<SurveyOutcome> = results.iter().map(survey_outcome).collect();
+    let count = |wanted: SurveyOutcome| outcomes.iter().filter(|o| **o == wanted).count();
+
+    let mut depths: Vec<usize> = outcomes
+        .iter()
+        .filter_map(|outcome| match outcome {
+            SurveyOutcome::Won(depth) => Some(*depth),
+            _ => None,
+        })
+        .collect();
+    depths.sort();
+    let mut iterations: Vec<usize> = results.iter().map(|r| r.iterations).collect();
+    iterations.sort();
+
+    let num_piles = results.len();
+    let fraction = |n: usize| n as f32 / num_piles.max(1) as f32;
+    MatchupSurvey {
+        matchup: matchup_to_str(matchup),
+        hero: format!("{:?}", matchup.0),
+        monster: format!("{:?}", matchup.1),
+        num_piles,
+        num_won: depths.len(),
+        num_lost: count(SurveyOutcome::Lost),
+        num_unsolved: count(SurveyOutcome::Unsolved),
+        num_errors: count(SurveyOutcome::Error),
+        win_rate: fraction(depths.len()),
+        unsolved_fraction: fraction(count(SurveyOutcome::Unsolved)),
+        p10_depth: percentile(&depths, 10.0),
+        median_depth: percentile(&depths, 50.0),
+        p90_depth: percentile(&depths, 90.0),
+        num_search_completed: results.iter().filter(|r| r.search_completed).count(),
+        mean_iterations: iterations.iter().sum::<usize>() as f32 / num_piles.max(1) as f32,
+        median_iterations: percentile(&iterations, 50.0).unwrap_or(0),
+        mean_elapsed_ms: results.iter().map(|r| r.elapsed_ms).sum::<f32>()
+            / num_piles.max(1) as f32,
+    }
+}
+
+/// Lower win rates first, then longer wins
+pub fn sort_hardest_first(surveys: &mut [MatchupSurvey]) {
+    surveys.sort_by(|a, b| {
+        a.win_rate
+            .total_cmp(&b.win_rate)
+            .then(b.median_depth.cmp(&a.median_depth))
+            .then(a.matchup.cmp(&b.matchup))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handy_core::game::Class;
+    use handy_core::solver::a_star::AStarDoneReason;
+
+    fn result(depth: Option<usize>, done_reason: Option<AStarDoneReason>) -> BatchResult {
+        BatchResult {
+            line: 1,
+            id: None,
+            pile: "1A 2A 6A 7A".to_owned(),
+            depth,
+            search_completed: matches!(
+                done_reason,
+                Some(AStarDoneReason::EmptyQueue | AStarDoneReason::FScoreCutoff)
+            ),
+            proven_optimal: false,
+            expected_depth: None,
+            matches_expected: None,
+            done_reason,
+            iterations: 10,
+            elapsed_ms: 2.0,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_percentile() {
+        assert_eq!(percentile(&[], 50.0), None);
+        assert_eq!(percentile(&[4], 90.0), Some(4));
+        let values: Vec<usize> = (1..=10).collect();
+        assert_eq!(percentile(&values, 10.0), Some(1));
+        assert_eq!(percentile(&values, 50.0), Some(5));
+        assert_eq!(percentile(&values, 90.0), Some(9));
+        assert_eq!(percentile(&values, 100.0), Some(10));
+    }
+
+    #[test]
+    fn test_trimmed_search_is_unsolved() {
+        let completed = result(None, Some(AStarDoneReason::EmptyQueue));
+        assert_eq!(survey_outcome(&completed), SurveyOutcome::Lost);
+
+        // A memory budget trimmed the queue before it ran out
+        let trimmed = BatchResult {
+            search_completed: false,
+            ..completed
+        };
+        assert_eq!(survey_outcome(&trimmed), SurveyOutcome::Unsolved);
+    }
+
+    #[test]
+    fn test_summarize_matchup() {
+        let results = vec![
+            result(Some(12), Some(AStarDoneReason::FScoreCutoff)),
+            result(Some(8), Some(AStarDoneReason::EmptyQueue)),
+            result(None, Some(AStarDoneReason::EmptyQueue)),
+            result(None, Some(AStarDoneReason::MaxIters)),
+            result(None, None),
+        ];
+        let survey = summarize_matchup((Class::Warrior, Class::Ogre), &results);
+        assert_eq!(survey.matchup, "Warrior.Ogre");
+        assert_eq!(survey.num_piles, 5);
+        assert_eq!(
+            (
+                survey.num_won,
+                survey.num_lost,
+                survey.num_unsolved,
+                survey.num_errors
+            ),
+            (2, 1, 1, 1)
+        );
+        assert_eq!(survey.win_rate, 0.4);
+        assert_eq!(survey.unsolved_fraction, 0.2);
+        assert_eq!(survey.p10_depth, Some(8));
+        assert_eq!(survey.median_depth, Some(8));
+        assert_eq!(survey.p90_depth, Some(12));
+        assert_eq!(survey.mean_iterations, 10.0);
+
+        let easy = summarize_matchup((Class::Monk, Class::Ooze), &results[..2]);
+        let mut surveys = vec![easy, survey];
+        sort_hardest_first(&mut surveys);
+        assert_eq!(surveys[0].matchup, "Warrior.Ogre");
+    }
+}
@@ -18,3 +18,12 @@ pub fn parse_dot_separated_matchup(s: &str) -> Result<Matchup, String> {
 
     Ok((hero, enemy))
 }
+
+/// A fraction strictly between 0 and 1, for splits where both sides need examples
+pub fn parse_split_fraction(s: &str) -> Result<f32, String> {
+    let fraction: f32 = s.parse().map_err(|err| format!("{}", err))?;
+    if !(fraction > 0.0 && fraction < 1.0) {
+        return Err(format!("{} must be between 0 and 1, exclusive", s));
+    }
+    Ok(fraction)
+}
@@ -1,15 +1,124 @@
-use handy_core::solver::model::{Matchup, Model};
-use lazy_static::lazy_static;
+use crate::commands::GlobalArgs;
+use crate::config::*;
+use crate::evaluation::EvaluationReport;
+use crate::get_datetime_stamp;
+use handy_core::game::end_game::GameEndCheckType;
+use handy_core::game::Pile;
+use handy_core::solver::ensemble::BlendWeights;
+use handy_core::solver::loss_cache::LossCache;
+use handy_core::solver::mlp_model::MlpModel;
+use handy_core::solver::model::{get_all_matchups_from_pile, Matchup, Model};
+use handy_core::solver::model_envelope::*;
+use handy_core::solver::semantic_model::SemanticModel;
 use serde_yaml;
+use std::env;
 use std::fs;
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Write};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
 use std::path::{Path, PathBuf};
 
-lazy_static! {
-    pub static ref DATA_DIR: PathBuf = Path::new("./data").into();
-    pub static ref TRAINING_DATA_DIR: PathBuf = DATA_DIR.join("training_data");
-    pub static ref MODELS_DIR: PathBuf = DATA_DIR.join("models");
+/// Where handy reads and writes its files. Resolved once from the flags, environment variables
+/// and config file, then passed to whatever needs a path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Paths {
+    pub data_dir: PathBuf,
+    // Where models are read from, first match wins
+    pub model_dirs: Vec<PathBuf>,
+}
+
+impl Paths {
+    /// Only reads models from the data dir's models
+    pub fn from_data_dir(data_dir: impl Into<PathBuf>) -> Self {
+        let data_dir = data_dir.into();
+        Self {
+            model_dirs: vec![data_dir.join("models")],
+            data_dir,
+        }
+    }
+
+    /// Flags first, then environment variables, then the config file, then ./data
+    pub fn resolve(global: &GlobalArgs) -> Result<Self, String> {
+        let config = try_read_config_from_flag_or_env(global.config.as_deref())?;
+        let data_dir = resolve_data_dir(
+            global
+                .data_dir
+                .clone()
+                .or_else(|| env::var_os(DATA_DIR_ENV_VAR).map(PathBuf::from)),
+            &config,
+        );
+        let override_model_dirs = match global.models_dir.is_empty() {
+            false => global.models_dir.clone(),
+            true => env::var_os(MODEL_PATH_ENV_VAR)
+                .map(|value| env::split_paths(&value).collect())
+                .unwrap_or_default(),
+        };
+        let model_dirs = resolve_model_dirs(override_model_dirs, &config, &data_dir.join("models"));
+        Ok(Self {
+            data_dir,
+            model_dirs,
+        })
+    }
+
+    pub fn training_data_dir(&self) -> PathBuf {
+        self.data_dir.join("training_data")
+    }
+
+    /// Where models are written
+    pub fn models_dir(&self) -> PathBuf {
+        self.data_dir.join("models")
+    }
+
+    pub fn loss_cache_dir(&self) -> PathBuf {
+        self.data_dir.join("loss_cache")
+    }
+
+    pub fn evaluations_dir(&self) -> PathBuf {
+        self.data_dir.join("evaluations")
+    }
+
+    pub fn matchup_survey_path(&self) -> PathBuf {
+        self.evaluations_dir().join("matchup_survey.json")
+    }
+
+    /// Commands typed into handy play
+    pub fn play_history_path(&self) -> PathBuf {
+        self.data_dir.join("play_history.txt")
+    }
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Self::from_data_dir(DEFAULT_DATA_DIR)
+    }
+}
+
+/// The first model dir with the file
+pub fn find_model_file(paths: &Paths, file_name: &str) -> Option<PathBuf> {
+    paths
+        .model_dirs
+        .iter()
+        .map(|dir| dir.join(file_name))
+        .find(|path| path.exists())
+}
+
+/// Names every missing file and every dir that was searched, for errors about missing models
+pub fn missing_models_error(paths: &Paths, file_names: &[String]) -> String {
+    let dirs: Vec<String> = paths
+        .model_dirs
+        .iter()
+        .map(|dir| dir.display().to_string())
+        .collect();
+    format!(
+        "Missing models {} (searched {})",
+        file_names.join(", "),
+        dirs.join(", ")
+    )
+}
+
+fn try_find_model_file(paths: &Paths, file_name: &str) -> Result<String, String> {
+    find_model_file(paths, file_name)
+        .map(|path| path.to_str().unwrap().to_owned())
+        .ok_or_else(|| missing_models_error(paths, &[file_name.to_owned()]))
 }
 
 pub fn matchup_to_str(matchup: Matchup) -> String {
@@ -28,71 +137,313 @@ pub fn matchup_to_jsonl_str(matchup: Matchup) -> String {
     matchup_with_ext(matchup, "jsonl")
 }
 
-pub fn model_path_for_matchup(matchup: Matchup) -> String {
-    MODELS_DIR
+pub fn model_path_for_matchup(paths: &Paths, matchup: Matchup) -> String {
+    paths
+        .models_dir()
         .join(matchup_to_yaml_str(matchup))
         .to_str()
         .unwrap()
         .to_owned()
 }
 
-pub fn quest_model_path() -> String {
-    MODELS_DIR.join("Quest.yaml").to_str().unwrap().to_owned()
+pub fn mlp_model_path_for_matchup(paths: &Paths, matchup: Matchup) -> String {
+    paths
+        .models_dir()
+        .join(matchup_with_ext(matchup, "mlp.yaml"))
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+pub fn blend_weights_path_for_matchup(paths: &Paths, matchup: Matchup) -> String {
+    paths
+        .models_dir()
+        .join(matchup_with_ext(matchup, "blend.yaml"))
+        .to_str()
+        .unwrap()
+        .to_owned()
 }
 
-pub fn swap_model_path_for_matchup(matchup: Matchup) -> String {
-    MODELS_DIR
+pub const QUEST_MODEL_FILE_NAME: &str = "Quest.yaml";
+const SEMANTIC_MODEL_FILE_NAME: &str = "Semantic.yaml";
+
+pub fn quest_model_path(paths: &Paths) -> String {
+    paths
+        .models_dir()
+        .join(QUEST_MODEL_FILE_NAME)
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+pub fn semantic_model_path(paths: &Paths) -> String {
+    paths
+        .models_dir()
+        .join(SEMANTIC_MODEL_FILE_NAME)
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+pub fn swap_model_path_for_matchup(paths: &Paths, matchup: Matchup) -> String {
+    paths
+        .models_dir()
         .join(format!("swap.{}", matchup_to_yaml_str(matchup)))
         .to_str()
         .unwrap()
         .to_owned()
 }
 
-pub fn training_path_for_matchup(matchup: Matchup) -> String {
-    TRAINING_DATA_DIR
+// Sharded binary examples, see training_data.rs
+pub fn training_shards_dir_for_matchup(paths: &Paths, matchup: Matchup) -> PathBuf {
+    paths.training_data_dir().join(matchup_to_str(matchup))
+}
+
+pub fn self_play_coverage_path(paths: &Paths) -> PathBuf {
+    paths.training_data_dir().join("self_play_coverage.json")
+}
+
+pub fn training_path_for_matchup(paths: &Paths, matchup: Matchup) -> String {
+    paths
+        .training_data_dir()
         .join(matchup_to_jsonl_str(matchup))
         .to_str()
         .unwrap()
         .to_owned()
 }
 
+/// Older models are migrated in memory, see parse_model_yaml
+pub fn try_read_model_envelope_from_full_path(full_path: &str) -> Result<LoadedModel, String> {
+    let contents = fs::read_to_string(full_path).map_err(|err| format!("{full_path}: {err}"))?;
+    parse_model_yaml(&contents).map_err(|err| format!("{full_path}: {err}"))
+}
+
 pub fn try_read_model_from_full_path(full_path: &str) -> Result<Model, String> {
-    let file = File::open(full_path).map_err(|err| format!("{err}"))?;
-    let reader = BufReader::new(file);
-    serde_yaml::from_reader(reader).map_err(|err| format!("{err}"))
+    try_read_model_envelope_from_full_path(full_path).map(|loaded| loaded.envelope.model)
+}
+
+pub fn write_model_envelope_to_full_path(envelope: &ModelEnvelope, full_path: &str) {
+    let file = File::create(full_path).unwrap();
+    let mut writer = BufWriter::new(file);
+    serde_yaml::to_writer(&mut writer, envelope).unwrap();
+    writer.flush().unwrap();
 }
 
-pub fn try_read_model_from_custom_name(name: &str) -> Result<Model, String> {
-    let full_path = MODELS_DIR.join(name);
+pub fn try_read_model_from_custom_name(paths: &Paths, name: &str) -> Result<Model, String> {
+    try_read_model_from_full_path(&try_find_model_file(paths, name)?)
+}
+
+pub fn try_read_model_for_matchup(paths: &Paths, matchup: Matchup) -> Result<Model, String> {
+    try_read_model_from_custom_name(paths, &matchup_to_yaml_str(matchup))
+}
+
+// For comparing against models that aren't in the default models dir
+pub fn try_read_model_for_matchup_in_dir(dir: &Path, matchup: Matchup) -> Result<Model, String> {
+    let full_path = dir.join(matchup_to_yaml_str(matchup));
     try_read_model_from_full_path(full_path.to_str().unwrap())
 }
 
-pub fn try_read_model_for_matchup(matchup: Matchup) -> Result<Model, String> {
-    let full_path = model_path_for_matchup(matchup);
-    try_read_model_from_full_path(&full_path)
+pub fn evaluation_report_path(paths: &Paths, name: &str) -> String {
+    paths
+        .evaluations_dir()
+        .join(format!("{name}.json"))
+        .to_str()
+        .unwrap()
+        .to_owned()
 }
 
-pub fn try_read_quest_model() -> Result<Model, String> {
-    let full_path = quest_model_path();
-    try_read_model_from_full_path(&full_path)
+pub fn write_evaluation_report(paths: &Paths, report: &EvaluationReport, name: &str) -> String {
+    fs::create_dir_all(paths.evaluations_dir()).unwrap();
+    let full_path = evaluation_report_path(paths, name);
+    let file = File::create(&full_path).unwrap();
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, report).unwrap();
+    writer.flush().unwrap();
+    full_path
+}
+
+pub fn try_read_quest_model(paths: &Paths) -> Result<Model, String> {
+    try_read_model_from_custom_name(paths, QUEST_MODEL_FILE_NAME)
 }
 
-pub fn write_model_for_matchup_with_custom_suffix(model: &Model, matchup: Matchup, suffix: &str) {
+pub fn write_model_envelope_for_matchup_with_custom_suffix(
+    paths: &Paths,
+    envelope: &ModelEnvelope,
+    matchup: Matchup,
+    suffix: &str,
+) {
     let full_path = match suffix {
-        "" => model_path_for_matchup(matchup),
-        _ => format!("{}.{suffix}", model_path_for_matchup(matchup)),
+        "" => model_path_for_matchup(paths, matchup),
+        _ => format!("{}.{suffix}", model_path_for_matchup(paths, matchup)),
     };
-    let swap_path = swap_model_path_for_matchup(matchup);
+    let swap_path = swap_model_path_for_matchup(paths, matchup);
     if let Err(err) = fs::rename(&full_path, &swap_path) {
         println!("Failed to write swap file: {err}");
     }
 
-    let file = File::create(full_path).unwrap();
+    write_model_envelope_to_full_path(envelope, &full_path);
+}
+
+pub fn write_model_for_matchup_with_custom_suffix(
+    paths: &Paths,
+    model: &Model,
+    matchup: Matchup,
+    suffix: &str,
+) {
+    let metadata = ModelMetadata {
+        matchup: Some(matchup_to_str(matchup)),
+        created_at: Some(get_datetime_stamp()),
+        ..ModelMetadata::default()
+    };
+    write_model_envelope_for_matchup_with_custom_suffix(
+        paths,
+        &ModelEnvelope::new(model.clone(), metadata),
+        matchup,
+        suffix,
+    )
+}
+
+pub fn write_model_for_matchup(paths: &Paths, model: &Model, matchup: Matchup) {
+    write_model_for_matchup_with_custom_suffix(paths, model, matchup, "")
+}
+
+pub fn try_read_mlp_model_for_matchup(paths: &Paths, matchup: Matchup) -> Result<MlpModel, String> {
+    let file = File::open(try_find_model_file(
+        paths,
+        &matchup_with_ext(matchup, "mlp.yaml"),
+    )?)
+    .map_err(|err| format!("{err}"))?;
+    let reader = BufReader::new(file);
+    let model: MlpModel = serde_yaml::from_reader(reader).map_err(|err| format!("{err}"))?;
+    if !model.is_valid() {
+        return Err(format!(
+            "The {matchup:?} MLP model's weights don't match its {} cards and hidden size {}",
+            model.card_ids.len(),
+            model.hidden_size
+        ));
+    }
+    Ok(model)
+}
+
+pub fn write_mlp_model_for_matchup(paths: &Paths, model: &MlpModel, matchup: Matchup) {
+    let file = File::create(mlp_model_path_for_matchup(paths, matchup)).unwrap();
     let mut writer = BufWriter::new(file);
     serde_yaml::to_writer(&mut writer, model).unwrap();
     writer.flush().unwrap();
 }
 
-pub fn write_model_for_matchup(model: &Model, matchup: Matchup) {
-    write_model_for_matchup_with_custom_suffix(model, matchup, "")
+pub fn try_read_blend_weights_for_matchup(
+    paths: &Paths,
+    matchup: Matchup,
+) -> Result<BlendWeights, String> {
+    let file = File::open(try_find_model_file(
+        paths,
+        &matchup_with_ext(matchup, "blend.yaml"),
+    )?)
+    .map_err(|err| format!("{err}"))?;
+    let reader = BufReader::new(file);
+    serde_yaml::from_reader(reader).map_err(|err| format!("{err}"))
+}
+
+pub fn write_blend_weights_for_matchup(paths: &Paths, blend: &BlendWeights, matchup: Matchup) {
+    let file = File::create(blend_weights_path_for_matchup(paths, matchup)).unwrap();
+    let mut writer = BufWriter::new(file);
+    serde_yaml::to_writer(&mut writer, blend).unwrap();
+    writer.flush().unwrap();
+}
+
+pub fn try_read_semantic_model(paths: &Paths) -> Result<SemanticModel, String> {
+    let file = File::open(try_find_model_file(paths, SEMANTIC_MODEL_FILE_NAME)?)
+        .map_err(|err| format!("{err}"))?;
+    let reader = BufReader::new(file);
+    let model: SemanticModel = serde_yaml::from_reader(reader).map_err(|err| format!("{err}"))?;
+    SemanticModel::from_weights(model.weights)
+}
+
+pub fn write_semantic_model(paths: &Paths, model: &SemanticModel) {
+    let file = File::create(semantic_model_path(paths)).unwrap();
+    let mut writer = BufWriter::new(file);
+    serde_yaml::to_writer(&mut writer, model).unwrap();
+    writer.flush().unwrap();
+}
+
+pub fn loss_cache_path_for_pile(
+    paths: &Paths,
+    pile: &Pile,
+    game_end_check_type: GameEndCheckType,
+) -> String {
+    let matchups_str = get_all_matchups_from_pile(pile)
+        .into_iter()
+        .map(matchup_to_str)
+        .collect::<Vec<String>>()
+        .join("+");
+
+    paths
+        .loss_cache_dir()
+        .join(format!("{matchups_str}.{game_end_check_type:?}.txt"))
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+/// A missing cache starts empty. Any other error is returned, so a corrupt cache isn't
+/// overwritten by a merge that thought it was empty.
+pub fn try_read_loss_cache_for_pile(
+    paths: &Paths,
+    pile: &Pile,
+    game_end_check_type: GameEndCheckType,
+) -> Result<LossCache, String> {
+    let path = loss_cache_path_for_pile(paths, pile, game_end_check_type);
+    let mut loss_cache = LossCache::new(game_end_check_type);
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(loss_cache),
+        Err(err) => return Err(format!("{path}: {err}")),
+    };
+    let lines = BufReader::new(file)
+        .lines()
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|err| format!("{path}: {err}"))?;
+
+    loss_cache
+        .extend_from_lines(lines.iter().map(|l| l.as_str()))
+        .map_err(|err| format!("{path}: {err}"))?;
+    Ok(loss_cache)
+}
+
+// Most piles a loss cache file keeps, so it can't grow without bound
+pub const MAX_LOSS_CACHE_PILES: usize = 2_000_000;
+
+/// Merges with the file on disk first, so runs on the same matchup keep each other's losses.
+/// There's no lock, so two runs that write at the same time can still drop the losses of
+/// whichever renamed first. Writes then renames, so a crash never leaves a partial cache.
+pub fn write_loss_cache_for_pile(
+    paths: &Paths,
+    loss_cache: &LossCache,
+    pile: &Pile,
+) -> Result<(), String> {
+    let game_end_check_type = loss_cache.game_end_check_type;
+    fs::create_dir_all(paths.loss_cache_dir()).map_err(|err| format!("{err}"))?;
+    let path = loss_cache_path_for_pile(paths, pile, game_end_check_type);
+
+    let mut merged = try_read_loss_cache_for_pile(paths, pile, game_end_check_type)?;
+    for lost_pile in loss_cache.iter() {
+        merged.insert(lost_pile.clone());
+    }
+    merged.truncate(MAX_LOSS_CACHE_PILES);
+
+    let tmp_path = format!("{path}.{}.tmp", std::process::id());
+    let file = File::create(&tmp_path).map_err(|err| format!("{err}"))?;
+    let mut writer = BufWriter::new(file);
+    for line in merged.to_lines() {
+        writeln!(writer, "{line}").map_err(|err| format!("{err}"))?;
+    }
+    writer.flush().map_err(|err| format!("{err}"))?;
+    fs::rename(&tmp_path, &path).map_err(|err| format!("{err}"))
+}
+
+/// The repo's data dir, so tests can read the checked in models wherever cargo runs them
+#[cfg(test)]
+pub fn test_paths() -> Paths {
+    Paths::from_data_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("../data"))
 }
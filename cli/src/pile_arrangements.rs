@@ -0,0 +1,193 @@
+use handy_core::game::*;
+use handy_core::utils::*;
+use itertools::Itertools;
+use std::collections::HashSet;
+
+/// One card of a multiset of cards to arrange, see parse_card_specs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CardSpec {
+    pub card_id: CardId,
+    // None for any face
+    pub face: Option<FaceKey>,
+    // 0 is the top of the pile. None for anywhere
+    pub position: Option<usize>,
+}
+
+/// Cards like a pile string, where a card without a face is face A, plus ? for any face and
+/// @n to fix a card at position n from the top. "15? 18 5B 2B@0" has 15 on any face and 2B on top.
+pub fn parse_card_specs(input: &str) -> Result<Vec<CardSpec>, String> {
+    let cards = parse_notation_cards(input, true).map_err(|err| format!("{err}"))?;
+    let mut positions: HashSet<usize> = HashSet::new();
+    for card in &cards {
+        let Some(position) = card.pinned_at else {
+            continue;
+        };
+        let err = if position >= cards.len() {
+            format!(
+                "Position {position} is past the bottom of a {} card pile",
+                cards.len()
+            )
+        } else if !positions.insert(position) {
+            format!("Two cards are fixed at position {position}")
+        } else {
+            continue;
+        };
+        return Err(format!("{}", PileNotationError::new(card.position, err)));
+    }
+    Ok(cards
+        .into_iter()
+        .map(|card| CardSpec {
+            card_id: card.card_id,
+            face: card.face,
+            position: card.pinned_at,
+        })
+        .collect())
+}
+
+fn faces_for_spec(spec: &CardSpec) -> Vec<CardPtr> {
+    match spec.face {
+        Some(face) => vec![CardPtr::new_from_id(spec.card_id, face)],
+        None => [FaceKey::A, FaceKey::B, FaceKey::C, FaceKey::D]
+            .into_iter()
+            .map(|face| CardPtr::new_from_id(spec.card_id, face))
+            .collect(),
+    }
+}
+
+pub const DEFAULT_MAX_ARRANGEMENTS: usize = 100_000;
+
+/// The most piles enumerate_arrangements can make, without making them. Fewer if cards repeat.
+/// Nine cards on any face are already 9! * 4^9, about 9.5e10 piles.
+pub fn count_arrangements(specs: &[CardSpec]) -> u128 {
+    let num_free = specs.iter().filter(|spec| spec.position.is_none()).count() as u128;
+    let num_orders = (1..=num_free).fold(1u128, |total, n| total.saturating_mul(n));
+    specs.iter().fold(num_orders, |total, spec| {
+        total.saturating_mul(faces_for_spec(spec).len() as u128)
+    })
+}
+
+/// Every distinct pile the cards can make, keeping fixed cards where they are. Dummy cards can
+/// repeat, so orders that only swap them are made once. Errors instead of making more than
+/// max_arrangements.
+pub fn enumerate_arrangements(
+    specs: &[CardSpec],
+    max_arrangements: usize,
+) -> Result<Vec<Pile>, String> {
+    let num_arrangements = count_arrangements(specs);
+    if num_arrangements > max_arrangements as u128 {
+        return Err(format!(
+            "The cards make {num_arrangements} arrangements, more than the limit of {max_arrangements}"
+        ));
+    }
+
+    let (fixed, free): (Vec<&CardSpec>, Vec<&CardSpec>) =
+        specs.iter().partition(|spec| spec.position.is_some());
+    let free_slots: Vec<usize> = (0..specs.len())
+        .filter(|i| fixed.iter().all(|spec| spec.position != Some(*i)))
+        .collect();
+
+    let mut seen: HashSet<Pile> = HashSet::new();
+    let mut result: Vec<Pile> = Vec::new();
+    for order in free.iter().permutations(free.len()) {
+        let mut slots: Vec<&CardSpec> = vec![&specs[0]; specs.len()];
+        for spec in &fixed {
+            slots[spec.position.unwrap()] = spec;
+        }
+        for (slot, spec) in free_slots.iter().zip(order) {
+            slots[*slot] = spec;
+        }
+
+        for cards in slots
+            .iter()
+            .map(|spec| faces_for_spec(spec))
+            .multi_cartesian_product()
+        {
+            let pile: Pile = cards.into_iter().collect();
+            if seen.insert(pile.clone()) {
+                result.push(pile);
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_card_specs() {
+        let specs = parse_card_specs("15? > 18 5b 2B@0").unwrap();
+        assert_eq!(
+            specs,
+            vec![
+                CardSpec {
+                    card_id: 15,
+                    face: None,
+                    position: None
+                },
+                CardSpec {
+                    card_id: 18,
+                    face: Some(FaceKey::A),
+                    position: None
+                },
+                CardSpec {
+                    card_id: 5,
+                    face: Some(FaceKey::B),
+                    position: None
+                },
+                CardSpec {
+                    card_id: 2,
+                    face: Some(FaceKey::B),
+                    position: Some(0)
+                },
+            ]
+        );
+
+        assert!(parse_card_specs("").is_err());
+        assert!(parse_card_specs("1 1B").is_err());
+        assert!(parse_card_specs("1@0 2@0").is_err());
+        assert!(parse_card_specs("1 2@2").is_err());
+        assert!(parse_card_specs("1 2E").is_err());
+        assert_eq!(
+            parse_card_specs("1 2@2").unwrap_err(),
+            "Position 2 is past the bottom of a 2 card pile at character 3"
+        );
+    }
+
+    #[test]
+    fn test_enumerate_arrangements() {
+        let max = DEFAULT_MAX_ARRANGEMENTS;
+        let piles = enumerate_arrangements(&parse_card_specs("1 2 6").unwrap(), max).unwrap();
+        assert_eq!(piles.len(), 6);
+        assert!(piles.contains(&string_to_pile("6A 1A 2A")));
+
+        let piles = enumerate_arrangements(&parse_card_specs("1 2 6@0 7").unwrap(), max).unwrap();
+        assert_eq!(piles.len(), 6);
+        assert!(piles.iter().all(|pile| pile[0].get_card_id() == 6));
+
+        let piles = enumerate_arrangements(&parse_card_specs("1? 2@1").unwrap(), max).unwrap();
+        assert_eq!(piles.len(), 4);
+        assert!(piles.contains(&string_to_pile("1C 2A")));
+
+        // Swapping the two dummies makes the same pile
+        let specs = parse_card_specs("0 0 1").unwrap();
+        assert_eq!(count_arrangements(&specs), 6);
+        let piles = enumerate_arrangements(&specs, max).unwrap();
+        assert_eq!(piles.len(), 3);
+        assert!(piles.contains(&string_to_pile("0A 1A 0A")));
+    }
+
+    #[test]
+    fn test_arrangement_limit() {
+        let specs = parse_card_specs("1? 2? 6 7@0").unwrap();
+        assert_eq!(count_arrangements(&specs), 96);
+        assert_eq!(enumerate_arrangements(&specs, 96).unwrap().len(), 96);
+        assert!(enumerate_arrangements(&specs, 95).is_err());
+
+        // Refused before any pile is made
+        let specs = parse_card_specs("1? 2? 3? 4? 5? 6? 7? 8? 9?").unwrap();
+        assert_eq!(count_arrangements(&specs), 362_880 * 262_144);
+        assert!(enumerate_arrangements(&specs, DEFAULT_MAX_ARRANGEMENTS).is_err());
+    }
+}
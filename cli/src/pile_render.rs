@@ -0,0 +1,328 @@
+use handy_core::game::*;
+use handy_core::utils::*;
+use serde::Deserialize;
+use std::fmt::Write;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentFormat {
+    // For forum posts
+    Markdown,
+    // A standalone page
+    Html,
+    // One strip of cards per turn
+    Svg,
+}
+
+impl DocumentFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "md" | "markdown" => Some(Self::Markdown),
+            "html" | "htm" => Some(Self::Html),
+            "svg" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+}
+
+/// One activation of a solution or game
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderedTurn {
+    // Like "1A: Row 2", None if the activation didn't pick a row
+    pub chosen_row: Option<String>,
+    // As printed by format_event_for_cli, without the row pick
+    pub events: Vec<String>,
+    // The pile after the activation
+    pub pile: Pile,
+}
+
+/// The parts of solve --format json that make up the path
+#[derive(Deserialize, Clone, Debug)]
+struct SolvedPath {
+    start_pile: String,
+    steps: Vec<SolvedPathStep>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct SolvedPathStep {
+    pile: String,
+}
+
+/// Reads the output of solve --format json, or a game record with one pile per line
+pub fn parse_rendered_path(contents: &str) -> Result<Vec<Pile>, String> {
+    if contents.trim_start().starts_with('{') {
+        let solved: SolvedPath = serde_json::from_str(contents)
+            .map_err(|err| format!("Could not parse solve: {err}"))?;
+        return std::iter::once(&solved.start_pile)
+            .chain(solved.steps.iter().map(|step| &step.pile))
+            .map(|pile| string_to_pile_result(pile))
+            .collect();
+    }
+
+    let piles = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(string_to_pile_result)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("Could not parse pile: {err}"))?;
+    if piles.is_empty() {
+        return Err("No piles to render".to_owned());
+    }
+    Ok(piles)
+}
+
+/// Turns that the engine can't reproduce are kept, with no events
+pub fn rendered_turns(path: &[Pile]) -> Vec<RenderedTurn> {
+    path.windows(2)
+        .map(|piles| {
+            let events = find_events_between_piles(&piles[0], &piles[1]).unwrap_or_default();
+            let chosen_row = events
+                .iter()
+                .find(|event| matches!(event, Event::PickRow(..)))
+                .map(format_event_for_cli);
+            RenderedTurn {
+                chosen_row,
+                events: events
+                    .iter()
+                    .filter(|event| !matches!(event, Event::PickRow(..)))
+                    .map(format_event_for_cli)
+                    .collect(),
+                pile: piles[1].clone(),
+            }
+        })
+        .collect()
+}
+
+fn allegiance_colour(allegiance: Allegiance) -> &'static str {
+    match allegiance {
+        Allegiance::Hero => "#4673A6",
+        Allegiance::Monster => "#942A3D",
+        Allegiance::Werewolf => "#B8860B",
+        Allegiance::Rat => "#636262",
+        Allegiance::Neutral => "#4D9116",
+        Allegiance::None => "#2B2A29",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn turn_heading(turn_number: usize, turn: &RenderedTurn) -> String {
+    match &turn.chosen_row {
+        Some(row) => format!("Turn {turn_number}: {row}"),
+        None => format!("Turn {turn_number}"),
+    }
+}
+
+/// Also shows what the card is, since cards are only coloured by allegiance
+fn card_title(card: &CardPtr) -> String {
+    let face = card.get_active_face();
+    format!(
+        "{:?} {:?}, {:?} health",
+        card.get_card_def().class,
+        face.allegiance,
+        face.health
+    )
+}
+
+pub fn render_markdown(title: &str, path: &[Pile]) -> String {
+    let mut doc = format!("## {title}\n\nStart: `{}`\n", pile_to_string(&path[0]));
+    for (i, turn) in rendered_turns(path).iter().enumerate() {
+        let _ = write!(doc, "\n**{}**\n\n", turn_heading(i + 1, turn));
+        for event in &turn.events {
+            let _ = writeln!(doc, "- {event}");
+        }
+        let _ = writeln!(doc, "\n`{}`", pile_to_string(&turn.pile));
+    }
+    doc
+}
+
+fn html_pile(pile: &Pile) -> String {
+    let cards: Vec<String> = pile
+        .iter()
+        .map(|card| {
+            let is_exhausted = card.get_active_face().health == Health::Empty;
+            format!(
+                r#"<span class="card{}" style="background: {}" title="{}">{:?}</span>"#,
+                if is_exhausted { " exhausted" } else { "" },
+                allegiance_colour(card.get_active_face().allegiance),
+                escape_html(&card_title(card)),
+                card,
+            )
+        })
+        .collect();
+    format!(r#"<div class="pile">{}</div>"#, cards.concat())
+}
+
+const HTML_STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #2b2a29; }
+.pile { display: flex; gap: 4px; margin: 0.5em 0 1.5em; }
+.card { color: #fefefe; padding: 0.4em 0.6em; border-radius: 4px; font-weight: bold; }
+.card.exhausted { opacity: 0.4; }
+ol { margin: 0; color: #555; }";
+
+pub fn render_html(title: &str, path: &[Pile]) -> String {
+    let title = escape_html(title);
+    let mut doc = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n{HTML_STYLE}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n<h2>Start</h2>\n{}\n",
+        html_pile(&path[0])
+    );
+    for (i, turn) in rendered_turns(path).iter().enumerate() {
+        let _ = writeln!(doc, "<h2>{}</h2>", escape_html(&turn_heading(i + 1, turn)));
+        if !turn.events.is_empty() {
+            let events: Vec<String> = turn
+                .events
+                .iter()
+                .map(|event| format!("<li>{}</li>", escape_html(event)))
+                .collect();
+            let _ = writeln!(doc, "<ol>{}</ol>", events.concat());
+        }
+        let _ = writeln!(doc, "{}", html_pile(&turn.pile));
+    }
+    doc.push_str("</body>\n</html>\n");
+    doc
+}
+
+const SVG_CARD_WIDTH: usize = 44;
+const SVG_CARD_HEIGHT: usize = 30;
+const SVG_GAP: usize = 4;
+const SVG_LABEL_HEIGHT: usize = 20;
+const SVG_ROW_HEIGHT: usize = SVG_LABEL_HEIGHT + SVG_CARD_HEIGHT + 12;
+// Room for the turn labels of short piles
+const SVG_MIN_WIDTH: usize = 240;
+
+fn svg_pile(doc: &mut String, label: &str, tooltip: &str, pile: &Pile, y: usize) {
+    let _ = writeln!(doc, "<g><title>{}</title>", escape_html(tooltip));
+    let _ = writeln!(
+        doc,
+        r#"<text x="{SVG_GAP}" y="{}" font-size="13">{}</text>"#,
+        y + SVG_LABEL_HEIGHT - 6,
+        escape_html(label)
+    );
+    for (i, card) in pile.iter().enumerate() {
+        let x = SVG_GAP + i * (SVG_CARD_WIDTH + SVG_GAP);
+        let card_y = y + SVG_LABEL_HEIGHT;
+        let opacity = match card.get_active_face().health {
+            Health::Empty => "0.4",
+            _ => "1",
+        };
+        let _ = writeln!(
+            doc,
+            "<rect x=\"{x}\" y=\"{card_y}\" width=\"{SVG_CARD_WIDTH}\" \
+             height=\"{SVG_CARD_HEIGHT}\" rx=\"4\" fill=\"{}\" opacity=\"{opacity}\"/>",
+            allegiance_colour(card.get_active_face().allegiance),
+        );
+        let _ = writeln!(
+            doc,
+            "<text x=\"{}\" y=\"{}\" font-size=\"13\" font-weight=\"bold\" fill=\"#FEFEFE\" \
+             text-anchor=\"middle\">{:?}</text>",
+            x + SVG_CARD_WIDTH / 2,
+            card_y + SVG_CARD_HEIGHT / 2 + 5,
+            card,
+        );
+    }
+    doc.push_str("</g>\n");
+}
+
+/// Hovering a turn shows its events
+pub fn render_svg(title: &str, path: &[Pile]) -> String {
+    let turns = rendered_turns(path);
+    let max_pile_len = path.iter().map(|pile| pile.len()).max().unwrap_or(0);
+    let width = (SVG_GAP + max_pile_len * (SVG_CARD_WIDTH + SVG_GAP)).max(SVG_MIN_WIDTH);
+    let height = (turns.len() + 1) * SVG_ROW_HEIGHT;
+
+    let mut doc = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         font-family=\"sans-serif\">"
+    );
+    let _ = writeln!(doc, "\n<title>{}</title>", escape_html(title));
+    svg_pile(&mut doc, "Start", title, &path[0], 0);
+    for (i, turn) in turns.iter().enumerate() {
+        svg_pile(
+            &mut doc,
+            &turn_heading(i + 1, turn),
+            &turn.events.join("\n"),
+            &turn.pile,
+            (i + 1) * SVG_ROW_HEIGHT,
+        );
+    }
+    doc.push_str("</svg>\n");
+    doc
+}
+
+pub fn render_document(format: DocumentFormat, title: &str, path: &[Pile]) -> String {
+    match format {
+        DocumentFormat::Markdown => render_markdown(title, path),
+        DocumentFormat::Html => render_html(title, path),
+        DocumentFormat::Svg => render_svg(title, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_turn_path() -> Vec<Pile> {
+        let start_pile = string_to_pile("1A 2A 6A 7A");
+        let child = resolve_top_card(&GameStateWithEventLog::new(start_pile.clone()))
+            .into_iter()
+            .next()
+            .unwrap();
+        vec![start_pile, child.pile]
+    }
+
+    #[test]
+    fn test_parse_rendered_path() {
+        let path = parse_rendered_path("1A 2A 6A 7A\n\n2A 6A 7A 1A\n").unwrap();
+        assert_eq!(path.len(), 2);
+
+        let json = r#"{"solver": "a-star", "start_pile": "1A 2A",
+            "steps": [{"events": [], "pile": "2A 1A"}]}"#;
+        let path = parse_rendered_path(json).unwrap();
+        assert_eq!(path, vec![string_to_pile("1A 2A"), string_to_pile("2A 1A")]);
+
+        assert!(parse_rendered_path("\n").is_err());
+        assert!(parse_rendered_path("{\"start_pile\": 1}").is_err());
+    }
+
+    #[test]
+    fn test_rendered_turns() {
+        let path = first_turn_path();
+        let turns = rendered_turns(&path);
+        assert_eq!(turns.len(), 1);
+        assert!(turns[0].chosen_row.as_ref().unwrap().starts_with("1A: Row"));
+        assert!(turns[0].events.iter().all(|event| !event.contains("Row")));
+        assert_eq!(turns[0].pile, path[1]);
+
+        // Not one activation apart
+        let turns = rendered_turns(&[string_to_pile("1A 2A"), string_to_pile("1A 2A")]);
+        assert_eq!(turns[0].chosen_row, None);
+        assert!(turns[0].events.is_empty());
+    }
+
+    #[test]
+    fn test_render_documents() {
+        let path = first_turn_path();
+        let markdown = render_markdown("Warrior <3", &path);
+        assert!(markdown.starts_with("## Warrior <3\n"));
+        assert!(markdown.contains("`1A 2A 6A 7A`"));
+        assert!(markdown.contains("**Turn 1: 1A: Row"));
+
+        let html = render_html("Warrior <3", &path);
+        assert!(html.contains("<title>Warrior &lt;3</title>"));
+        assert_eq!(html.matches(r#"<span class="card"#).count(), 8);
+        assert!(html.contains(allegiance_colour(Allegiance::Hero)));
+
+        let svg = render_svg("Warrior", &path);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect").count(), 8);
+        assert_eq!(
+            DocumentFormat::from_extension("svg"),
+            Some(DocumentFormat::Svg)
+        );
+    }
+}
@@ -0,0 +1,459 @@
+use handy_core::game::end_game::{is_game_winner, GameEndCheckType};
+use handy_core::game::*;
+use handy_core::solver::*;
+use handy_core::utils::*;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+struct SessionSnapshot {
+    piles: Vec<Pile>,
+    events: Vec<Event>,
+    current_pile: Pile,
+}
+
+/// A game played one choice at a time, with undo and redo of every choice made.
+/// Choices with a single option are made automatically, and can't be undone on their own.
+pub struct PlaySession {
+    pub game_end_check_type: GameEndCheckType,
+    // The start pile, followed by the pile after each finished activation
+    piles: Vec<Pile>,
+    // Events chosen so far in the current activation
+    events: Vec<Event>,
+    // The pile after the last chosen event
+    current_pile: Pile,
+    // Every way the current activation can play out
+    states: Vec<GameStateWithPileTrackedEventLog>,
+    undo_stack: Vec<SessionSnapshot>,
+    redo_stack: Vec<SessionSnapshot>,
+    // Analysis of the current activation, kept until the activation changes
+    analysis: Option<(Pile, Vec<MoveAnalysis>)>,
+}
+
+impl PlaySession {
+    pub fn new(start_pile: Pile, game_end_check_type: GameEndCheckType) -> Self {
+        Self::from_piles(vec![start_pile], game_end_check_type)
+    }
+
+    fn from_piles(piles: Vec<Pile>, game_end_check_type: GameEndCheckType) -> Self {
+        let mut session = Self {
+            game_end_check_type,
+            current_pile: piles.last().unwrap().clone(),
+            piles,
+            events: vec![],
+            states: vec![],
+            undo_stack: vec![],
+            redo_stack: vec![],
+            analysis: None,
+        };
+        session.restore(session.snapshot());
+        session.make_forced_choices();
+        session
+    }
+
+    /// The pile the current activation started from
+    pub fn activation_pile(&self) -> &Pile {
+        self.piles.last().unwrap()
+    }
+
+    pub fn current_pile(&self) -> &Pile {
+        &self.current_pile
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn record(&self) -> GameRecord {
+        GameRecord::from_piles(&self.piles).unwrap()
+    }
+
+    pub fn win_type(&self) -> WinType {
+        is_game_winner(self.activation_pile(), self.game_end_check_type)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.win_type().is_over()
+    }
+
+    /// The next event to pick, or the final pile once the activation has no events left to pick
+    pub fn options(&self) -> Vec<PrefixResult> {
+        if self.is_over() {
+            return vec![];
+        }
+        find_next_events_matching_prefix(&self.states, &self.events)
+    }
+
+    pub fn choose(&mut self, choice: usize) -> Result<(), String> {
+        let options = self.options();
+        if options.is_empty() {
+            return Err("The game is over".to_owned());
+        }
+        let option = options
+            .get(choice)
+            .ok_or_else(|| format!("No option {choice}, pick 0 to {}", options.len() - 1))?;
+        self.undo_stack.push(self.snapshot());
+        self.redo_stack.clear();
+        self.apply(option.clone());
+        self.make_forced_choices();
+        Ok(())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn undo(&mut self) -> Result<(), String> {
+        let snapshot = self.undo_stack.pop().ok_or("Nothing to undo")?;
+        self.redo_stack.push(self.snapshot());
+        self.restore(snapshot);
+        Ok(())
+    }
+
+    pub fn redo(&mut self) -> Result<(), String> {
+        let snapshot = self.redo_stack.pop().ok_or("Nothing to redo")?;
+        self.undo_stack.push(self.snapshot());
+        self.restore(snapshot);
+        Ok(())
+    }
+
+    /// The index of the option leading to the best outcome, and how good that outcome is
+    pub fn hint<M: ModelT + Clone + 'static>(
+        &mut self,
+        model: &M,
+        max_iters_per_move: usize,
+    ) -> Option<(usize, PileEval)> {
+        let options = self.options();
+        let analysis = self.analysis(model, max_iters_per_move);
+        options
+            .iter()
+            .enumerate()
+            .filter_map(|(i, option)| {
+                let final_piles = self.final_piles_for_option(option);
+                analysis
+                    .iter()
+                    .filter(|a| final_piles.contains(&a.pile))
+                    .map(|a| a.eval)
+                    .min()
+                    .map(|eval| (i, eval))
+            })
+            .min_by_key(|(_, eval)| *eval)
+    }
+
+    /// Activations needed to win from here, counting the current one if it's not done yet
+    pub fn eval<M: ModelT + Clone + 'static>(
+        &mut self,
+        model: &M,
+        max_iters_per_move: usize,
+    ) -> PileEval {
+        match self.win_type() {
+            WinType::Win => return PileEval::Win(0),
+            WinType::Lose => return PileEval::Loss,
+            WinType::Unresolved => (),
+        }
+        self.hint(model, max_iters_per_move)
+            .map_or(PileEval::Unknown, |(_, eval)| eval)
+    }
+
+    /// Saves every finished activation. Choices in the current activation aren't saved.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        write_game_record(&self.record(), path)
+    }
+
+    /// Continues from the last pile of a saved game. Undo history is lost.
+    pub fn load(path: &Path, game_end_check_type: GameEndCheckType) -> Result<Self, String> {
+        Ok(Self::from_piles(
+            read_game_record(path)?.all_piles(),
+            game_end_check_type,
+        ))
+    }
+
+    fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot {
+            piles: self.piles.clone(),
+            events: self.events.clone(),
+            current_pile: self.current_pile.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: SessionSnapshot) {
+        let is_new_activation =
+            snapshot.piles.last() != self.piles.last() || self.states.is_empty();
+        self.piles = snapshot.piles;
+        self.events = snapshot.events;
+        self.current_pile = snapshot.current_pile;
+        if is_new_activation {
+            self.start_activation();
+        }
+    }
+
+    fn start_activation(&mut self) {
+        self.states = if self.is_over() {
+            vec![]
+        } else {
+            let state = GameStateWithPileTrackedEventLog::new(self.activation_pile().clone());
+            resolve_top_card_starting_with_prefix_dedupe_excess(&state, &self.events)
+        };
+    }
+
+    fn apply(&mut self, option: PrefixResult) {
+        match option {
+            PrefixResult::Event(event, pile) => {
+                self.events.push(event);
+                self.current_pile = pile;
+            }
+            PrefixResult::Pile(pile) => {
+                self.piles.push(pile.clone());
+                self.events.clear();
+                self.current_pile = pile;
+                self.start_activation();
+            }
+        }
+    }
+
+    fn make_forced_choices(&mut self) {
+        loop {
+            let options = self.options();
+            if options.len() != 1 {
+                return;
+            }
+            self.apply(options[0].clone());
+        }
+    }
+
+    fn final_piles_for_option(&self, option: &PrefixResult) -> Vec<Pile> {
+        let event = match option {
+            PrefixResult::Pile(pile) => return vec![pile.clone()],
+            PrefixResult::Event(event, _) => event,
+        };
+        let mut prefix = self.events.clone();
+        prefix.push(event.clone());
+        self.states
+            .iter()
+            .filter(|state| {
+                state.events.len() >= prefix.len()
+                    && state
+                        .events
+                        .iter()
+                        .zip(prefix.iter())
+                        .all(|((_, a), b)| a == b)
+            })
+            .map(|state| state.pile.clone())
+            .collect()
+    }
+
+    fn analysis<M: ModelT + Clone + 'static>(
+        &mut self,
+        model: &M,
+        max_iters_per_move: usize,
+    ) -> Vec<MoveAnalysis> {
+        let activation_pile = self.activation_pile().clone();
+        match &self.analysis {
+            Some((pile, analysis)) if pile == &activation_pile => analysis.clone(),
+            _ => {
+                let analysis = analyze_moves(
+                    &activation_pile,
+                    model,
+                    self.game_end_check_type,
+                    max_iters_per_move,
+                );
+                self.analysis = Some((activation_pile, analysis.clone()));
+                analysis
+            }
+        }
+    }
+}
+
+/// One pile per line: the start pile, followed by the pile after each activation
+pub fn read_game_record(path: &Path) -> Result<GameRecord, String> {
+    let record_str = fs::read_to_string(path)
+        .map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+    let piles = record_str
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(string_to_pile_result)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("Could not parse pile: {err}"))?;
+    GameRecord::from_piles(&piles).ok_or_else(|| "Game record is empty".to_owned())
+}
+
+pub fn write_game_record(record: &GameRecord, path: &Path) -> Result<(), String> {
+    let lines: Vec<String> = record
+        .all_piles()
+        .iter()
+        .map(|pile| pile_to_string(pile) + "\n")
+        .collect();
+    fs::write(path, lines.concat())
+        .map_err(|err| format!("Could not write {}: {err}", path.display()))
+}
+
+fn format_row(row: &Row) -> String {
+    let mut result = row
+        .actions
+        .iter()
+        .map(format_wrapped_action)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if let Some(condition) = &row.condition {
+        result = format!("If {condition:?}: {result}");
+    }
+    if let Some(mandatory) = &row.mandatory {
+        result = format!("{result}, then {mandatory:?}");
+    }
+    if row.is_mandatory {
+        result += " (mandatory)";
+    }
+    result
+}
+
+/// Every face of a card, with the face it's showing in the pile marked
+pub fn format_card_faces(pile: &Pile, card_id: CardId) -> Result<String, String> {
+    let card = pile
+        .iter()
+        .find(|card| card.get_card_id() == card_id)
+        .ok_or_else(|| format!("Card {card_id} isn't in the pile"))?;
+    let card_def = card.get_card_def();
+
+    let mut lines = vec![format!("{} ({:?})", card_def.id, card_def.class)];
+    for (key, face) in card_def.faces.iter() {
+        let marker = if key == card.get_card_face() {
+            "*"
+        } else {
+            " "
+        };
+        lines.push(format!(
+            "{marker}{key}: {:?} {:?}",
+            face.allegiance, face.health
+        ));
+        if let Some(reaction) = &face.reaction {
+            lines.push(format!("    Reaction: {reaction:?}"));
+        }
+        if let Some(swarm) = &face.swarm {
+            lines.push(format!("    Swarm: {}", format_row(swarm)));
+        }
+        for row in &face.rows {
+            lines.push(format!("    {}", format_row(row)));
+        }
+        for row in &face.assists {
+            lines.push(format!("    Assist: {}", format_row(row)));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play_to_end(session: &mut PlaySession) {
+        while !session.is_over() {
+            session.choose(0).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_choose_after_game_over() {
+        let mut session =
+            PlaySession::new(string_to_pile("1A 2A 6A 7A"), GameEndCheckType::Standard);
+        play_to_end(&mut session);
+        assert_eq!(session.choose(0), Err("The game is over".to_owned()));
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut session =
+            PlaySession::new(string_to_pile("1A 2A 6A 7A"), GameEndCheckType::Standard);
+        let start = session.snapshot();
+        assert!(!session.can_undo());
+        assert!(session.undo().is_err());
+
+        session.choose(0).unwrap();
+        let after_first = session.snapshot();
+        session.choose(0).unwrap();
+
+        session.undo().unwrap();
+        assert_eq!(session.snapshot(), after_first);
+        session.undo().unwrap();
+        assert_eq!(session.snapshot(), start);
+        assert!(session.can_redo());
+
+        session.redo().unwrap();
+        assert_eq!(session.snapshot(), after_first);
+
+        // A new choice drops what could be redone
+        session.choose(1).unwrap();
+        assert!(!session.can_redo());
+        assert!(session.choose(1000).is_err());
+    }
+
+    #[test]
+    fn test_play_record() {
+        let start_pile = string_to_pile("1A 2A 6A 7A");
+        let mut session = PlaySession::new(start_pile.clone(), GameEndCheckType::Standard);
+        play_to_end(&mut session);
+
+        let record = session.record();
+        assert_eq!(record.start_pile, start_pile);
+        assert!(!record.piles.is_empty());
+        assert_eq!(record.piles.last(), Some(session.activation_pile()));
+        assert!(session.options().is_empty());
+        for piles in record.all_piles().windows(2) {
+            assert!(find_events_between_piles(&piles[0], &piles[1]).is_some());
+        }
+    }
+
+    #[test]
+    fn test_hint_and_eval() {
+        let start_pile = string_to_pile("1A 2A 6A 7A");
+        let model = Model::new_empty_for_pile(&start_pile);
+        let mut session = PlaySession::new(start_pile, GameEndCheckType::Standard);
+
+        // Following the hint should keep the distance to win going down by one each activation
+        let PileEval::Win(mut depth) = session.eval(&model, 100_000) else {
+            panic!("Expected a win");
+        };
+        assert_eq!(depth, 10);
+        while !session.is_over() {
+            let (choice, eval) = session.hint(&model, 100_000).unwrap();
+            assert_eq!(eval, PileEval::Win(depth));
+            let num_piles = session.record().piles.len();
+            session.choose(choice).unwrap();
+            if session.record().piles.len() > num_piles {
+                depth -= 1;
+            }
+        }
+        assert_eq!(depth, 0);
+        assert_eq!(session.win_type(), WinType::Win);
+        assert_eq!(session.eval(&model, 100_000), PileEval::Win(0));
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut session =
+            PlaySession::new(string_to_pile("1A 2A 6A 7A"), GameEndCheckType::Standard);
+        session.choose(0).unwrap();
+        session.choose(0).unwrap();
+
+        let path = std::env::temp_dir().join("handy_play_session_test.txt");
+        session.save(&path).unwrap();
+        let loaded = PlaySession::load(&path, GameEndCheckType::Standard).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.record(), session.record());
+        assert!(loaded.events().is_empty());
+        assert!(!loaded.can_undo());
+    }
+
+    #[test]
+    fn test_format_card_faces() {
+        let pile = string_to_pile("1A 2A 6B 7A");
+        let faces = format_card_faces(&pile, 6).unwrap();
+        assert!(faces.contains("*B:"), "{faces}");
+        assert_eq!(faces.lines().filter(|l| l.contains('*')).count(), 1);
+        assert!(format_card_faces(&pile, 3).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+use crate::{
+    find_solutions, get_fully_random_pile, memoized_children, solution_steps, ChildrenMemo,
+    SearchObjective, SolutionStep, VictoryConditionChecker, VictoryConditionResult,
+};
+use handy_core::game::*;
+use handy_core::utils::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ObjectiveKind {
+    Win,
+    SurviveUntilTop,
+    Exhaust,
+}
+
+/// What kind of puzzles to look for
+#[derive(Clone, Debug, PartialEq)]
+pub struct PuzzleSpec {
+    pub hero: Class,
+    pub monster: Class,
+    // Cards in the puzzle pile, picked from a start pile of the two classes
+    pub num_cards: usize,
+    // The puzzle has to be solved in exactly this many activations
+    pub turns: usize,
+    // Picked at random for each candidate
+    pub objective_kinds: Vec<ObjectiveKind>,
+    // Random faces rather than the faces cards start on
+    pub fully_random: bool,
+    // Skip puzzles with a lower difficulty score
+    pub min_score: Option<f32>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct PuzzleDifficulty {
+    pub turns: usize,
+    // Mean distinct outcomes of an activation, over the piles reachable before the last turn
+    pub branching_factor: f32,
+    // Lines that meet the objective, but one turn too late
+    pub near_misses: usize,
+    // turns * log2(branching_factor) + log2(1 + near_misses)
+    pub score: f32,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Puzzle {
+    // Top card first, in the same format --pile takes
+    pub pile: String,
+    // As posted in the BoardGameGeek puzzle thread, top card last
+    pub bgg_notation: String,
+    pub objective: SearchObjective,
+    pub description: String,
+    pub difficulty: PuzzleDifficulty,
+    pub solution: Vec<SolutionStep>,
+}
+
+/// The puzzle thread lists the top card last, like "5C > 6D > 2B > 9B"
+pub fn to_bgg_notation(pile: &Pile) -> String {
+    format_pile_notation(pile, PileDialect::Reversed)
+}
+
+pub fn from_bgg_notation(notation: &str) -> Result<Pile, String> {
+    parse_pile_notation(notation, PileDialect::Reversed).map_err(|err| format!("{err}"))
+}
+
+/// The only solution, if there's exactly one in that many turns and none in fewer
+pub fn find_unique_solution(
+    pile: &Pile,
+    objective: SearchObjective,
+    turns: usize,
+    memo: &mut ChildrenMemo,
+) -> Option<Vec<Pile>> {
+    let checker = objective.checker();
+    if !matches!(
+        checker.condition_result(pile),
+        VictoryConditionResult::Continue
+    ) {
+        return None;
+    }
+    for shorter_turns in 1..turns {
+        if !find_solutions(pile, checker.as_ref(), Some(shorter_turns), memo).is_empty() {
+            return None;
+        }
+    }
+
+    let mut solutions = find_solutions(pile, checker.as_ref(), Some(turns), memo);
+    match solutions.len() {
+        1 => solutions.pop(),
+        _ => None,
+    }
+}
+
+/// Piles reachable in fewer than turns activations that haven't met or failed the objective
+fn open_piles_before_last_turn(
+    pile: &Pile,
+    checker: &dyn VictoryConditionChecker,
+    turns: usize,
+    memo: &mut ChildrenMemo,
+) -> HashSet<Pile> {
+    let mut seen: HashSet<Pile> = HashSet::from([pile.clone()]);
+    let mut frontier = vec![pile.clone()];
+    for _ in 1..turns {
+        let mut next_frontier = vec![];
+        for parent in &frontier {
+            for child in memoized_children(parent, memo).clone() {
+                if matches!(
+                    checker.condition_result(&child),
+                    VictoryConditionResult::Continue
+                ) && seen.insert(child.clone())
+                {
+                    next_frontier.push(child);
+                }
+            }
+        }
+        frontier = next_frontier;
+    }
+    seen
+}
+
+pub fn rate_puzzle(
+    pile: &Pile,
+    objective: SearchObjective,
+    turns: usize,
+    memo: &mut ChildrenMemo,
+) -> PuzzleDifficulty {
+    let checker = objective.checker();
+    let open_piles = open_piles_before_last_turn(pile, checker.as_ref(), turns, memo);
+    let total_children: usize = open_piles
+        .iter()
+        .map(|open_pile| memoized_children(open_pile, memo).len())
+        .sum();
+    let branching_factor = total_children as f32 / open_piles.len() as f32;
+    let near_misses = find_solutions(pile, checker.as_ref(), Some(turns + 1), memo).len();
+
+    PuzzleDifficulty {
+        turns,
+        branching_factor,
+        near_misses,
+        score: turns as f32 * branching_factor.max(1.0).log2() + (1.0 + near_misses as f32).log2(),
+    }
+}
+
+/// A puzzle if the pile has a unique solution for the objective, in exactly that many turns
+pub fn try_make_puzzle(
+    pile: &Pile,
+    objective: SearchObjective,
+    turns: usize,
+    memo: &mut ChildrenMemo,
+) -> Option<Puzzle> {
+    let solution = find_unique_solution(pile, objective, turns, memo)?;
+    Some(Puzzle {
+        pile: pile_to_string(pile),
+        bgg_notation: to_bgg_notation(pile),
+        objective,
+        description: format!("{objective} in exactly {turns} turns"),
+        difficulty: rate_puzzle(pile, objective, turns, memo),
+        solution: solution_steps(&solution),
+    })
+}
+
+fn random_objective<R: Rng>(
+    pile: &Pile,
+    kinds: &[ObjectiveKind],
+    rng: &mut R,
+) -> Option<SearchObjective> {
+    let card_id = pile.choose(rng)?.get_card_id();
+    Some(match kinds.choose(rng)? {
+        ObjectiveKind::Win => SearchObjective::Win,
+        ObjectiveKind::Exhaust => SearchObjective::Exhaust { card_id },
+        ObjectiveKind::SurviveUntilTop => SearchObjective::SurviveUntilTop { card_id },
+    })
+}
+
+/// A random subset of a start pile, in the order the cards were in
+pub fn random_puzzle_candidate<R: Rng>(
+    spec: &PuzzleSpec,
+    rng: &mut R,
+) -> Option<(Pile, SearchObjective)> {
+    let full_pile = if spec.fully_random {
+        get_fully_random_pile(spec.hero, spec.monster, rng)
+    } else {
+        get_start_from_classes(spec.hero, spec.monster, rng)
+    };
+    let num_cards = spec.num_cards.min(full_pile.len());
+    let mut indexes: Vec<usize> =
+        rand::seq::index::sample(rng, full_pile.len(), num_cards).into_vec();
+    indexes.sort();
+    let pile: Pile = indexes.into_iter().map(|i| full_pile[i]).collect();
+
+    let objective = random_objective(&pile, &spec.objective_kinds, rng)?;
+    Some((pile, objective))
+}
+
+/// Tries random candidates until there are enough puzzles or the attempts run out.
+/// The same pile and objective is only returned once.
+pub fn generate_puzzles<R: Rng>(
+    spec: &PuzzleSpec,
+    num_puzzles: usize,
+    max_attempts: usize,
+    rng: &mut R,
+    on_puzzle: &mut dyn FnMut(&Puzzle),
+) -> Vec<Puzzle> {
+    let mut seen: HashSet<(Pile, SearchObjective)> = HashSet::new();
+    let mut puzzles: Vec<Puzzle> = vec![];
+    for _ in 0..max_attempts {
+        if puzzles.len() >= num_puzzles {
+            break;
+        }
+        let Some(candidate) = random_puzzle_candidate(spec, rng) else {
+            continue;
+        };
+        if !seen.insert(candidate.clone()) {
+            continue;
+        }
+        // Candidates rarely share piles, so each gets its own memo
+        let mut memo: ChildrenMemo = HashMap::new();
+        let Some(puzzle) = try_make_puzzle(&candidate.0, candidate.1, spec.turns, &mut memo) else {
+            continue;
+        };
+        if spec
+            .min_score
+            .map_or(true, |min_score| puzzle.difficulty.score >= min_score)
+        {
+            on_puzzle(&puzzle);
+            puzzles.push(puzzle);
+        }
+    }
+    puzzles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_pcg::Pcg64;
+    use rand_seeder::Seeder;
+
+    #[test]
+    fn test_bgg_notation() {
+        let pile = string_to_pile("2B 5C 6D");
+        assert_eq!(to_bgg_notation(&pile), "6D > 5C > 2B");
+        assert_eq!(from_bgg_notation("6D > 5C > 2B").unwrap(), pile);
+    }
+
+    #[test]
+    fn test_make_puzzle() {
+        // Puzzle 2 from the puzzle thread
+        let pile = from_bgg_notation("15A > 18A > 5A > 2B > 4A").unwrap();
+        let objective = SearchObjective::Exhaust { card_id: 15 };
+        let mut memo: ChildrenMemo = HashMap::new();
+        // Two solutions in 3 turns
+        assert_eq!(try_make_puzzle(&pile, objective, 3, &mut memo), None);
+    }
+
+    #[test]
+    fn test_generate_puzzles() {
+        let spec = PuzzleSpec {
+            hero: Class::Warrior,
+            monster: Class::Ogre,
+            num_cards: 4,
+            turns: 2,
+            objective_kinds: vec![ObjectiveKind::Exhaust, ObjectiveKind::Win],
+            fully_random: true,
+            min_score: None,
+        };
+        let mut rng = Seeder::from("puzzles").make_rng::<Pcg64>();
+        let mut num_found = 0;
+        let puzzles = generate_puzzles(&spec, 3, 2000, &mut rng, &mut |_| num_found += 1);
+        assert!(!puzzles.is_empty());
+        assert_eq!(puzzles.len(), num_found);
+
+        for puzzle in puzzles {
+            let pile = string_to_pile(&puzzle.pile);
+            assert_eq!(pile.len(), 4);
+            assert_eq!(from_bgg_notation(&puzzle.bgg_notation).unwrap(), pile);
+            assert_eq!(puzzle.solution.len(), 2);
+            assert_eq!(puzzle.difficulty.turns, 2);
+            assert!(puzzle.difficulty.branching_factor >= 1.0);
+
+            let mut memo: ChildrenMemo = HashMap::new();
+            let solution = find_unique_solution(&pile, puzzle.objective, 2, &mut memo).unwrap();
+            assert_eq!(solution_steps(&solution), puzzle.solution);
+            assert!(find_unique_solution(&pile, puzzle.objective, 1, &mut memo).is_none());
+        }
+    }
+}
@@ -1,38 +1,226 @@
-use crate::get_model_for_pile;
+use crate::paths::{loss_cache_path_for_pile, Paths};
+use crate::solve_report::{write_progress_record, ProgressRecord};
+use crate::try_get_heuristic_for_pile;
+use crate::try_read_loss_cache_for_pile;
 use end_game::GameEndCheckType;
 use handy_core::game::*;
 use handy_core::solver::a_star::*;
+use handy_core::solver::loss_cache::LossCache;
+use handy_core::solver::model_t::ModelT;
 use handy_core::solver::tiny_pile::NoopPileStorageConverter;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 // use handy_core::solver::tiny_pile::{TinyPile, TinyPileConverter};
 
+/// Solver settings, where None keeps the solver's default
+#[derive(Clone, Debug, Default)]
+pub struct AStarOptions {
+    pub max_depth: Option<DepthType>,
+    pub g_bias: Option<f32>,
+    pub game_end_check_type: Option<GameEndCheckType>,
+    pub budget: SolverBudget,
+    pub loss_pruning: LossPruning,
+    // Known losses for the start pile's matchups, see LossCaches
+    pub loss_cache: Option<Arc<LossCache>>,
+}
+
+/// The known losses of every pile a command solves, each file read once. A file can hold
+/// MAX_LOSS_CACHE_PILES piles, too many to read again for every pile of a batch.
+#[derive(Default)]
+pub struct LossCaches {
+    by_path: Mutex<HashMap<String, Option<Arc<LossCache>>>>,
+}
+
+impl LossCaches {
+    /// None if there are no known losses for the pile
+    pub fn get(
+        &self,
+        paths: &Paths,
+        pile: &Pile,
+        game_end_check_type: GameEndCheckType,
+    ) -> Option<Arc<LossCache>> {
+        let path = loss_cache_path_for_pile(paths, pile, game_end_check_type);
+        self.by_path
+            .lock()
+            .unwrap()
+            .entry(path)
+            .or_insert_with(|| load_loss_cache(paths, pile, game_end_check_type))
+            .clone()
+    }
+}
+
+/// None if there are no known losses for the pile
+pub fn load_loss_cache(
+    paths: &Paths,
+    pile: &Pile,
+    game_end_check_type: GameEndCheckType,
+) -> Option<Arc<LossCache>> {
+    // The cache only speeds the search up, so a broken one is skipped rather than fatal
+    match try_read_loss_cache_for_pile(paths, pile, game_end_check_type) {
+        Ok(loss_cache) if !loss_cache.is_empty() => Some(Arc::new(loss_cache)),
+        Ok(_) => None,
+        Err(err) => {
+            eprintln!("Couldn't read the loss cache, solving without it: {err}");
+            None
+        }
+    }
+}
+
 pub fn run_a_star_solver(
+    paths: &Paths,
     start_pile: Pile,
-    max_depth: Option<DepthType>,
+    options: &AStarOptions,
     max_iters: Option<usize>,
-    g_bias: Option<f32>,
-    game_end_check_type: Option<GameEndCheckType>,
     should_print_solution: bool,
-) -> Vec<Pile> {
-    let model = get_model_for_pile(&start_pile);
+) -> Result<Vec<Pile>, String> {
+    let model = try_get_heuristic_for_pile(paths, &start_pile)?;
+    let a_star_solver = new_a_star_solver_with_model(start_pile, model, options);
+    let (path, _) = run_a_star_solver_to_end(a_star_solver, max_iters, should_print_solution);
+    Ok(path)
+}
 
+/// A solver for the pile with the options' known losses, for callers that pick their own
+/// heuristic
+pub fn new_a_star_solver_with_model(
+    start_pile: Pile,
+    model: Box<dyn ModelT>,
+    options: &AStarOptions,
+) -> AStarSolver<Pile, NoopPileStorageConverter> {
     // let mut a_star_solver =
     //     AStarSolver::<TinyPile, TinyPileConverter>::new(&vec![start_pile], Box::new(model));
 
     let mut a_star_solver =
-        AStarSolver::<Pile, NoopPileStorageConverter>::new(&vec![start_pile], Box::new(model));
-
-    if let Some(def_max_iters) = max_iters {
-        a_star_solver.set_max_iters(def_max_iters);
+        AStarSolver::<Pile, NoopPileStorageConverter>::new(&[start_pile], model);
+    if let Some(loss_cache) = &options.loss_cache {
+        a_star_solver.set_loss_cache(loss_cache.clone());
     }
-    if let Some(def_max_depth) = max_depth {
+
+    if let Some(def_max_depth) = options.max_depth {
         a_star_solver.set_max_depth(def_max_depth);
     }
-    if let Some(g_bias) = g_bias {
+    if let Some(g_bias) = options.g_bias {
         a_star_solver.set_g_bias(g_bias);
     }
-    if let Some(game_end_check_type) = game_end_check_type {
+    if let Some(game_end_check_type) = options.game_end_check_type {
         a_star_solver.set_game_end_check_type(game_end_check_type)
     }
+    a_star_solver.set_budget(options.budget);
+    a_star_solver.set_loss_pruning(options.loss_pruning);
+    a_star_solver
+}
+
+/// Reported while the solver runs, so callers can show progress their own way
+pub enum SolverEvent<'a> {
+    NewBest(&'a Pile),
+    // Every 100k iterations
+    Progress,
+    QueueTrimmed,
+    Done(AStarDoneReason),
+}
+
+pub struct AStarRunResult {
+    // Empty if no win was found
+    pub path: Vec<Pile>,
+    pub is_optimality_lost: bool,
+    pub done_reason: AStarDoneReason,
+}
+
+pub fn run_a_star_solver_to_end(
+    mut a_star_solver: AStarSolver<Pile, NoopPileStorageConverter>,
+    max_iters: Option<usize>,
+    should_print_solution: bool,
+) -> (Vec<Pile>, bool) {
+    let result =
+        run_a_star_solver_with_observer(&mut a_star_solver, max_iters, &mut |solver, event| {
+            print_solver_event(solver, &event, should_print_solution)
+        });
+    (result.path, result.is_optimality_lost)
+}
+
+pub fn print_solver_event(
+    a_star_solver: &AStarSolver<Pile, NoopPileStorageConverter>,
+    event: &SolverEvent,
+    should_print_solution: bool,
+) {
+    match event {
+        SolverEvent::NewBest(pile) => {
+            println!("New best solution: {}", a_star_solver.max_depth);
+            if should_print_solution {
+                a_star_solver.print_solution_from_pile(pile);
+            }
+        }
+        SolverEvent::Progress => {
+            println!(
+                "Iteration: {:?}, queue_size: {}, seen_size: {}, mem_mb: {}, f_score: {}, best_len: {} max_fscore: {}",
+                a_star_solver.total_iters,
+                a_star_solver.queue.len(),
+                a_star_solver.seen_states.len(),
+                a_star_solver.estimated_memory_bytes() >> 20,
+                a_star_solver.queue.peek().map_or(0.0, |e| e.0),
+                a_star_solver.max_depth,
+                a_star_solver.max_fscore,
+            );
+        }
+        SolverEvent::QueueTrimmed => println!("Trimming queue"),
+        SolverEvent::Done(reason) => {
+            println!("Stopping Solver: {:?}", reason);
+            if a_star_solver.total_loss_cache_hits > 0 {
+                println!(
+                    "Known losses pruned: {}",
+                    a_star_solver.total_loss_cache_hits
+                );
+            }
+            if a_star_solver.total_loss_prunes > 0 {
+                println!(
+                    "Likely losses pruned: {}. Solution may not be optimal",
+                    a_star_solver.total_loss_prunes
+                );
+            }
+            if a_star_solver.total_memory_trims > 0 {
+                println!(
+                    "Memory budget hit {} times. Solution may not be optimal",
+                    a_star_solver.total_memory_trims
+                );
+            }
+        }
+    }
+}
+
+/// Writes new bests and periodic progress as JSON lines on stderr
+pub fn write_solver_event_progress(
+    a_star_solver: &AStarSolver<Pile, NoopPileStorageConverter>,
+    event: &SolverEvent,
+    start_time: Instant,
+) {
+    let elapsed_ms = start_time.elapsed().as_secs_f32() * 1000.0;
+    let record = match event {
+        SolverEvent::NewBest(_) => ProgressRecord::NewBest {
+            iterations: a_star_solver.total_iters,
+            depth: a_star_solver.max_depth as usize,
+            elapsed_ms,
+        },
+        SolverEvent::Progress => ProgressRecord::Progress {
+            iterations: a_star_solver.total_iters,
+            queue_size: a_star_solver.queue.len(),
+            seen_size: a_star_solver.seen_states.len(),
+            mem_mb: a_star_solver.estimated_memory_bytes() >> 20,
+            best_depth: a_star_solver.max_depth as usize,
+            elapsed_ms,
+        },
+        SolverEvent::QueueTrimmed | SolverEvent::Done(_) => return,
+    };
+    write_progress_record(&record);
+}
+
+pub fn run_a_star_solver_with_observer(
+    a_star_solver: &mut AStarSolver<Pile, NoopPileStorageConverter>,
+    max_iters: Option<usize>,
+    observer: &mut dyn FnMut(&AStarSolver<Pile, NoopPileStorageConverter>, SolverEvent),
+) -> AStarRunResult {
+    if let Some(def_max_iters) = max_iters {
+        a_star_solver.set_max_iters(def_max_iters);
+    }
 
     let mut count: usize = 0;
     loop {
@@ -40,18 +228,22 @@ pub fn run_a_star_solver(
         let iter_result = a_star_solver.single_iter();
         match iter_result {
             AStarIterResult::Done(reason) => {
-                println!("Stopping Solver: {:?}", reason);
-                if let Some(best_win) = a_star_solver.best_win.clone() {
-                    let real_pile = a_star_solver.tiny_pile_to_pile(&best_win);
-                    return a_star_solver.unroll_state(real_pile);
-                }
-                return vec![];
+                observer(a_star_solver, SolverEvent::Done(reason));
+                let path = match a_star_solver.best_win.clone() {
+                    Some(best_win) => {
+                        let real_pile = a_star_solver.tiny_pile_to_pile(&best_win);
+                        a_star_solver.unroll_state(real_pile)
+                    }
+                    None => vec![],
+                };
+                return AStarRunResult {
+                    path,
+                    is_optimality_lost: a_star_solver.is_optimality_lost,
+                    done_reason: reason,
+                };
             }
             AStarIterResult::NewBest(pile) => {
-                println!("New best solution: {}", a_star_solver.max_depth);
-                if should_print_solution {
-                    a_star_solver.print_solution_from_pile(&pile);
-                }
+                observer(a_star_solver, SolverEvent::NewBest(&pile));
             }
             AStarIterResult::Continue(_) => {
                 if let Some(def_max_iter) = max_iters {
@@ -59,23 +251,53 @@ pub fn run_a_star_solver(
                     if queue_len > 2_000_000
                         && queue_len >> 2 > (def_max_iter - a_star_solver.total_iters)
                     {
-                        println!("Trimming queue");
+                        observer(a_star_solver, SolverEvent::QueueTrimmed);
                         a_star_solver.reset_queue_and_fscore();
                     }
                 }
                 if count > 100_000 {
-                    println!(
-                        "Iteration: {:?}, queue_size: {}, seen_size: {}, f_score: {}, best_len: {} max_fscore: {}",
-                        a_star_solver.total_iters,
-                        a_star_solver.queue.len(),
-                        a_star_solver.seen_states.len(),
-                        a_star_solver.queue.peek().map_or(0.0, |e| e.0),
-                        a_star_solver.max_depth,
-                        a_star_solver.max_fscore,
-                    );
+                    observer(a_star_solver, SolverEvent::Progress);
                     count = 0;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::write_loss_cache_for_pile;
+    use handy_core::utils::string_to_pile;
+    use std::fs;
+
+    #[test]
+    fn test_loss_caches_read_each_file_once() {
+        let data_dir =
+            std::env::temp_dir().join(format!("handy_loss_caches_{}", std::process::id()));
+        let paths = Paths::from_data_dir(&data_dir);
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let game_end_check_type = GameEndCheckType::Standard;
+
+        let loss_caches = LossCaches::default();
+        assert!(loss_caches
+            .get(&paths, &pile, game_end_check_type)
+            .is_none());
+
+        let mut loss_cache = LossCache::new(game_end_check_type);
+        loss_cache.insert(string_to_pile("7A 6A 2A 1C"));
+        write_loss_cache_for_pile(&paths, &loss_cache, &pile).unwrap();
+
+        // Still the empty read from before
+        assert!(loss_caches
+            .get(&paths, &pile, game_end_check_type)
+            .is_none());
+
+        let loss_caches = LossCaches::default();
+        let first = loss_caches.get(&paths, &pile, game_end_check_type).unwrap();
+        fs::remove_dir_all(&data_dir).unwrap();
+        let second = loss_caches.get(&paths, &pile, game_end_check_type).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first.len(), 1);
+    }
+}
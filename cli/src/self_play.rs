@@ -0,0 +1,273 @@
+use crate::pile_randomizers::*;
+use crate::training_utils::*;
+use handy_core::game::*;
+use handy_core::solver::a_star::*;
+use handy_core::solver::model::Model;
+use handy_core::solver::tiny_pile::NoopPileStorageConverter;
+use handy_core::solver::{Matchup, ModelT};
+use handy_core::utils::get_start_from_classes;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartPileSampler {
+    Standard,
+    FullyRandom,
+    NoWinner,
+    // Picks one of the others for every pile
+    Mixed,
+}
+
+pub fn sample_start_pile<R: Rng>(sampler: StartPileSampler, matchup: Matchup, rng: &mut R) -> Pile {
+    let sampler = if sampler == StartPileSampler::Mixed {
+        [
+            StartPileSampler::Standard,
+            StartPileSampler::FullyRandom,
+            StartPileSampler::NoWinner,
+        ][rng.gen_range(0..3)]
+    } else {
+        sampler
+    };
+
+    match sampler {
+        StartPileSampler::FullyRandom => get_fully_random_pile(matchup.0, matchup.1, rng),
+        StartPileSampler::NoWinner => get_random_pile_with_no_winner(matchup.0, matchup.1, rng),
+        _ => get_start_from_classes(matchup.0, matchup.1, rng),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelfPlayOutcome {
+    // From the start pile to the winning pile
+    Solved(Vec<Pile>),
+    ProvenLoss,
+    Unknown,
+}
+
+pub fn solve_start_pile(pile: &Pile, model: Box<dyn ModelT>, max_iters: usize) -> SelfPlayOutcome {
+    let mut solver = AStarSolver::<Pile, NoopPileStorageConverter>::new(&[pile.clone()], model);
+    solver.set_max_iters(max_iters);
+
+    loop {
+        match solver.single_iter() {
+            AStarIterResult::Done(reason) => {
+                return match solver.best_win.clone() {
+                    Some(best_win) => SelfPlayOutcome::Solved(solver.unroll_state(best_win)),
+                    // Only a search that dropped nothing proves a loss
+                    None if matches!(reason, AStarDoneReason::EmptyQueue)
+                        && !solver.is_optimality_lost =>
+                    {
+                        SelfPlayOutcome::ProvenLoss
+                    }
+                    None => SelfPlayOutcome::Unknown,
+                };
+            }
+            AStarIterResult::NewBest(_) | AStarIterResult::Continue(_) => (),
+        }
+    }
+}
+
+/// The shortest distance to a win, if a breadth first search proves it within max_iters.
+/// Only looks for wins up to max_depth, which should be a known upper bound.
+pub fn prove_depth(pile: &Pile, max_depth: usize, max_iters: usize) -> Option<usize> {
+    if max_depth >= DepthType::MAX as usize {
+        return None;
+    }
+
+    let mut solver = AStarSolver::<Pile, NoopPileStorageConverter>::new(
+        &[pile.clone()],
+        Box::new(Model::new_empty_for_pile(pile)),
+    );
+    solver.set_max_iters(max_iters);
+    solver.set_max_depth(max_depth as DepthType + 1);
+    // Without a heuristic the queue is ordered by depth, so the first win is the shortest
+    solver.h_bias = 0.0;
+
+    loop {
+        match solver.single_iter() {
+            AStarIterResult::NewBest(_) => return Some(solver.max_depth as usize),
+            AStarIterResult::Done(_) => return None,
+            AStarIterResult::Continue(_) => (),
+        }
+    }
+}
+
+/// Labels every pile on a winning path, proving exact distances from the end backwards until
+/// a proof runs out of iterations. The piles before that are labeled with upper bounds.
+pub fn label_solution(path: &[Pile], prove_iters: usize) -> Vec<DepthModeTrainingExample> {
+    let mut result = Vec::new();
+    let mut upper_bound = 0;
+    let mut is_proving = prove_iters > 0;
+
+    for pile in path.iter().rev().skip(1) {
+        upper_bound += 1;
+        let proven_depth = if is_proving {
+            prove_depth(pile, upper_bound, prove_iters)
+        } else {
+            None
+        };
+
+        let confidence = match proven_depth {
+            Some(depth) => {
+                upper_bound = depth;
+                LabelConfidence::Exact
+            }
+            None => {
+                // Deeper piles only get harder to prove
+                is_proving = false;
+                LabelConfidence::UpperBound
+            }
+        };
+        result.push(DepthModeTrainingExample {
+            pile: pile.clone(),
+            eval: StateEval::Win(upper_bound),
+            confidence,
+        });
+    }
+
+    result
+}
+
+/// Skips examples whose depth already has more than max_ratio times the average count
+#[derive(Debug, Clone)]
+pub struct DepthBalancer {
+    counts: BTreeMap<usize, usize>,
+    total: usize,
+    max_ratio: f32,
+}
+
+impl DepthBalancer {
+    pub fn new(max_ratio: f32) -> Self {
+        Self {
+            counts: BTreeMap::new(),
+            total: 0,
+            max_ratio,
+        }
+    }
+
+    pub fn record(&mut self, eval: StateEval) {
+        *self.counts.entry(state_eval_to_score(eval)).or_default() += 1;
+        self.total += 1;
+    }
+
+    pub fn should_keep(&self, eval: StateEval) -> bool {
+        let Some(count) = self.counts.get(&state_eval_to_score(eval)) else {
+            return true;
+        };
+        let mean_count = self.total as f32 / self.counts.len() as f32;
+        (*count as f32) < self.max_ratio * mean_count
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Keyed by depth, with losses at 100
+    pub fn counts(&self) -> &BTreeMap<usize, usize> {
+        &self.counts
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SelfPlayStats {
+    pub num_start_piles: usize,
+    pub num_solved: usize,
+    pub num_proven_losses: usize,
+    pub num_unknown: usize,
+    pub num_exact_labels: usize,
+    pub num_bounded_labels: usize,
+    pub num_skipped_by_balance: usize,
+    pub num_written: usize,
+}
+
+impl SelfPlayStats {
+    pub fn add(&mut self, other: &SelfPlayStats) {
+        self.num_start_piles += other.num_start_piles;
+        self.num_solved += other.num_solved;
+        self.num_proven_losses += other.num_proven_losses;
+        self.num_unknown += other.num_unknown;
+        self.num_exact_labels += other.num_exact_labels;
+        self.num_bounded_labels += other.num_bounded_labels;
+        self.num_skipped_by_balance += other.num_skipped_by_balance;
+        self.num_written += other.num_written;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handy_core::utils::string_to_pile;
+
+    fn empty_model(pile: &Pile) -> Box<dyn ModelT> {
+        Box::new(Model::new_empty_for_pile(pile))
+    }
+
+    #[test]
+    fn test_solve_and_label() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let SelfPlayOutcome::Solved(path) = solve_start_pile(&pile, empty_model(&pile), 1_000_000)
+        else {
+            panic!("Expected a solution");
+        };
+        assert_eq!(path[0], pile);
+
+        let examples = label_solution(&path, 1_000_000);
+        assert_eq!(examples.len(), path.len() - 1);
+        assert!(examples.iter().all(|ex| ex.confidence.is_exact()));
+        assert_eq!(examples.last().unwrap().pile, pile);
+        assert_eq!(
+            examples.last().unwrap().eval,
+            StateEval::Win(prove_depth(&pile, path.len(), 1_000_000).unwrap())
+        );
+
+        // Without iterations to prove anything, the path length is all that's known
+        let bounded = label_solution(&path, 0);
+        assert!(bounded.iter().all(|ex| !ex.confidence.is_exact()));
+        assert_eq!(bounded.last().unwrap().eval, StateEval::Win(path.len() - 1));
+    }
+
+    #[test]
+    fn test_proven_loss() {
+        let pile = string_to_pile("7A 6A 2A 1C");
+        assert_eq!(
+            solve_start_pile(&pile, empty_model(&pile), 1_000_000),
+            SelfPlayOutcome::ProvenLoss
+        );
+        assert_eq!(prove_depth(&pile, 10, 1_000_000), None);
+    }
+
+    struct OverestimatingModel;
+
+    impl ModelT for OverestimatingModel {
+        fn score_pile(&self, _pile: &Pile) -> f32 {
+            1000.0
+        }
+    }
+
+    #[test]
+    fn test_dropped_states_dont_prove_a_loss() {
+        // Every child is past the fscore cutoff, so the queue empties without a win
+        let pile = string_to_pile("1A 2A 6A 7A");
+        assert_eq!(
+            solve_start_pile(&pile, Box::new(OverestimatingModel), 1_000_000),
+            SelfPlayOutcome::Unknown
+        );
+    }
+
+    #[test]
+    fn test_depth_balancer() {
+        let mut balancer = DepthBalancer::new(1.5);
+        for _ in 0..10 {
+            balancer.record(StateEval::Win(1));
+        }
+        balancer.record(StateEval::Win(2));
+        balancer.record(StateEval::Loss);
+
+        assert!(!balancer.should_keep(StateEval::Win(1)));
+        assert!(balancer.should_keep(StateEval::Win(2)));
+        assert!(balancer.should_keep(StateEval::Loss));
+        assert!(balancer.should_keep(StateEval::Win(7)));
+        assert_eq!(balancer.counts().get(&100), Some(&1));
+    }
+}
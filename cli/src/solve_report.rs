@@ -0,0 +1,132 @@
+use handy_core::game::*;
+use handy_core::solver::a_star::AStarDoneReason;
+use handy_core::utils::*;
+use serde::Serialize;
+use std::io::{self, Write};
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SolutionStep {
+    // As printed by format_event_for_cli
+    pub events: Vec<String>,
+    // The pile after the activation, in the same format --pile takes
+    pub pile: String,
+}
+
+/// What a solver found, for scripts that read --format json
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SolveReport {
+    pub solver: String,
+    pub start_pile: String,
+    // Only the A* solver has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub done_reason: Option<AStarDoneReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iterations: Option<usize>,
+    pub elapsed_ms: f32,
+    // None if no win was found
+    pub depth: Option<usize>,
+    // States were dropped, so the win found might not be the shortest
+    pub is_optimality_lost: bool,
+    pub steps: Vec<SolutionStep>,
+}
+
+/// One line of the progress stream of a long solve
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProgressRecord {
+    Progress {
+        iterations: usize,
+        queue_size: usize,
+        seen_size: usize,
+        mem_mb: usize,
+        best_depth: usize,
+        elapsed_ms: f32,
+    },
+    NewBest {
+        iterations: usize,
+        depth: usize,
+        elapsed_ms: f32,
+    },
+}
+
+/// Steps from the first pile of the path to the last. Steps the engine can't reproduce get no events.
+pub fn solution_steps(path: &[Pile]) -> Vec<SolutionStep> {
+    path.windows(2)
+        .map(|piles| SolutionStep {
+            events: find_events_between_piles(&piles[0], &piles[1])
+                .unwrap_or_default()
+                .iter()
+                .map(format_event_for_cli)
+                .collect(),
+            pile: pile_to_string(&piles[1]),
+        })
+        .collect()
+}
+
+/// Progress goes to stderr, so stdout only has the final result
+pub fn write_progress_record(record: &ProgressRecord) {
+    let Ok(line) = serde_json::to_string(record) else {
+        return;
+    };
+    let _ = writeln!(io::stderr().lock(), "{line}");
+}
+
+pub fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|err| format!("{err}"))?;
+    writeln!(io::stdout().lock(), "{json}").map_err(|err| format!("{err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solution_steps() {
+        let start_pile = string_to_pile("1A 2A 6A 7A");
+        let child = resolve_top_card(&GameStateWithEventLog::new(start_pile.clone()))
+            .into_iter()
+            .next()
+            .unwrap();
+
+        let steps = solution_steps(&[start_pile.clone(), child.pile.clone()]);
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].pile, pile_to_string(&child.pile));
+        assert_eq!(string_to_pile(&steps[0].pile), child.pile);
+        assert_eq!(
+            steps[0].events,
+            child
+                .events
+                .iter()
+                .map(format_event_for_cli)
+                .collect::<Vec<_>>()
+        );
+
+        assert!(solution_steps(&[start_pile]).is_empty());
+    }
+
+    #[test]
+    fn test_report_json() {
+        let report = SolveReport {
+            solver: "a-star".to_owned(),
+            start_pile: "1A 2A 6A 7A".to_owned(),
+            done_reason: Some(AStarDoneReason::FScoreCutoff),
+            iterations: Some(10),
+            elapsed_ms: 1.5,
+            depth: None,
+            is_optimality_lost: false,
+            steps: vec![],
+        };
+        let json = serde_json::to_value(report).unwrap();
+        assert_eq!(json["done_reason"], "f_score_cutoff");
+        assert!(json["depth"].is_null());
+
+        let record = ProgressRecord::NewBest {
+            iterations: 5,
+            depth: 3,
+            elapsed_ms: 2.0,
+        };
+        let json = serde_json::to_value(record).unwrap();
+        assert_eq!(json["type"], "new_best");
+        assert_eq!(json["depth"], 3);
+    }
+}
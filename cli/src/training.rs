@@ -0,0 +1,548 @@
+use crate::training_utils::*;
+use handy_core::game::Pile;
+use handy_core::solver::sigmoid;
+use handy_core::utils::pile_to_string;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_pcg::Pcg64;
+use rand_seeder::Seeder;
+
+const MAX_DEPTH_WEIGHT: f32 = 100.0;
+const ADAM_BETA_1: f32 = 0.9;
+const ADAM_BETA_2: f32 = 0.999;
+const ADAM_EPSILON: f32 = 1e-8;
+const MOMENTUM: f32 = 0.9;
+const LOGISTIC_EPSILON: f32 = 1e-6;
+pub const DEFAULT_UPPER_BOUND_WEIGHT: f32 = 0.5;
+pub const DEFAULT_VALIDATION_SEED: &str = "validation";
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptimizerType {
+    Sgd,
+    Momentum,
+    Adam,
+}
+
+impl OptimizerType {
+    pub fn default_learning_rate(&self) -> f32 {
+        match self {
+            OptimizerType::Sgd | OptimizerType::Momentum => 0.001,
+            OptimizerType::Adam => 0.01,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainConfig {
+    pub optimizer_type: OptimizerType,
+    pub learning_rate: f32,
+    // Learning rate is multiplied by this after every epoch
+    pub learning_rate_decay: f32,
+    pub l2: f32,
+    pub batch_size: usize,
+    pub max_epochs: usize,
+    // Stop after this many epochs without a better validation loss
+    pub patience: usize,
+    pub validation_fraction: f32,
+    pub is_depth_balanced: bool,
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        Self {
+            optimizer_type: OptimizerType::Adam,
+            learning_rate: OptimizerType::Adam.default_learning_rate(),
+            learning_rate_decay: 0.95,
+            l2: 0.0,
+            batch_size: 5000,
+            max_epochs: 200,
+            patience: 10,
+            validation_fraction: 0.1,
+            is_depth_balanced: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainingExample {
+    pub features: Vec<f32>,
+    pub target: f32,
+    pub weight: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainSummary {
+    pub num_train_examples: usize,
+    pub num_validation_examples: usize,
+    pub init_validation_loss: f32,
+    pub best_validation_loss: f32,
+    pub best_epoch: usize,
+    pub epochs_run: usize,
+    pub is_stopped_early: bool,
+}
+
+pub struct Optimizer {
+    optimizer_type: OptimizerType,
+    first_moment: Vec<f32>,
+    second_moment: Vec<f32>,
+    num_steps: i32,
+}
+
+impl Optimizer {
+    pub fn new(optimizer_type: OptimizerType, num_params: usize) -> Self {
+        Self {
+            optimizer_type,
+            first_moment: vec![0.0; num_params],
+            second_moment: vec![0.0; num_params],
+            num_steps: 0,
+        }
+    }
+
+    pub fn step(&mut self, params: &mut [f32], grads: &[f32], learning_rate: f32) {
+        assert_eq!(params.len(), grads.len());
+        self.num_steps += 1;
+
+        match self.optimizer_type {
+            OptimizerType::Sgd => {
+                for (p, g) in params.iter_mut().zip(grads) {
+                    *p -= learning_rate * g;
+                }
+            }
+            OptimizerType::Momentum => {
+                for i in 0..params.len() {
+                    self.first_moment[i] = MOMENTUM * self.first_moment[i] + grads[i];
+                    params[i] -= learning_rate * self.first_moment[i];
+                }
+            }
+            OptimizerType::Adam => {
+                let first_correction = 1.0 - ADAM_BETA_1.powi(self.num_steps);
+                let second_correction = 1.0 - ADAM_BETA_2.powi(self.num_steps);
+                for i in 0..params.len() {
+                    let g = grads[i];
+                    self.first_moment[i] =
+                        ADAM_BETA_1 * self.first_moment[i] + (1.0 - ADAM_BETA_1) * g;
+                    self.second_moment[i] =
+                        ADAM_BETA_2 * self.second_moment[i] + (1.0 - ADAM_BETA_2) * g * g;
+
+                    let m = self.first_moment[i] / first_correction;
+                    let v = self.second_moment[i] / second_correction;
+                    params[i] -= learning_rate * m / (v.sqrt() + ADAM_EPSILON);
+                }
+            }
+        }
+    }
+}
+
+/// Training weight of a label. Upper bounds can overstate the depth, so they count for less.
+pub fn label_weight(confidence: LabelConfidence, upper_bound_weight: f32) -> f32 {
+    match confidence {
+        LabelConfidence::Exact => 1.0,
+        LabelConfidence::UpperBound => upper_bound_weight,
+    }
+}
+
+/// Weight for each win depth, so that every depth contributes equally to the loss.
+pub fn get_weights_by_depth(examples: &[DepthModeTrainingExample]) -> Vec<f32> {
+    let mut counts_by_depth: Vec<usize> = Vec::new();
+
+    for example in examples {
+        let StateEval::Win(depth) = example.eval else {
+            continue;
+        };
+
+        while depth >= counts_by_depth.len() {
+            counts_by_depth.push(0);
+        }
+
+        counts_by_depth[depth] += 1;
+    }
+
+    let num_depths = counts_by_depth.iter().filter(|c| **c > 0).count();
+    let num_wins: usize = counts_by_depth.iter().sum();
+    let weight_per_level = num_wins as f32 / num_depths.max(1) as f32;
+
+    counts_by_depth
+        .iter()
+        .map(|count| {
+            if *count == 0 {
+                0.0
+            } else {
+                (weight_per_level / *count as f32).min(MAX_DEPTH_WEIGHT)
+            }
+        })
+        .collect()
+}
+
+/// Shuffles the examples, and returns (train, validation)
+pub fn split_validation<T, R: Rng>(
+    mut examples: Vec<T>,
+    validation_fraction: f32,
+    rng: &mut R,
+) -> (Vec<T>, Vec<T>) {
+    examples.shuffle(rng);
+    let num_validation = (examples.len() as f32 * validation_fraction).round() as usize;
+    let validation = examples.split_off(examples.len() - num_validation);
+    (examples, validation)
+}
+
+/// Whether a pile is held out for validation. Decided by hashing the pile with the seed rather
+/// than by shuffling, so every model trained with the same seed and fraction holds out the same
+/// piles, even after more examples are generated. The blender relies on this to fit on piles
+/// none of its members trained on.
+pub fn is_validation_pile(pile: &Pile, seed: &str, validation_fraction: f32) -> bool {
+    let mut rng = Seeder::from((seed, pile_to_string(pile))).make_rng::<Pcg64>();
+    rng.gen::<f32>() < validation_fraction
+}
+
+/// Returns (train, validation), split with is_validation_pile
+pub fn split_validation_by_pile<T>(
+    examples: Vec<T>,
+    seed: &str,
+    validation_fraction: f32,
+    get_pile: impl Fn(&T) -> &Pile,
+) -> (Vec<T>, Vec<T>) {
+    examples
+        .into_iter()
+        .partition(|ex| !is_validation_pile(get_pile(ex), seed, validation_fraction))
+}
+
+pub fn linear_predict(params: &[f32], features: &[f32]) -> f32 {
+    params.iter().zip(features).map(|(p, f)| p * f).sum()
+}
+
+pub fn logistic_predict(params: &[f32], features: &[f32]) -> f32 {
+    sigmoid(linear_predict(params, features))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Objective {
+    // Regression on the target, eg the win depth
+    SquaredError,
+    // Classification, with targets of 0 or 1
+    Logistic,
+}
+
+impl Objective {
+    fn predict(&self, params: &[f32], features: &[f32]) -> f32 {
+        match self {
+            Objective::SquaredError => linear_predict(params, features),
+            Objective::Logistic => logistic_predict(params, features),
+        }
+    }
+
+    fn example_loss(&self, prediction: f32, target: f32) -> f32 {
+        match self {
+            Objective::SquaredError => (prediction - target) * (prediction - target),
+            Objective::Logistic => binary_cross_entropy(prediction, target),
+        }
+    }
+
+    // Derivative of the example loss with respect to the linear output
+    fn output_gradient(&self, prediction: f32, target: f32) -> f32 {
+        match self {
+            Objective::SquaredError => 2.0 * (prediction - target),
+            Objective::Logistic => prediction - target,
+        }
+    }
+
+    /// Unweighted mean loss, comparable between models and training runs
+    fn mean_loss(&self, params: &[f32], examples: &[TrainingExample]) -> f32 {
+        let total: f32 = examples
+            .iter()
+            .map(|ex| self.example_loss(self.predict(params, &ex.features), ex.target))
+            .sum();
+        total / examples.len().max(1) as f32
+    }
+
+    fn gradients(&self, params: &[f32], batch: &[TrainingExample], l2: f32) -> Vec<f32> {
+        let mut grads: Vec<f32> = params.iter().map(|p| 2.0 * l2 * p).collect();
+        let total_weight: f32 = batch.iter().map(|ex| ex.weight).sum();
+        if total_weight <= 0.0 {
+            return grads;
+        }
+
+        for ex in batch {
+            let prediction = self.predict(params, &ex.features);
+            let coeff = self.output_gradient(prediction, ex.target) * ex.weight / total_weight;
+            for (g, f) in grads.iter_mut().zip(&ex.features) {
+                *g += coeff * f;
+            }
+        }
+
+        grads
+    }
+}
+
+pub fn binary_cross_entropy(probability: f32, target: f32) -> f32 {
+    let p = probability.clamp(LOGISTIC_EPSILON, 1.0 - LOGISTIC_EPSILON);
+    -(target * p.ln() + (1.0 - target) * (1.0 - p).ln())
+}
+
+/// Unweighted mean squared error, comparable between models and training runs
+pub fn linear_loss(params: &[f32], examples: &[TrainingExample]) -> f32 {
+    Objective::SquaredError.mean_loss(params, examples)
+}
+
+/// Unweighted mean binary cross entropy, for targets of 0 (won) or 1 (lost)
+pub fn logistic_loss(params: &[f32], examples: &[TrainingExample]) -> f32 {
+    Objective::Logistic.mean_loss(params, examples)
+}
+
+/// Mini-batch training of a linear model, keeping the parameters with the best validation loss.
+/// on_epoch is called with (epoch, train loss, validation loss).
+pub fn train_linear<R: Rng>(
+    init_params: Vec<f32>,
+    train_examples: &mut [TrainingExample],
+    validation_examples: &[TrainingExample],
+    config: &TrainConfig,
+    rng: &mut R,
+    on_epoch: &mut dyn FnMut(usize, f32, f32),
+) -> (Vec<f32>, TrainSummary) {
+    train_objective(
+        Objective::SquaredError,
+        init_params,
+        train_examples,
+        validation_examples,
+        config,
+        rng,
+        on_epoch,
+    )
+}
+
+/// Same as train_linear, but fits a logistic classifier, eg a loss head
+pub fn train_logistic<R: Rng>(
+    init_params: Vec<f32>,
+    train_examples: &mut [TrainingExample],
+    validation_examples: &[TrainingExample],
+    config: &TrainConfig,
+    rng: &mut R,
+    on_epoch: &mut dyn FnMut(usize, f32, f32),
+) -> (Vec<f32>, TrainSummary) {
+    train_objective(
+        Objective::Logistic,
+        init_params,
+        train_examples,
+        validation_examples,
+        config,
+        rng,
+        on_epoch,
+    )
+}
+
+fn train_objective<R: Rng>(
+    objective: Objective,
+    init_params: Vec<f32>,
+    train_examples: &mut [TrainingExample],
+    validation_examples: &[TrainingExample],
+    config: &TrainConfig,
+    rng: &mut R,
+    on_epoch: &mut dyn FnMut(usize, f32, f32),
+) -> (Vec<f32>, TrainSummary) {
+    let mut params = init_params;
+    let mut optimizer = Optimizer::new(config.optimizer_type, params.len());
+    let mut learning_rate = config.learning_rate;
+
+    let init_validation_loss = objective.mean_loss(&params, validation_examples);
+    let mut best_params = params.clone();
+    let mut summary = TrainSummary {
+        num_train_examples: train_examples.len(),
+        num_validation_examples: validation_examples.len(),
+        init_validation_loss,
+        best_validation_loss: init_validation_loss,
+        best_epoch: 0,
+        epochs_run: 0,
+        is_stopped_early: false,
+    };
+
+    for epoch in 1..=config.max_epochs {
+        train_examples.shuffle(rng);
+        for batch in train_examples.chunks(config.batch_size.max(1)) {
+            let grads = objective.gradients(&params, batch, config.l2);
+            optimizer.step(&mut params, &grads, learning_rate);
+        }
+        learning_rate *= config.learning_rate_decay;
+
+        let train_loss = objective.mean_loss(&params, train_examples);
+        let validation_loss = objective.mean_loss(&params, validation_examples);
+        summary.epochs_run = epoch;
+        on_epoch(epoch, train_loss, validation_loss);
+
+        if validation_loss < summary.best_validation_loss {
+            summary.best_validation_loss = validation_loss;
+            summary.best_epoch = epoch;
+            best_params = params.clone();
+        } else if epoch - summary.best_epoch >= config.patience {
+            summary.is_stopped_early = true;
+            break;
+        }
+    }
+
+    (best_params, summary)
+}
+
+/// Weight for losses, so that they count as much as all the wins combined
+pub fn get_loss_class_weight(num_wins: usize, num_losses: usize) -> f32 {
+    if num_losses == 0 {
+        0.0
+    } else {
+        (num_wins as f32 / num_losses as f32).clamp(1.0, MAX_DEPTH_WEIGHT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handy_core::utils::string_to_pile;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // y = 3a - 2b + 1
+    fn toy_examples(rng: &mut StdRng) -> Vec<TrainingExample> {
+        (0..500)
+            .map(|_| {
+                let a: f32 = rng.gen_range(-1.0..1.0);
+                let b: f32 = rng.gen_range(-1.0..1.0);
+                TrainingExample {
+                    features: vec![a, b, 1.0],
+                    target: 3.0 * a - 2.0 * b + 1.0,
+                    weight: 1.0,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_optimizers_fit_linear_model() {
+        for optimizer_type in [
+            OptimizerType::Sgd,
+            OptimizerType::Momentum,
+            OptimizerType::Adam,
+        ] {
+            let mut rng = StdRng::seed_from_u64(0);
+            let (mut train, validation) = split_validation(toy_examples(&mut rng), 0.2, &mut rng);
+            let config = TrainConfig {
+                optimizer_type,
+                learning_rate: optimizer_type.default_learning_rate() * 10.0,
+                batch_size: 10,
+                max_epochs: 100,
+                ..TrainConfig::default()
+            };
+
+            let (params, summary) = train_linear(
+                vec![0.0; 3],
+                &mut train,
+                &validation,
+                &config,
+                &mut rng,
+                &mut |_, _, _| (),
+            );
+
+            assert_eq!(summary.num_validation_examples, 100);
+            assert!(summary.best_validation_loss < 0.01, "{optimizer_type:?}");
+            assert!((params[0] - 3.0).abs() < 0.1, "{optimizer_type:?}");
+        }
+    }
+
+    #[test]
+    fn test_early_stopping() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (mut train, validation) = split_validation(toy_examples(&mut rng), 0.2, &mut rng);
+        let config = TrainConfig {
+            learning_rate: 0.0,
+            patience: 3,
+            ..TrainConfig::default()
+        };
+
+        let (params, summary) = train_linear(
+            vec![0.0; 3],
+            &mut train,
+            &validation,
+            &config,
+            &mut rng,
+            &mut |_, _, _| (),
+        );
+
+        assert!(summary.is_stopped_early);
+        assert_eq!(summary.epochs_run, 3);
+        assert_eq!(summary.best_epoch, 0);
+        assert_eq!(params, vec![0.0; 3]);
+    }
+
+    #[test]
+    fn test_split_validation_by_pile() {
+        let piles: Vec<Pile> = ["1A 2A 6A 7A", "7A 6A 2A 1C", "2A 1A 6A 7A", "6A 7A 1B 2B"]
+            .iter()
+            .map(|pile| string_to_pile(pile))
+            .collect();
+        let many: Vec<Pile> = (0..50).flat_map(|_| piles.clone()).collect();
+
+        let (train, validation) = split_validation_by_pile(many, "seed", 0.5, |pile| pile);
+        assert_eq!(train.len() + validation.len(), 200);
+        // Every copy of a pile lands on the same side, whatever the order
+        for pile in &piles {
+            assert!(!(train.contains(pile) && validation.contains(pile)));
+            assert_eq!(
+                validation.contains(pile),
+                is_validation_pile(pile, "seed", 0.5)
+            );
+        }
+
+        let (train, validation) = split_validation_by_pile(piles.clone(), "seed", 0.0, |p| p);
+        assert_eq!((train.len(), validation.len()), (4, 0));
+        let (train, validation) = split_validation_by_pile(piles, "seed", 1.0, |p| p);
+        assert_eq!((train.len(), validation.len()), (0, 4));
+    }
+
+    #[test]
+    fn test_weights_by_depth() {
+        let pile = string_to_pile("1 2 3");
+        let example = |depth| DepthModeTrainingExample {
+            pile: pile.clone(),
+            eval: StateEval::Win(depth),
+            confidence: LabelConfidence::Exact,
+        };
+        let examples = vec![example(1), example(1), example(1), example(3)];
+
+        let weights = get_weights_by_depth(&examples);
+        assert_eq!(weights.len(), 4);
+        assert_eq!(weights[0], 0.0);
+        assert_eq!(weights[2], 0.0);
+        // 3 examples at depth 1 count as much as the single example at depth 3
+        assert!((weights[1] * 3.0 - weights[3]).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_logistic_separates_classes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // Lost whenever a > b
+        let examples: Vec<TrainingExample> = (0..500)
+            .map(|_| {
+                let a: f32 = rng.gen_range(-1.0..1.0);
+                let b: f32 = rng.gen_range(-1.0..1.0);
+                TrainingExample {
+                    features: vec![a, b, 1.0],
+                    target: if a > b { 1.0 } else { 0.0 },
+                    weight: 1.0,
+                }
+            })
+            .collect();
+        let (mut train, validation) = split_validation(examples, 0.2, &mut rng);
+
+        let (params, summary) = train_logistic(
+            vec![0.0; 3],
+            &mut train,
+            &validation,
+            &TrainConfig {
+                batch_size: 10,
+                ..TrainConfig::default()
+            },
+            &mut rng,
+            &mut |_, _, _| (),
+        );
+
+        assert!((summary.init_validation_loss - 2.0_f32.ln()).abs() < 0.0001);
+        assert!(summary.best_validation_loss < 0.3);
+        assert!(logistic_predict(&params, &[0.8, -0.8, 1.0]) > 0.9);
+        assert!(logistic_predict(&params, &[-0.8, 0.8, 1.0]) < 0.1);
+    }
+}
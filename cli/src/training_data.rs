@@ -0,0 +1,819 @@
+use crate::paths::*;
+use crate::training_utils::*;
+use enum_map::Enum;
+use handy_core::game::*;
+use handy_core::solver::Matchup;
+use serde::{Deserialize, Serialize};
+use serde_jsonlines::json_lines;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Eval, pile length, then (card id, face) for every slot
+pub const RECORD_BYTES: usize = 2 + 2 * MAX_PILE_LEN;
+pub const MAX_EXAMPLES_PER_SHARD: usize = 1_000_000;
+const LOSS_EVAL_BYTE: u8 = u8::MAX;
+// Set on the eval byte of wins that are only an upper bound, leaving 7 bits for the depth
+const UPPER_BOUND_EVAL_FLAG: u8 = 0x80;
+const SHARD_MAGIC: &[u8; 4] = b"HNDX";
+// Version 1 shards had no upper bound flag, so their depths can't be told apart from bounds
+const SHARD_VERSION: u8 = 2;
+const SHARD_HEADER_BYTES: usize = 8;
+const INDEX_FILE_NAME: &str = "index.json";
+const LOCK_FILE_NAME: &str = "index.lock";
+const LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+const LABELS_FILE_NAME: &str = "labels.bin";
+const LABELS_MAGIC: &[u8; 4] = b"HNDL";
+const LABELS_VERSION: u8 = 1;
+// Packed pile, shard position in the index, record number in the shard, then the eval byte
+const LABEL_ENTRY_BYTES: usize = RECORD_BYTES - 1 + 4 + 4 + 1;
+// Used to compare matchups that haven't been converted yet
+const APPROX_JSONL_BYTES_PER_EXAMPLE: u64 = 100;
+
+// Pile length, then (card id, face) for every slot
+pub type PackedPile = [u8; RECORD_BYTES - 1];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ShardInfo {
+    pub file_name: String,
+    pub num_examples: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ShardIndex {
+    pub shards: Vec<ShardInfo>,
+}
+
+impl ShardIndex {
+    pub fn num_examples(&self) -> usize {
+        self.shards.iter().map(|s| s.num_examples).sum()
+    }
+}
+
+pub fn pack_pile(pile: &Pile) -> PackedPile {
+    let mut result = [0; RECORD_BYTES - 1];
+    result[0] = pile.len() as u8;
+    for (i, card) in pile.iter().enumerate() {
+        result[1 + 2 * i] = card.get_card_id();
+        result[2 + 2 * i] = card.get_card_face().into_usize() as u8;
+    }
+    result
+}
+
+pub fn pack_example(example: &DepthModeTrainingExample) -> Result<[u8; RECORD_BYTES], String> {
+    let eval_byte = match (example.eval, example.confidence) {
+        (StateEval::Win(depth), _) if depth >= (UPPER_BOUND_EVAL_FLAG - 1) as usize => {
+            return Err(format!("Depth {depth} is too large to pack"))
+        }
+        (StateEval::Win(depth), LabelConfidence::Exact) => depth as u8,
+        (StateEval::Win(depth), LabelConfidence::UpperBound) => depth as u8 | UPPER_BOUND_EVAL_FLAG,
+        (StateEval::Loss, LabelConfidence::Exact) => LOSS_EVAL_BYTE,
+        (StateEval::Loss, LabelConfidence::UpperBound) => {
+            return Err("Losses can't be upper bounds".to_owned())
+        }
+    };
+
+    let mut result = [0; RECORD_BYTES];
+    result[0] = eval_byte;
+    result[1..].copy_from_slice(&pack_pile(&example.pile));
+    Ok(result)
+}
+
+pub fn unpack_example(record: &[u8]) -> Result<DepthModeTrainingExample, String> {
+    if record.len() != RECORD_BYTES {
+        return Err(format!(
+            "Expected {RECORD_BYTES} bytes, got {}",
+            record.len()
+        ));
+    }
+    let pile_len = record[1] as usize;
+    if pile_len > MAX_PILE_LEN {
+        return Err(format!("Invalid pile length {pile_len}"));
+    }
+
+    let mut pile = Pile::new();
+    for i in 0..pile_len {
+        let card_id = record[2 + 2 * i];
+        let face_idx = record[3 + 2 * i] as usize;
+        if CARDS.get_card_if_exists(card_id as usize).is_none() || face_idx >= FaceKey::LENGTH {
+            return Err(format!("Invalid card {card_id} face {face_idx}"));
+        }
+        pile.push(CardPtr::new_from_id(card_id, FaceKey::from_usize(face_idx)));
+    }
+
+    let (eval, confidence) = match record[0] {
+        LOSS_EVAL_BYTE => (StateEval::Loss, LabelConfidence::Exact),
+        byte if byte & UPPER_BOUND_EVAL_FLAG != 0 => (
+            StateEval::Win((byte & !UPPER_BOUND_EVAL_FLAG) as usize),
+            LabelConfidence::UpperBound,
+        ),
+        depth => (StateEval::Win(depth as usize), LabelConfidence::Exact),
+    };
+
+    Ok(DepthModeTrainingExample {
+        pile,
+        eval,
+        confidence,
+    })
+}
+
+pub fn read_shard_index(dir: &Path) -> Result<ShardIndex, String> {
+    let file = File::open(dir.join(INDEX_FILE_NAME)).map_err(|err| format!("{err}"))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|err| format!("{err}"))
+}
+
+// A directory nothing has been written to yet has no index
+fn read_shard_index_or_default(dir: &Path) -> Result<ShardIndex, String> {
+    match File::open(dir.join(INDEX_FILE_NAME)) {
+        Ok(file) => serde_json::from_reader(BufReader::new(file)).map_err(|err| format!("{err}")),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(ShardIndex::default()),
+        Err(err) => Err(format!("{err}")),
+    }
+}
+
+fn write_shard_index(dir: &Path, index: &ShardIndex) -> Result<(), String> {
+    // Write then rename, so readers never see a partial index
+    let tmp_path = dir.join(format!("{INDEX_FILE_NAME}.{}.tmp", std::process::id()));
+    let file = File::create(&tmp_path).map_err(|err| format!("{err}"))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), index).map_err(|err| format!("{err}"))?;
+    fs::rename(tmp_path, dir.join(INDEX_FILE_NAME)).map_err(|err| format!("{err}"))
+}
+
+/// Held while reading or changing a directory's index and labels file, so writers in other
+/// processes can't drop each other's shards. The lock file is removed on drop, so one left
+/// behind means a writer died holding it.
+struct ShardDirLock {
+    path: PathBuf,
+}
+
+impl ShardDirLock {
+    fn acquire(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(LOCK_FILE_NAME);
+        let start_time = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self { path }),
+                Err(err) if err.kind() != ErrorKind::AlreadyExists => {
+                    return Err(format!("{}: {err}", path.display()))
+                }
+                Err(_) if start_time.elapsed() > LOCK_TIMEOUT => {
+                    return Err(format!(
+                        "{} is still locked after {}s, delete it if no other generator is running",
+                        path.display(),
+                        LOCK_TIMEOUT.as_secs()
+                    ))
+                }
+                Err(_) => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+    }
+}
+
+impl Drop for ShardDirLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Streams the examples of a single shard, without loading it all into memory
+pub struct ShardReader {
+    reader: BufReader<File>,
+}
+
+impl ShardReader {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let mut reader = BufReader::new(file);
+
+        let mut header = [0; SHARD_HEADER_BYTES];
+        reader
+            .read_exact(&mut header)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        if &header[0..4] != SHARD_MAGIC || header[5] as usize != RECORD_BYTES {
+            return Err(format!("{} is not a training data shard", path.display()));
+        }
+        if header[4] != SHARD_VERSION {
+            return Err(format!(
+                "{} is a version {} shard, but this build reads version {SHARD_VERSION}. \
+                 Regenerate it",
+                path.display(),
+                header[4]
+            ));
+        }
+
+        Ok(Self { reader })
+    }
+}
+
+impl ShardReader {
+    pub fn next_record(&mut self) -> Option<Result<[u8; RECORD_BYTES], String>> {
+        let mut record = [0; RECORD_BYTES];
+        match self.reader.read_exact(&mut record) {
+            Ok(()) => Some(Ok(record)),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => None,
+            Err(err) => Some(Err(format!("{err}"))),
+        }
+    }
+}
+
+impl Iterator for ShardReader {
+    type Item = Result<DepthModeTrainingExample, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record()
+            .map(|record| record.and_then(|record| unpack_example(&record)))
+    }
+}
+
+/// Streams every example in a sharded directory, in index order
+pub fn read_sharded_examples(
+    dir: &Path,
+) -> Result<impl Iterator<Item = Result<DepthModeTrainingExample, String>>, String> {
+    let index = read_shard_index(dir)?;
+    let dir = dir.to_owned();
+    Ok(index.shards.into_iter().flat_map(move |shard| {
+        match ShardReader::open(&dir.join(shard.file_name)) {
+            Ok(reader) => Box::new(reader) as Box<dyn Iterator<Item = _>>,
+            Err(err) => Box::new(std::iter::once(Err(err))),
+        }
+    }))
+}
+
+/// Lower is better: wins before losses, then shorter wins, then exact depths before bounds.
+/// Every win label is the length of a path that was played out, so a shorter one is never wrong.
+fn label_rank(eval_byte: u8) -> (bool, u8, bool) {
+    match eval_byte {
+        LOSS_EVAL_BYTE => (true, 0, false),
+        byte => (
+            false,
+            byte & !UPPER_BOUND_EVAL_FLAG,
+            byte & UPPER_BOUND_EVAL_FLAG != 0,
+        ),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct LabelLocation {
+    // Into ShardedExampleWriter::shard_names
+    shard: u32,
+    record: u32,
+    eval_byte: u8,
+}
+
+fn label_entry(pile: &PackedPile, position: u32, location: &LabelLocation) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(LABEL_ENTRY_BYTES);
+    entry.extend_from_slice(pile);
+    entry.extend_from_slice(&position.to_le_bytes());
+    entry.extend_from_slice(&location.record.to_le_bytes());
+    entry.push(location.eval_byte);
+    entry
+}
+
+/// Appends examples as new shards, with one record per pile across every run. When a pile comes
+/// up again with a better label, its record is rewritten in place. Where each pile's record is
+/// gets appended to a labels file, so opening only has to scan shards the file doesn't cover.
+pub struct ShardedExampleWriter {
+    dir: PathBuf,
+    shard_names: Vec<String>,
+    labels: HashMap<PackedPile, LabelLocation>,
+}
+
+impl ShardedExampleWriter {
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|err| format!("{err}"))?;
+        let _lock = ShardDirLock::acquire(dir)?;
+        let index = read_shard_index_or_default(dir)?;
+
+        let mut writer = Self {
+            dir: dir.to_owned(),
+            shard_names: index.shards.iter().map(|s| s.file_name.clone()).collect(),
+            labels: HashMap::new(),
+        };
+        let covered_shards = writer.read_labels_file()?;
+
+        // Shards written by a run that died before logging them, or by an older build
+        let mut new_entries = Vec::new();
+        for (position, shard_name) in writer.shard_names.iter().enumerate() {
+            if covered_shards.contains(&(position as u32)) {
+                continue;
+            }
+            let mut reader = ShardReader::open(&dir.join(shard_name))?;
+            let mut record_num = 0;
+            while let Some(record) = reader.next_record() {
+                let record = record?;
+                let pile: PackedPile = record[1..].try_into().unwrap();
+                let location = LabelLocation {
+                    shard: position as u32,
+                    record: record_num,
+                    eval_byte: record[0],
+                };
+                new_entries.extend(label_entry(&pile, position as u32, &location));
+                let is_better = writer.labels.get(&pile).map_or(true, |current| {
+                    label_rank(location.eval_byte) < label_rank(current.eval_byte)
+                });
+                if is_better {
+                    writer.labels.insert(pile, location);
+                }
+                record_num += 1;
+            }
+        }
+        writer.append_label_entries(&new_entries)?;
+
+        Ok(writer)
+    }
+
+    // Returns the positions of the shards the labels file has entries for
+    fn read_labels_file(&mut self) -> Result<HashSet<u32>, String> {
+        let mut covered_shards = HashSet::new();
+        let path = self.dir.join(LABELS_FILE_NAME);
+        if !path.exists() {
+            return Ok(covered_shards);
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        let file_len = file.metadata().map_err(|err| format!("{err}"))?.len() as usize;
+        // Created by a run that died before writing anything to it
+        if file_len == 0 {
+            return Ok(covered_shards);
+        }
+        let mut reader = BufReader::new(&file);
+        let mut header = [0; SHARD_HEADER_BYTES];
+        let is_labels_file = reader.read_exact(&mut header).is_ok()
+            && &header[0..4] == LABELS_MAGIC
+            && header[4] == LABELS_VERSION
+            && header[5] as usize == LABEL_ENTRY_BYTES;
+        if !is_labels_file {
+            return Err(format!("{} is not a training labels file", path.display()));
+        }
+
+        let num_entries = (file_len - SHARD_HEADER_BYTES) / LABEL_ENTRY_BYTES;
+        let mut entry = [0; LABEL_ENTRY_BYTES];
+        for _ in 0..num_entries {
+            reader
+                .read_exact(&mut entry)
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+            let pile: PackedPile = entry[..RECORD_BYTES - 1].try_into().unwrap();
+            let field =
+                |start: usize| u32::from_le_bytes(entry[start..start + 4].try_into().unwrap());
+            let position = field(RECORD_BYTES - 1);
+            if position as usize >= self.shard_names.len() {
+                return Err(format!(
+                    "{} refers to shard {position}, which isn't in the index",
+                    path.display()
+                ));
+            }
+            covered_shards.insert(position);
+            // Later entries are labels that were improved
+            self.labels.insert(
+                pile,
+                LabelLocation {
+                    shard: position,
+                    record: field(RECORD_BYTES + 3),
+                    eval_byte: entry[LABEL_ENTRY_BYTES - 1],
+                },
+            );
+        }
+
+        // A run that died mid-append leaves part of an entry, which would misalign later ones
+        let entries_end = SHARD_HEADER_BYTES + num_entries * LABEL_ENTRY_BYTES;
+        if file_len > entries_end {
+            file.set_len(entries_end as u64)
+                .map_err(|err| format!("{err}"))?;
+        }
+        Ok(covered_shards)
+    }
+
+    fn append_label_entries(&self, entries: &[u8]) -> Result<(), String> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let path = self.dir.join(LABELS_FILE_NAME);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        let mut bytes = Vec::with_capacity(SHARD_HEADER_BYTES + entries.len());
+        if file.metadata().map_err(|err| format!("{err}"))?.len() == 0 {
+            let mut header = [0; SHARD_HEADER_BYTES];
+            header[0..4].copy_from_slice(LABELS_MAGIC);
+            header[4] = LABELS_VERSION;
+            header[5] = LABEL_ENTRY_BYTES as u8;
+            bytes.extend_from_slice(&header);
+        }
+        bytes.extend_from_slice(entries);
+        // One write, so concurrent writers don't interleave their entries
+        file.write_all(&bytes).map_err(|err| format!("{err}"))
+    }
+
+    // Returns the eval byte the record has afterwards
+    fn rewrite_eval_byte(&self, location: &LabelLocation, eval_byte: u8) -> Result<u8, String> {
+        let path = self.dir.join(&self.shard_names[location.shard as usize]);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        let offset = (SHARD_HEADER_BYTES + location.record as usize * RECORD_BYTES) as u64;
+
+        // Another run might have improved it since this one read the labels
+        let mut current = [0; 1];
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut current))
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        if label_rank(eval_byte) >= label_rank(current[0]) {
+            return Ok(current[0]);
+        }
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.write_all(&[eval_byte]))
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        Ok(eval_byte)
+    }
+
+    pub fn num_seen_piles(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Returns the number of examples written, after deduplication, counting labels that
+    /// replaced a worse one
+    pub fn write_examples(
+        &mut self,
+        examples: impl Iterator<Item = DepthModeTrainingExample>,
+    ) -> Result<usize, String> {
+        let mut new_shards: Vec<ShardInfo> = Vec::new();
+        let mut writer: Option<BufWriter<File>> = None;
+        let mut changed_piles: Vec<PackedPile> = Vec::new();
+        let mut num_written = 0;
+
+        for example in examples {
+            let record = pack_example(&example)?;
+            let pile = pack_pile(&example.pile);
+
+            if let Some(location) = self.labels.get(&pile).copied() {
+                if label_rank(record[0]) >= label_rank(location.eval_byte) {
+                    continue;
+                }
+                // The record might still be buffered if this run wrote it
+                if let Some(writer) = writer.as_mut() {
+                    writer.flush().map_err(|err| format!("{err}"))?;
+                }
+                let eval_byte = self.rewrite_eval_byte(&location, record[0])?;
+                if eval_byte == record[0] {
+                    num_written += 1;
+                    changed_piles.push(pile);
+                }
+                self.labels.get_mut(&pile).unwrap().eval_byte = eval_byte;
+                continue;
+            }
+
+            if new_shards
+                .last()
+                .map_or(true, |shard| shard.num_examples >= MAX_EXAMPLES_PER_SHARD)
+            {
+                if let Some(mut full_writer) = writer.take() {
+                    full_writer.flush().map_err(|err| format!("{err}"))?;
+                }
+                let file_name = new_shard_file_name(new_shards.len());
+                let file =
+                    File::create(self.dir.join(&file_name)).map_err(|err| format!("{err}"))?;
+                let mut new_writer = BufWriter::new(file);
+                let mut header = [0; SHARD_HEADER_BYTES];
+                header[0..4].copy_from_slice(SHARD_MAGIC);
+                header[4] = SHARD_VERSION;
+                header[5] = RECORD_BYTES as u8;
+                new_writer
+                    .write_all(&header)
+                    .map_err(|err| format!("{err}"))?;
+                writer = Some(new_writer);
+                self.shard_names.push(file_name.clone());
+                new_shards.push(ShardInfo {
+                    file_name,
+                    num_examples: 0,
+                });
+            }
+
+            writer
+                .as_mut()
+                .unwrap()
+                .write_all(&record)
+                .map_err(|err| format!("{err}"))?;
+            let shard = new_shards.last_mut().unwrap();
+            self.labels.insert(
+                pile,
+                LabelLocation {
+                    shard: (self.shard_names.len() - 1) as u32,
+                    record: shard.num_examples as u32,
+                    eval_byte: record[0],
+                },
+            );
+            changed_piles.push(pile);
+            shard.num_examples += 1;
+            num_written += 1;
+        }
+
+        if let Some(mut writer) = writer {
+            writer.flush().map_err(|err| format!("{err}"))?;
+        }
+        if changed_piles.is_empty() {
+            return Ok(num_written);
+        }
+
+        // Re-read the index, in case another process added shards in the meantime
+        let _lock = ShardDirLock::acquire(&self.dir)?;
+        let mut index = read_shard_index_or_default(&self.dir)?;
+        if !new_shards.is_empty() {
+            index.shards.extend(new_shards);
+            write_shard_index(&self.dir, &index)?;
+        }
+
+        // Which is also why the labels file refers to shards by their position in the index
+        let positions: HashMap<&str, u32> = index
+            .shards
+            .iter()
+            .enumerate()
+            .map(|(position, shard)| (shard.file_name.as_str(), position as u32))
+            .collect();
+        let mut entries = Vec::with_capacity(changed_piles.len() * LABEL_ENTRY_BYTES);
+        for pile in changed_piles {
+            let location = self.labels[&pile];
+            // Left for the next open to scan, if the shard isn't in the index
+            let Some(position) = positions.get(self.shard_names[location.shard as usize].as_str())
+            else {
+                continue;
+            };
+            entries.extend(label_entry(&pile, *position, &location));
+        }
+        self.append_label_entries(&entries)?;
+
+        Ok(num_written)
+    }
+}
+
+fn new_shard_file_name(shard_num: usize) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    format!("shard-{nanos}-{}-{shard_num}.bin", std::process::id())
+}
+
+pub fn write_examples_for_matchup(
+    paths: &Paths,
+    matchup: Matchup,
+    examples: impl Iterator<Item = DepthModeTrainingExample>,
+) -> Result<usize, String> {
+    ShardedExampleWriter::open(&training_shards_dir_for_matchup(paths, matchup))?
+        .write_examples(examples)
+}
+
+pub fn read_jsonl_examples(
+    path: &str,
+) -> Result<impl Iterator<Item = Result<DepthModeTrainingExample, String>>, String> {
+    let examples = json_lines(path).map_err(|err| format!("{err}"))?;
+    Ok(examples.map(|example| example.map_err(|err| format!("{err}"))))
+}
+
+/// Streams a matchup's examples from its shards, followed by the older jsonl file if it
+/// hasn't been converted yet
+pub fn read_examples_for_matchup(
+    paths: &Paths,
+    matchup: Matchup,
+) -> Result<Box<dyn Iterator<Item = Result<DepthModeTrainingExample, String>>>, String> {
+    let shards_dir = training_shards_dir_for_matchup(paths, matchup);
+    let jsonl_path = training_path_for_matchup(paths, matchup);
+    let has_shards = shards_dir.join(INDEX_FILE_NAME).exists();
+    let has_jsonl = Path::new(&jsonl_path).exists();
+
+    match (has_shards, has_jsonl) {
+        (true, true) => Ok(Box::new(
+            read_sharded_examples(&shards_dir)?.chain(read_jsonl_examples(&jsonl_path)?),
+        )),
+        (true, false) => Ok(Box::new(read_sharded_examples(&shards_dir)?)),
+        _ => Ok(Box::new(read_jsonl_examples(&jsonl_path)?)),
+    }
+}
+
+pub fn read_all_examples_for_matchup(
+    paths: &Paths,
+    matchup: Matchup,
+) -> Result<Vec<DepthModeTrainingExample>, String> {
+    read_examples_for_matchup(paths, matchup)?.collect()
+}
+
+pub fn has_examples_for_matchup(paths: &Paths, matchup: Matchup) -> bool {
+    training_shards_dir_for_matchup(paths, matchup)
+        .join(INDEX_FILE_NAME)
+        .exists()
+        || Path::new(&training_path_for_matchup(paths, matchup)).exists()
+}
+
+/// Exact for shards, and estimated from the file size for jsonl
+pub fn approx_num_examples_for_matchup(paths: &Paths, matchup: Matchup) -> u64 {
+    let num_sharded = read_shard_index(&training_shards_dir_for_matchup(paths, matchup))
+        .map_or(0, |index| index.num_examples() as u64);
+    let num_jsonl = fs::metadata(training_path_for_matchup(paths, matchup))
+        .map_or(0, |m| m.len() / APPROX_JSONL_BYTES_PER_EXAMPLE);
+
+    num_sharded + num_jsonl
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handy_core::utils::string_to_pile;
+
+    fn example(pile_str: &str, eval: StateEval) -> DepthModeTrainingExample {
+        DepthModeTrainingExample {
+            pile: string_to_pile(pile_str),
+            eval,
+            confidence: LabelConfidence::Exact,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("handy_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_pack_round_trip() {
+        for ex in [
+            example("1A 2B 6C 7D", StateEval::Win(3)),
+            example("3B 102A 2B 101C 6B 8B 4B 1B 9B 5B 7B 103D", StateEval::Loss),
+            DepthModeTrainingExample {
+                pile: Pile::new(),
+                eval: StateEval::Win(0),
+                confidence: LabelConfidence::Exact,
+            },
+            DepthModeTrainingExample {
+                pile: string_to_pile("1A 2A 6A 7A"),
+                eval: StateEval::Win(126),
+                confidence: LabelConfidence::UpperBound,
+            },
+        ] {
+            let record = pack_example(&ex).unwrap();
+            assert_eq!(unpack_example(&record).unwrap(), ex);
+        }
+
+        assert!(pack_example(&example("1A", StateEval::Win(1000))).is_err());
+        assert!(pack_example(&example("1A", StateEval::Win(127))).is_err());
+        assert!(unpack_example(&[0; 3]).is_err());
+    }
+
+    #[test]
+    fn test_writers_keep_each_others_shards() {
+        let dir = temp_dir("two_writers");
+        let mut first = ShardedExampleWriter::open(&dir).unwrap();
+        let mut second = ShardedExampleWriter::open(&dir).unwrap();
+        let first_examples = vec![example("1A 2A 6A 7A", StateEval::Win(3))];
+        let second_examples = vec![example("2A 1A 6A 7A", StateEval::Win(4))];
+        assert_eq!(first.write_examples(first_examples.into_iter()).unwrap(), 1);
+        assert_eq!(
+            second.write_examples(second_examples.into_iter()).unwrap(),
+            1
+        );
+        assert_eq!(read_shard_index(&dir).unwrap().shards.len(), 2);
+        assert!(!dir.join(LOCK_FILE_NAME).exists());
+
+        // An index that can't be read isn't replaced by one with only the new shards
+        fs::write(dir.join(INDEX_FILE_NAME), "{").unwrap();
+        let more = vec![example("6A 1A 2A 7A", StateEval::Win(5))];
+        assert!(first.write_examples(more.into_iter()).is_err());
+        assert_eq!(fs::read_to_string(dir.join(INDEX_FILE_NAME)).unwrap(), "{");
+        assert!(ShardedExampleWriter::open(&dir).is_err());
+        assert!(!dir.join(LOCK_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn test_sharded_write_dedups_and_streams() {
+        let dir = temp_dir("shards");
+        let first = vec![
+            example("1A 2A 6A 7A", StateEval::Win(3)),
+            example("7A 6A 2A 1C", StateEval::Loss),
+            example("1A 2A 6A 7A", StateEval::Win(5)),
+        ];
+
+        let mut writer = ShardedExampleWriter::open(&dir).unwrap();
+        assert_eq!(writer.write_examples(first.into_iter()).unwrap(), 2);
+
+        // A later run only adds the piles it hasn't seen
+        let mut writer = ShardedExampleWriter::open(&dir).unwrap();
+        assert_eq!(writer.num_seen_piles(), 2);
+        let second = vec![
+            example("7A 6A 2A 1C", StateEval::Loss),
+            example("2A 1A 6A 7A", StateEval::Win(4)),
+        ];
+        assert_eq!(writer.write_examples(second.into_iter()).unwrap(), 1);
+        assert_eq!(writer.write_examples(std::iter::empty()).unwrap(), 0);
+
+        let index = read_shard_index(&dir).unwrap();
+        assert_eq!(index.shards.len(), 2);
+        assert_eq!(index.num_examples(), 3);
+
+        let examples: Vec<DepthModeTrainingExample> = read_sharded_examples(&dir)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            examples,
+            vec![
+                example("1A 2A 6A 7A", StateEval::Win(3)),
+                example("7A 6A 2A 1C", StateEval::Loss),
+                example("2A 1A 6A 7A", StateEval::Win(4)),
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sharded_write_keeps_better_labels() {
+        let dir = temp_dir("better_labels");
+        let bounded = |depth: usize| DepthModeTrainingExample {
+            confidence: LabelConfidence::UpperBound,
+            ..example("1A 2A 6A 7A", StateEval::Win(depth))
+        };
+
+        let mut writer = ShardedExampleWriter::open(&dir).unwrap();
+        let first = vec![
+            bounded(6),
+            example("7A 6A 2A 1C", StateEval::Loss),
+            bounded(5),
+        ];
+        assert_eq!(writer.write_examples(first.into_iter()).unwrap(), 3);
+        // Exact beats a bound of the same depth, and a shorter win beats anything
+        let mut writer = ShardedExampleWriter::open(&dir).unwrap();
+        assert_eq!(
+            writer.write_examples(vec![bounded(7)].into_iter()).unwrap(),
+            0
+        );
+        let better = vec![example("1A 2A 6A 7A", StateEval::Win(5))];
+        assert_eq!(writer.write_examples(better.into_iter()).unwrap(), 1);
+        let better = vec![bounded(4)];
+        assert_eq!(writer.write_examples(better.into_iter()).unwrap(), 1);
+
+        let examples: Vec<DepthModeTrainingExample> = read_sharded_examples(&dir)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            examples,
+            vec![bounded(4), example("7A 6A 2A 1C", StateEval::Loss)]
+        );
+        assert_eq!(read_shard_index(&dir).unwrap().num_examples(), 2);
+
+        // Reopening reads the labels file rather than the shards, so losing the shards' contents
+        // doesn't lose the labels
+        let shard_path = dir.join(&read_shard_index(&dir).unwrap().shards[0].file_name);
+        let shard_bytes = fs::read(&shard_path).unwrap();
+        fs::write(&shard_path, &shard_bytes[..SHARD_HEADER_BYTES]).unwrap();
+        let mut writer = ShardedExampleWriter::open(&dir).unwrap();
+        assert_eq!(writer.num_seen_piles(), 2);
+        assert_eq!(
+            writer.write_examples(vec![bounded(5)].into_iter()).unwrap(),
+            0
+        );
+        fs::write(&shard_path, &shard_bytes).unwrap();
+
+        // A partly written entry is dropped, and shards the file doesn't cover get scanned
+        fs::remove_file(dir.join(LABELS_FILE_NAME)).unwrap();
+        let writer = ShardedExampleWriter::open(&dir).unwrap();
+        assert_eq!(writer.num_seen_piles(), 2);
+        let mut labels_bytes = fs::read(dir.join(LABELS_FILE_NAME)).unwrap();
+        labels_bytes.extend_from_slice(&[1, 2, 3]);
+        fs::write(dir.join(LABELS_FILE_NAME), labels_bytes).unwrap();
+        let mut writer = ShardedExampleWriter::open(&dir).unwrap();
+        assert_eq!(writer.num_seen_piles(), 2);
+        let more = vec![example("2A 1A 6A 7A", StateEval::Win(4))];
+        assert_eq!(writer.write_examples(more.into_iter()).unwrap(), 1);
+        let writer = ShardedExampleWriter::open(&dir).unwrap();
+        assert_eq!(writer.num_seen_piles(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_old_shard_versions() {
+        let dir = temp_dir("old_shards");
+        let mut writer = ShardedExampleWriter::open(&dir).unwrap();
+        let examples = vec![example("1A 2A 6A 7A", StateEval::Win(3))];
+        writer.write_examples(examples.into_iter()).unwrap();
+
+        let shard_path = dir.join(&read_shard_index(&dir).unwrap().shards[0].file_name);
+        let mut bytes = fs::read(&shard_path).unwrap();
+        bytes[4] = 1;
+        fs::write(&shard_path, bytes).unwrap();
+        let err = ShardReader::open(&shard_path).err().unwrap();
+        assert!(err.contains("version 1 shard"), "{err}");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
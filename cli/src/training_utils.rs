@@ -22,6 +22,20 @@ pub enum StateEval {
     Loss,
 }
 
+/// Whether a label is the true distance to a win, or only the length of some winning path
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, Eq, Hash)]
+pub enum LabelConfidence {
+    #[default]
+    Exact,
+    UpperBound,
+}
+
+impl LabelConfidence {
+    pub fn is_exact(&self) -> bool {
+        *self == LabelConfidence::Exact
+    }
+}
+
 pub fn state_eval_to_score(s: StateEval) -> usize {
     match s {
         StateEval::Win(v) => v,
@@ -53,4 +67,6 @@ pub struct DepthModeTrainingExample {
     )]
     pub pile: Pile,
     pub eval: StateEval,
+    #[serde(default, skip_serializing_if = "LabelConfidence::is_exact")]
+    pub confidence: LabelConfidence,
 }
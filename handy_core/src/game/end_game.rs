@@ -4,7 +4,10 @@ use crate::game::Features;
 use enum_map::{enum_map, EnumMap};
 use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, strum_macros::EnumString,
+)]
+#[strum(serialize_all = "snake_case")]
 pub enum GameEndCheckType {
     Standard,
     PerHeroClass,
@@ -2771,7 +2771,7 @@ mod tests {
     #[test]
     fn test_bug5() {
         // Not showing pull action
-        let pile = string_to_pile("p2A, o9C, p4B, p3B, p1A, o8B, o7C, p5D, o6B");
+        let pile = string_to_pile("2A, 9C, 4B, 3B, 1A, 8B, 7C, 5D, 6B");
         let new_states = GameStateEvaluator::new(get_identity_fn()).resolve_player_action(
             &T::new(pile),
             &WrappedAction {
@@ -4493,7 +4493,7 @@ mod tests {
 
     #[test]
     fn test_ooze_roll_delay_no_health() {
-        let pile = string_to_pile("3A 69A 1C 1A 1A");
+        let pile = string_to_pile("3A 69A 1C 2A 4A");
 
         let new_states = GameStateEvaluator::new(get_identity_fn()).move_card_by_up_to_amount(
             &T::new(pile),
@@ -4506,8 +4506,8 @@ mod tests {
         assert_actual_vs_expected_piles(
             &new_states,
             vec![
-                "3A 1C 69A 1A 1A", // Move by 1, no roll
-                "3A 1C 1D 69B 1A", // Move by 2 and roll
+                "3A 1C 69A 2A 4A", // Move by 1, no roll
+                "3A 1C 2D 69B 4A", // Move by 2 and roll
             ],
         );
     }
@@ -1,12 +1,28 @@
 pub mod a_star;
+pub mod beam;
+pub mod ensemble;
+pub mod game_report;
 pub mod ida;
+pub mod loss_cache;
+pub mod mlp_model;
 pub mod model;
+pub mod model_envelope;
 pub mod model_t;
+pub mod move_analysis;
 pub mod pile_mapping;
+pub mod semantic_model;
 pub mod tiny_pile;
 pub mod vectorize;
 
+pub use beam::*;
+pub use ensemble::*;
+pub use game_report::*;
+pub use loss_cache::*;
+pub use mlp_model::*;
 pub use model::*;
+pub use model_envelope::*;
 pub use model_t::*;
+pub use move_analysis::*;
+pub use semantic_model::*;
 pub use tiny_pile::*;
 pub use vectorize::*;
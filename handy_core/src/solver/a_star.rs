@@ -1,12 +1,17 @@
 use crate::game::end_game::is_game_winner;
 use crate::game::*;
+use crate::solver::loss_cache::LossCache;
 use crate::solver::model_t::ModelT;
 use crate::solver::tiny_pile::*;
 use crate::utils::*;
 use end_game::GameEndCheckType;
 use priq::PriorityQueue;
+use serde::Serialize;
 use std::collections::BTreeMap;
 use std::fmt::Debug;
+use std::mem::size_of;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // BTree is slower, but memory is more compact, and resize events are gradual
 // type SeenMap = HashMap<StoredPileT, SolverState>;
@@ -23,6 +28,32 @@ pub struct SolverState<StoredPileT> {
 // Stop if the next state in the queue has fscore > maxdepth + DEFAULT_F_SCORE_END_CUTOFF
 const DEFAULT_F_SCORE_END_CUTOFF_FROM_MAX: f32 = 20.0;
 
+// Rough per-entry cost of the BTreeMap nodes, on top of the key and value
+const SEEN_MAP_ENTRY_OVERHEAD_BYTES: usize = 16;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SolverBudget {
+    // Estimated size of the seen map and queue. Once exceeded, the queue is trimmed down to
+    // its best states, and optimality is no longer guaranteed.
+    pub max_memory_bytes: Option<usize>,
+    pub time_limit: Option<Duration>,
+}
+
+/// How the solver uses the model's loss probability
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LossPruning {
+    // Added to the fscore of each state, scaled by the loss probability
+    pub penalty: f32,
+    // States that are at least this likely to lose are dropped
+    pub prune_threshold: Option<f32>,
+}
+
+impl LossPruning {
+    pub fn is_active(&self) -> bool {
+        self.penalty > 0.0 || self.prune_threshold.is_some()
+    }
+}
+
 pub struct AStarSolver<StoredPileT, StorageConverterT> {
     pub tiny_pile_converter: StorageConverterT,
     pub seen_states: SeenMap<StoredPileT>,
@@ -37,11 +68,27 @@ pub struct AStarSolver<StoredPileT, StorageConverterT> {
     pub max_iters: usize,
     pub best_win: Option<StoredPileT>,
     pub game_end_check_type: GameEndCheckType,
+    pub loss_cache: Option<Arc<LossCache>>,
+    pub total_loss_cache_hits: usize,
+    pub budget: SolverBudget,
+    // Only set once a time limit is used, since Instant isn't available everywhere
+    pub start_time: Option<Instant>,
+    pub total_memory_trims: usize,
+    // True if states were dropped, so the best win might not be the shortest,
+    // and running out of states doesn't prove a loss
+    pub is_optimality_lost: bool,
+    // Cached, since checking admissibility can mean scanning every weight of the model.
+    // A loss penalty adds to the model's score, so it's never admissible with one.
+    pub is_model_admissible: bool,
+    pub loss_pruning: LossPruning,
+    pub total_loss_prunes: usize,
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum AStarDoneReason {
     MaxIters,
+    TimeLimit,
     FScoreCutoff,
     EmptyQueue,
 }
@@ -85,6 +132,8 @@ where
         let fscore_depth_delta = DEFAULT_F_SCORE_END_CUTOFF_FROM_MAX;
         let default_max_depth = 250;
 
+        let is_model_admissible = model.is_admissible();
+
         Self {
             tiny_pile_converter,
             seen_states,
@@ -99,6 +148,15 @@ where
             max_iters: usize::MAX,
             best_win: None,
             game_end_check_type: GameEndCheckType::Standard,
+            loss_cache: None,
+            total_loss_cache_hits: 0,
+            budget: SolverBudget::default(),
+            start_time: None,
+            total_memory_trims: 0,
+            is_optimality_lost: false,
+            is_model_admissible,
+            loss_pruning: LossPruning::default(),
+            total_loss_prunes: 0,
         }
     }
 
@@ -106,6 +164,39 @@ where
         self.game_end_check_type = game_end_check_type;
     }
 
+    // Piles in the cache are pruned as soon as they're generated
+    pub fn set_loss_cache(&mut self, loss_cache: Arc<LossCache>) {
+        self.loss_cache = Some(loss_cache);
+    }
+
+    pub fn set_budget(&mut self, budget: SolverBudget) {
+        self.budget = budget;
+    }
+
+    pub fn set_max_memory_bytes(&mut self, max_memory_bytes: usize) {
+        self.budget.max_memory_bytes = Some(max_memory_bytes);
+    }
+
+    pub fn set_time_limit(&mut self, time_limit: Duration) {
+        self.budget.time_limit = Some(time_limit);
+    }
+
+    pub fn set_loss_pruning(&mut self, loss_pruning: LossPruning) {
+        self.loss_pruning = loss_pruning;
+        self.is_model_admissible = self.model.is_admissible() && loss_pruning.penalty <= 0.0;
+    }
+
+    pub fn set_loss_penalty(&mut self, penalty: f32) {
+        self.set_loss_pruning(LossPruning {
+            penalty,
+            ..self.loss_pruning
+        });
+    }
+
+    pub fn set_loss_prune_threshold(&mut self, prune_threshold: f32) {
+        self.loss_pruning.prune_threshold = Some(prune_threshold);
+    }
+
     pub fn set_g_bias(&mut self, g_bias: f32) {
         self.g_bias = g_bias;
         self.h_bias = 2.0 - g_bias;
@@ -139,6 +230,17 @@ where
         if self.total_iters > self.max_iters {
             return AStarIterResult::Done(AStarDoneReason::MaxIters);
         }
+        if let Some(time_limit) = self.budget.time_limit {
+            let start_time = *self.start_time.get_or_insert_with(Instant::now);
+            if start_time.elapsed() > time_limit {
+                return AStarIterResult::Done(AStarDoneReason::TimeLimit);
+            }
+        }
+        if let Some(max_memory_bytes) = self.budget.max_memory_bytes {
+            if self.estimated_memory_bytes() > max_memory_bytes {
+                self.trim_to_memory_bytes(max_memory_bytes / 2);
+            }
+        }
         let Some((f_score, tiny_pile)) = self.queue.pop() else {
             return AStarIterResult::Done(AStarDoneReason::EmptyQueue);
         };
@@ -185,22 +287,54 @@ where
                 continue;
             }
 
+            if self
+                .loss_cache
+                .as_ref()
+                .map_or(false, |loss_cache| loss_cache.contains(&new_pile))
+            {
+                self.total_loss_cache_hits += 1;
+                continue;
+            }
+
+            let loss_probability = if self.loss_pruning.is_active() {
+                self.model.loss_probability(&new_pile)
+            } else {
+                0.0
+            };
+            if self
+                .loss_pruning
+                .prune_threshold
+                .map_or(false, |threshold| loss_probability >= threshold)
+            {
+                // Unlike the loss cache, this is only a guess
+                self.total_loss_prunes += 1;
+                self.is_optimality_lost = true;
+                continue;
+            }
+            let loss_score = self.loss_pruning.penalty * loss_probability;
+
             if let Some(current_child_entry) = self.seen_states.get_mut(&new_tiny_pile) {
                 if current_child_entry.depth > child_depth as DepthType {
                     current_child_entry.depth = child_depth as DepthType;
                     current_child_entry.parent = Some(tiny_pile.clone());
 
                     let new_score = self.g_bias * child_depth as f32
-                        + self.h_bias * self.model.score_pile(&new_pile);
+                        + self.h_bias * self.model.score_pile(&new_pile)
+                        + loss_score;
                     if new_score <= self.max_fscore {
                         self.queue.put(new_score, new_tiny_pile);
+                    } else {
+                        self.note_fscore_drop();
                     }
                 }
             } else {
                 let new_score = self.g_bias * child_depth as f32
-                    + self.h_bias * self.model.score_pile(&new_pile);
+                    + self.h_bias * self.model.score_pile(&new_pile)
+                    + loss_score;
                 if new_score <= self.max_fscore {
                     self.queue.put(new_score, new_tiny_pile.clone());
+                } else {
+                    self.note_fscore_drop();
                 }
 
                 self.seen_states.insert(
@@ -215,6 +349,80 @@ where
         return AStarIterResult::Continue(DoneIterResult::Computed);
     }
 
+    // An overestimating model can push a winning state past the cutoff before any win is found
+    fn note_fscore_drop(&mut self) {
+        if self.best_win.is_none() && !self.is_model_admissible {
+            self.is_optimality_lost = true;
+        }
+    }
+
+    pub fn estimated_memory_bytes(&self) -> usize {
+        Self::estimate_memory_bytes(self.seen_states.len(), self.queue.len())
+    }
+
+    fn estimate_memory_bytes(seen_len: usize, queue_len: usize) -> usize {
+        let seen_entry_bytes = size_of::<StoredPileT>()
+            + size_of::<SolverState<StoredPileT>>()
+            + SEEN_MAP_ENTRY_OVERHEAD_BYTES;
+        let queue_entry_bytes = size_of::<f32>() + size_of::<StoredPileT>();
+
+        seen_len * seen_entry_bytes + queue_len * queue_entry_bytes
+    }
+
+    // Moves a pile and all of its ancestors from the seen map into new_seen_states
+    fn move_ancestors(
+        &mut self,
+        tiny_pile: &StoredPileT,
+        new_seen_states: &mut SeenMap<StoredPileT>,
+    ) {
+        let mut current = tiny_pile.clone();
+        while !new_seen_states.contains_key(&current) {
+            let Some(state) = self.seen_states.remove(&current) else {
+                return;
+            };
+            let parent = state.parent.clone();
+            new_seen_states.insert(current, state);
+
+            let Some(parent) = parent else {
+                return;
+            };
+            current = parent;
+        }
+    }
+
+    /// Beam-style fallback for when memory runs low: keep only the best states in the queue,
+    /// along with the seen entries needed to unroll them.
+    pub fn trim_to_memory_bytes(&mut self, target_bytes: usize) {
+        let mut new_seen_states = SeenMap::<StoredPileT>::new();
+        let mut new_queue = PriorityQueue::new();
+
+        if let Some(best_win) = self.best_win.clone() {
+            self.move_ancestors(&best_win, &mut new_seen_states);
+        }
+
+        while Self::estimate_memory_bytes(new_seen_states.len(), new_queue.len()) < target_bytes {
+            let Some((f_score, tiny_pile)) = self.queue.pop() else {
+                break;
+            };
+            if f_score > self.max_fscore {
+                break;
+            }
+            if !self.seen_states.contains_key(&tiny_pile)
+                && !new_seen_states.contains_key(&tiny_pile)
+            {
+                continue;
+            }
+
+            self.move_ancestors(&tiny_pile, &mut new_seen_states);
+            new_queue.put(f_score, tiny_pile);
+        }
+
+        self.seen_states = new_seen_states;
+        self.queue = new_queue;
+        self.total_memory_trims += 1;
+        self.is_optimality_lost = true;
+    }
+
     fn clear_depth(&mut self) {
         self.seen_states.retain(|_, v| v.depth < self.max_depth);
     }
@@ -238,7 +446,14 @@ where
             new_queue.put(k, v);
         }
 
-        self.clamp_max_fscore(self.queue.peek().unwrap().0);
+        if let Some((next_fscore, _)) = self.queue.peek() {
+            let next_fscore = *next_fscore;
+            // States that are left behind under the cutoff might have led to a better win
+            if next_fscore <= self.max_fscore {
+                self.is_optimality_lost = true;
+            }
+            self.clamp_max_fscore(next_fscore);
+        }
         self.queue = new_queue;
     }
 
@@ -307,3 +522,123 @@ where
         self.tiny_pile_converter.tiny_pile_to_pile(&tiny_pile)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::model::{vec_to_model, Model};
+    use crate::solver::vectorize::Vectorize;
+
+    fn new_solver_for_pile(pile: &Pile) -> AStarSolver<Pile, NoopPileStorageConverter> {
+        let model = Model::new_empty_for_pile(pile);
+        AStarSolver::<Pile, NoopPileStorageConverter>::new(&[pile.clone()], Box::new(model))
+    }
+
+    fn run_to_completion(
+        solver: &mut AStarSolver<Pile, NoopPileStorageConverter>,
+    ) -> AStarDoneReason {
+        loop {
+            if let AStarIterResult::Done(reason) = solver.single_iter() {
+                return reason;
+            }
+        }
+    }
+
+    #[test]
+    fn test_memory_budget_reports_unproven() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let mut unbounded = new_solver_for_pile(&pile);
+        run_to_completion(&mut unbounded);
+        assert!(!unbounded.is_optimality_lost);
+
+        // Trimming can drop every winning line, so a win isn't promised, only that the budget is
+        // kept and that whatever is found isn't claimed to be optimal
+        for max_memory_bytes in [20_000, 50_000, 100_000] {
+            let mut bounded = new_solver_for_pile(&pile);
+            bounded.set_max_memory_bytes(max_memory_bytes);
+            run_to_completion(&mut bounded);
+
+            assert!(bounded.total_memory_trims > 0);
+            assert!(bounded.is_optimality_lost);
+            assert!(bounded.estimated_memory_bytes() <= max_memory_bytes);
+
+            if let Some(best_win) = bounded.best_win.clone() {
+                let path = bounded.unroll_state(best_win);
+                assert_eq!(path[0], pile);
+                assert!(bounded.max_depth >= unbounded.max_depth);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reset_queue_drops_states() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let mut solver = new_solver_for_pile(&pile);
+        for _ in 0..10 {
+            solver.single_iter();
+        }
+        solver.set_max_iters(solver.total_iters + 2);
+        assert!(solver.queue.len() > solver.max_iters - solver.total_iters);
+
+        solver.reset_queue_and_fscore();
+        assert_eq!(solver.queue.len(), solver.max_iters - solver.total_iters);
+        assert!(solver.is_optimality_lost);
+    }
+
+    #[test]
+    fn test_reset_queue_keeps_small_queues() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let mut solver = new_solver_for_pile(&pile);
+        solver.set_max_iters(100_000);
+        solver.single_iter();
+
+        solver.reset_queue_and_fscore();
+        assert!(!solver.is_optimality_lost);
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let mut solver = new_solver_for_pile(&pile);
+        solver.set_time_limit(Duration::ZERO);
+        assert!(matches!(
+            run_to_completion(&mut solver),
+            AStarDoneReason::TimeLimit
+        ));
+    }
+
+    #[test]
+    fn test_loss_prune_threshold() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let cards: Vec<CardId> = pile.iter().map(|c| c.get_card_id()).collect();
+        let mut model = Model::new_empty_for_cards(&cards);
+        let mut head_vec = vec![0.0; model.vectorize().len()];
+        // Every pile looks lost
+        head_vec[0] = 5.0;
+        model.set_loss_head(Some(vec_to_model(&head_vec, &cards)));
+
+        let mut penalized = AStarSolver::<Pile, NoopPileStorageConverter>::new(
+            &[pile.clone()],
+            Box::new(model.clone()),
+        );
+        assert!(penalized.is_model_admissible);
+        penalized.set_loss_penalty(1.0);
+        // The penalty can overestimate, so the win found isn't proven shortest
+        assert!(!penalized.is_model_admissible);
+        run_to_completion(&mut penalized);
+        assert!(penalized.best_win.is_some());
+        penalized.set_loss_penalty(0.0);
+        assert!(penalized.is_model_admissible);
+
+        let mut pruned =
+            AStarSolver::<Pile, NoopPileStorageConverter>::new(&[pile.clone()], Box::new(model));
+        pruned.set_loss_prune_threshold(0.9);
+        assert!(matches!(
+            run_to_completion(&mut pruned),
+            AStarDoneReason::EmptyQueue
+        ));
+        assert!(pruned.best_win.is_none());
+        assert!(pruned.total_loss_prunes > 0);
+        assert!(pruned.is_optimality_lost);
+    }
+}
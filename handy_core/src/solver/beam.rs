@@ -0,0 +1,149 @@
+use crate::game::end_game::{is_game_winner, GameEndCheckType};
+use crate::game::*;
+use crate::solver::a_star::DepthType;
+use crate::solver::model_t::ModelT;
+use crate::utils::*;
+use std::collections::HashMap;
+
+pub const DEFAULT_BEAM_WIDTH: usize = 1000;
+pub const DEFAULT_BEAM_MAX_DEPTH: DepthType = 60;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeamSolution {
+    // Starts with the seed pile, and ends with the winning pile
+    pub piles: Vec<Pile>,
+    // events[i] takes piles[i] to piles[i + 1]
+    pub events: Vec<Vec<Event>>,
+}
+
+impl BeamSolution {
+    pub fn depth(&self) -> usize {
+        self.piles.len() - 1
+    }
+}
+
+/// Level by level search, that only keeps the best `beam_width` piles of each level according
+/// to the model. Much faster than A*, but solutions aren't guaranteed to be the shortest.
+pub struct BeamSolver {
+    pub model: Box<dyn ModelT>,
+    pub beam_width: usize,
+    pub max_depth: DepthType,
+    pub game_end_check_type: GameEndCheckType,
+}
+
+impl BeamSolver {
+    pub fn new(model: Box<dyn ModelT>) -> Self {
+        Self {
+            model,
+            beam_width: DEFAULT_BEAM_WIDTH,
+            max_depth: DEFAULT_BEAM_MAX_DEPTH,
+            game_end_check_type: GameEndCheckType::Standard,
+        }
+    }
+
+    pub fn set_beam_width(&mut self, beam_width: usize) {
+        self.beam_width = beam_width;
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: DepthType) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn set_game_end_check_type(&mut self, game_end_check_type: GameEndCheckType) {
+        self.game_end_check_type = game_end_check_type;
+    }
+
+    pub fn solve(&self, seed_piles: &[Pile]) -> Option<BeamSolution> {
+        let mut parents: HashMap<Pile, Option<Pile>> =
+            seed_piles.iter().map(|pile| (pile.clone(), None)).collect();
+        let mut current_level: Vec<Pile> = seed_piles.to_vec();
+
+        for _ in 0..self.max_depth {
+            let mut next_level: Vec<(f32, Pile)> = Vec::new();
+
+            for pile in &current_level {
+                for state in resolve_top_card(&GameStateNoEventLog::new(pile.clone())) {
+                    let child_pile = state.pile;
+                    if parents.contains_key(&child_pile) {
+                        continue;
+                    }
+
+                    match is_game_winner(&child_pile, self.game_end_check_type) {
+                        WinType::Win => {
+                            parents.insert(child_pile.clone(), Some(pile.clone()));
+                            return Some(Self::unroll_solution(&parents, child_pile));
+                        }
+                        WinType::Lose => continue,
+                        WinType::Unresolved => (),
+                    }
+
+                    parents.insert(child_pile.clone(), Some(pile.clone()));
+                    next_level.push((self.model.score_pile(&child_pile), child_pile));
+                }
+            }
+
+            if next_level.len() > self.beam_width {
+                next_level.select_nth_unstable_by(self.beam_width, |a, b| a.0.total_cmp(&b.0));
+                next_level.truncate(self.beam_width);
+            }
+
+            if next_level.is_empty() {
+                return None;
+            }
+            current_level = next_level.into_iter().map(|(_, pile)| pile).collect();
+        }
+
+        None
+    }
+
+    fn unroll_solution(parents: &HashMap<Pile, Option<Pile>>, final_pile: Pile) -> BeamSolution {
+        let mut piles = vec![final_pile];
+        while let Some(Some(parent)) = parents.get(piles.last().unwrap()) {
+            piles.push(parent.clone());
+        }
+        piles.reverse();
+
+        let events = piles
+            .windows(2)
+            .map(|pair| find_events_between_piles(&pair[0], &pair[1]).unwrap())
+            .collect();
+
+        BeamSolution { piles, events }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::model::Model;
+
+    #[test]
+    fn test_beam_finds_valid_solution() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let solver = BeamSolver::new(Box::new(Model::new_empty_for_pile(&pile)));
+        let solution = solver.solve(&[pile.clone()]).unwrap();
+
+        assert_eq!(solution.piles[0], pile);
+        assert_eq!(solution.events.len(), solution.depth());
+        assert_eq!(
+            is_game_winner(solution.piles.last().unwrap(), GameEndCheckType::Standard),
+            WinType::Win
+        );
+        for (pair, events) in solution.piles.windows(2).zip(solution.events.iter()) {
+            assert_eq!(
+                find_events_between_piles(&pair[0], &pair[1]).as_ref(),
+                Some(events)
+            );
+        }
+    }
+
+    #[test]
+    fn test_beam_respects_max_depth() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let mut solver = BeamSolver::new(Box::new(Model::new_empty_for_pile(&pile)));
+        let solution = solver.solve(&[pile.clone()]).unwrap();
+
+        solver.set_max_depth(solution.depth() as DepthType - 1);
+        assert!(solver.solve(&[pile]).is_none());
+    }
+}
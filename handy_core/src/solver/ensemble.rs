@@ -0,0 +1,349 @@
+use crate::game::*;
+use crate::solver::model_t::ModelT;
+use serde::{Deserialize, Serialize};
+
+/// Weighted sum of the member scores. The loss probability is the weighted mean.
+pub struct WeightedEnsemble {
+    members: Vec<(f32, Box<dyn ModelT>)>,
+}
+
+impl WeightedEnsemble {
+    pub fn new(members: Vec<(f32, Box<dyn ModelT>)>) -> Self {
+        Self { members }
+    }
+}
+
+impl ModelT for WeightedEnsemble {
+    fn score_pile(&self, pile: &Pile) -> f32 {
+        self.members
+            .iter()
+            .map(|(weight, model)| weight * model.score_pile(pile))
+            .sum()
+    }
+
+    fn loss_probability(&self, pile: &Pile) -> f32 {
+        let total_weight: f32 = self.members.iter().map(|(weight, _)| weight.abs()).sum();
+        if total_weight == 0.0 {
+            return 0.0;
+        }
+        self.members
+            .iter()
+            .map(|(weight, model)| weight.abs() * model.loss_probability(pile))
+            .sum::<f32>()
+            / total_weight
+    }
+}
+
+/// The largest member score, so admissible members stay admissible and the most informed one wins
+pub struct MaxOfModel {
+    members: Vec<Box<dyn ModelT>>,
+}
+
+impl MaxOfModel {
+    pub fn new(members: Vec<Box<dyn ModelT>>) -> Self {
+        Self { members }
+    }
+}
+
+impl ModelT for MaxOfModel {
+    fn score_pile(&self, pile: &Pile) -> f32 {
+        self.members
+            .iter()
+            .map(|model| model.score_pile(pile))
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+
+    fn loss_probability(&self, pile: &Pile) -> f32 {
+        self.members
+            .iter()
+            .map(|model| model.loss_probability(pile))
+            .fold(0.0, f32::max)
+    }
+
+    fn is_admissible(&self) -> bool {
+        self.members.iter().all(|model| model.is_admissible())
+    }
+}
+
+/// Weights each member by the fraction of the pile's cards that belong to its classes, so the
+/// specialist for whichever classes dominate the pile counts the most. Members must be able to
+/// score every card in the piles they see. Piles with no gated cards go to the fallback.
+pub struct ClassGatedModel {
+    members: Vec<(Vec<Class>, Box<dyn ModelT>)>,
+    fallback: Option<Box<dyn ModelT>>,
+}
+
+impl ClassGatedModel {
+    pub fn new(
+        members: Vec<(Vec<Class>, Box<dyn ModelT>)>,
+        fallback: Option<Box<dyn ModelT>>,
+    ) -> Self {
+        Self { members, fallback }
+    }
+
+    /// Normalized weight of each member for the pile, all zero if no member's classes are in it
+    pub fn gate_weights(&self, pile: &Pile) -> Vec<f32> {
+        let mut weights: Vec<f32> = self
+            .members
+            .iter()
+            .map(|(classes, _)| {
+                pile.iter()
+                    .filter(|card| classes.contains(&card.get_card_def().class))
+                    .count() as f32
+            })
+            .collect();
+
+        let total: f32 = weights.iter().sum();
+        if total > 0.0 {
+            for weight in weights.iter_mut() {
+                *weight /= total;
+            }
+        }
+        weights
+    }
+
+    fn gated_mean(&self, pile: &Pile, f: impl Fn(&dyn ModelT) -> f32) -> Option<f32> {
+        let weights = self.gate_weights(pile);
+        if weights.iter().all(|weight| *weight == 0.0) {
+            return None;
+        }
+        Some(
+            self.members
+                .iter()
+                .zip(weights)
+                .filter(|(_, weight)| *weight > 0.0)
+                .map(|((_, model), weight)| weight * f(model.as_ref()))
+                .sum(),
+        )
+    }
+}
+
+impl ModelT for ClassGatedModel {
+    fn score_pile(&self, pile: &Pile) -> f32 {
+        self.gated_mean(pile, |model| model.score_pile(pile))
+            .or_else(|| self.fallback.as_ref().map(|model| model.score_pile(pile)))
+            .unwrap_or(0.0)
+    }
+
+    fn loss_probability(&self, pile: &Pile) -> f32 {
+        self.gated_mean(pile, |model| model.loss_probability(pile))
+            .or_else(|| {
+                self.fallback
+                    .as_ref()
+                    .map(|model| model.loss_probability(pile))
+            })
+            .unwrap_or(0.0)
+    }
+}
+
+/// Linear blend of named member models, fit on held out examples
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct BlendWeights {
+    pub member_names: Vec<String>,
+    pub weights: Vec<f32>,
+    pub bias: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_loss: Option<f32>,
+}
+
+/// Weighted ridge regression of targets on member scores, with an unpenalized bias.
+/// Each row of member_scores has one score per member, and one weight in example_weights.
+pub fn fit_blend_weights(
+    member_scores: &[Vec<f32>],
+    targets: &[f32],
+    example_weights: &[f32],
+    l2: f32,
+) -> Result<(Vec<f32>, f32), String> {
+    if member_scores.is_empty()
+        || member_scores.len() != targets.len()
+        || member_scores.len() != example_weights.len()
+    {
+        return Err(format!(
+            "Need the same nonzero number of score rows, targets and weights, got {}, {} and {}",
+            member_scores.len(),
+            targets.len(),
+            example_weights.len()
+        ));
+    }
+    let num_members = member_scores[0].len();
+    if member_scores.iter().any(|row| row.len() != num_members) {
+        return Err("Every row needs a score for each member".to_owned());
+    }
+
+    // Normal equations over [scores..., 1], in f64 since scores are summed over many examples
+    let n = num_members + 1;
+    let mut a = vec![vec![0.0f64; n + 1]; n];
+    for ((row, target), weight) in member_scores.iter().zip(targets).zip(example_weights) {
+        let x: Vec<f64> = row.iter().map(|score| *score as f64).chain([1.0]).collect();
+        let weight = *weight as f64;
+        for i in 0..n {
+            for j in 0..n {
+                a[i][j] += weight * x[i] * x[j];
+            }
+            a[i][n] += weight * x[i] * *target as f64;
+        }
+    }
+    for (i, row) in a.iter_mut().enumerate().take(num_members) {
+        row[i] += l2 as f64;
+    }
+
+    // Gaussian elimination with partial pivoting
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|x, y| a[*x][col].abs().total_cmp(&a[*y][col].abs()))
+            .unwrap();
+        if a[pivot][col].abs() < 1e-12 {
+            return Err("Member scores are degenerate, try a larger l2".to_owned());
+        }
+        a.swap(col, pivot);
+        for row in 0..n {
+            if row != col {
+                let factor = a[row][col] / a[col][col];
+                for k in col..=n {
+                    a[row][k] -= factor * a[col][k];
+                }
+            }
+        }
+    }
+
+    let solution: Vec<f32> = (0..n).map(|i| (a[i][n] / a[i][i]) as f32).collect();
+    Ok((solution[..num_members].to_vec(), solution[num_members]))
+}
+
+/// Scores piles with a fitted blend. The loss probability is the most pessimistic member's.
+pub struct BlendedModel {
+    blend: BlendWeights,
+    members: Vec<Box<dyn ModelT>>,
+}
+
+impl BlendedModel {
+    pub fn new(blend: BlendWeights, members: Vec<Box<dyn ModelT>>) -> Result<Self, String> {
+        if blend.weights.len() != members.len() {
+            return Err(format!(
+                "Blend has {} weights but got {} members",
+                blend.weights.len(),
+                members.len()
+            ));
+        }
+        Ok(Self { blend, members })
+    }
+}
+
+impl ModelT for BlendedModel {
+    fn score_pile(&self, pile: &Pile) -> f32 {
+        self.blend.bias
+            + self
+                .members
+                .iter()
+                .zip(&self.blend.weights)
+                .map(|(model, weight)| weight * model.score_pile(pile))
+                .sum::<f32>()
+    }
+
+    fn loss_probability(&self, pile: &Pile) -> f32 {
+        self.members
+            .iter()
+            .map(|model| model.loss_probability(pile))
+            .fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::string_to_pile;
+
+    struct ConstModel(f32, f32);
+
+    impl ModelT for ConstModel {
+        fn score_pile(&self, _pile: &Pile) -> f32 {
+            self.0
+        }
+
+        fn loss_probability(&self, _pile: &Pile) -> f32 {
+            self.1
+        }
+    }
+
+    fn boxed(score: f32, loss: f32) -> Box<dyn ModelT> {
+        Box::new(ConstModel(score, loss))
+    }
+
+    #[test]
+    fn test_weighted_and_max() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let weighted =
+            WeightedEnsemble::new(vec![(0.25, boxed(4.0, 0.2)), (0.75, boxed(8.0, 0.6))]);
+        assert_eq!(weighted.score_pile(&pile), 7.0);
+        assert!((weighted.loss_probability(&pile) - 0.5).abs() < 1e-6);
+
+        let max_of = MaxOfModel::new(vec![boxed(4.0, 0.2), boxed(8.0, 0.1)]);
+        assert_eq!(max_of.score_pile(&pile), 8.0);
+        assert_eq!(max_of.loss_probability(&pile), 0.2);
+    }
+
+    #[test]
+    fn test_class_gating() {
+        // Two warrior cards and two ogre cards
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let gated = ClassGatedModel::new(
+            vec![
+                (vec![Class::Warrior], boxed(2.0, 0.0)),
+                (vec![Class::Ogre], boxed(6.0, 0.0)),
+                (vec![Class::Quest], boxed(100.0, 0.0)),
+            ],
+            None,
+        );
+        assert_eq!(gated.gate_weights(&pile), vec![0.5, 0.5, 0.0]);
+        assert_eq!(gated.score_pile(&pile), 4.0);
+
+        let fallback_only = ClassGatedModel::new(
+            vec![(vec![Class::Quest], boxed(100.0, 0.0))],
+            Some(boxed(3.0, 0.0)),
+        );
+        assert_eq!(fallback_only.score_pile(&pile), 3.0);
+    }
+
+    #[test]
+    fn test_fit_blend() {
+        let member_scores: Vec<Vec<f32>> = (0..50)
+            .map(|i| vec![i as f32, ((i * 7) % 11) as f32])
+            .collect();
+        let targets: Vec<f32> = member_scores
+            .iter()
+            .map(|row| 2.0 * row[0] - row[1] + 3.0)
+            .collect();
+
+        let ones = vec![1.0; targets.len()];
+        let (weights, bias) = fit_blend_weights(&member_scores, &targets, &ones, 0.0).unwrap();
+        assert!((weights[0] - 2.0).abs() < 1e-3);
+        assert!((weights[1] + 1.0).abs() < 1e-3);
+        assert!((bias - 3.0).abs() < 1e-3);
+
+        let blend = BlendWeights {
+            member_names: vec!["a".to_owned(), "b".to_owned()],
+            weights,
+            bias,
+            validation_loss: None,
+        };
+        let model = BlendedModel::new(blend, vec![boxed(1.0, 0.3), boxed(2.0, 0.0)]).unwrap();
+        assert!((model.score_pile(&string_to_pile("1A")) - 3.0).abs() < 1e-3);
+        assert_eq!(model.loss_probability(&string_to_pile("1A")), 0.3);
+
+        assert!(fit_blend_weights(&[vec![1.0], vec![1.0]], &[1.0, 2.0], &[1.0, 1.0], 0.0).is_err());
+        assert!(fit_blend_weights(&member_scores, &targets, &[1.0], 0.0).is_err());
+
+        // Rows with no weight don't pull the fit
+        let mut noisy_targets = targets.clone();
+        let mut example_weights = ones.clone();
+        for i in (0..noisy_targets.len()).step_by(5) {
+            noisy_targets[i] += 100.0;
+            example_weights[i] = 0.0;
+        }
+        let (weights, bias) =
+            fit_blend_weights(&member_scores, &noisy_targets, &example_weights, 0.0).unwrap();
+        assert!((weights[0] - 2.0).abs() < 1e-3);
+        assert!((weights[1] + 1.0).abs() < 1e-3);
+        assert!((bias - 3.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::game::end_game::GameEndCheckType;
+use crate::game::*;
+use crate::solver::model_t::ModelT;
+use crate::solver::move_analysis::*;
+use crate::utils::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    pub start_pile: Pile,
+    // The pile after each activation
+    pub piles: Vec<Pile>,
+}
+
+impl GameRecord {
+    pub fn new(start_pile: Pile) -> Self {
+        Self {
+            start_pile,
+            piles: vec![],
+        }
+    }
+
+    /// Builds a record from a list of piles, where the first pile is the start pile
+    pub fn from_piles(piles: &[Pile]) -> Option<Self> {
+        let (start_pile, rest) = piles.split_first()?;
+        Some(Self {
+            start_pile: start_pile.clone(),
+            piles: rest.to_vec(),
+        })
+    }
+
+    pub fn push(&mut self, pile: Pile) {
+        self.piles.push(pile);
+    }
+
+    pub fn all_piles(&self) -> Vec<Pile> {
+        let mut result = vec![self.start_pile.clone()];
+        result.extend(self.piles.iter().cloned());
+        result
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BetterMove {
+    pub pile: Pile,
+    pub events: Vec<Event>,
+    pub eval: PileEval,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TurnReport {
+    pub turn: usize,
+    pub pile_before: Pile,
+    pub pile_after: Pile,
+    // Distance to win before the move
+    pub eval_before: PileEval,
+    // Distance to win from pile_before, through the move that was played
+    pub eval_played: PileEval,
+    pub grade: MoveGrade,
+    // The grade compares wins that might be faster than found, so it's only an estimate
+    pub is_grade_bounded: bool,
+    // Only set if the grade shows the played move made the distance to win worse
+    pub better_move: Option<BetterMove>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameReport {
+    pub turns: Vec<TurnReport>,
+    pub final_eval: PileEval,
+}
+
+impl GameReport {
+    pub fn mistakes(&self) -> impl Iterator<Item = &TurnReport> {
+        self.turns.iter().filter(|t| t.better_move.is_some())
+    }
+}
+
+/// The next pile on the best path, if the grade shows the played move was worse than it. A tie
+/// the evals can't break, like Win(3) against WinWithin(3), isn't a mistake.
+fn find_better_move(
+    pile_before: &Pile,
+    best_path: &[Pile],
+    eval_before: PileEval,
+    grade: MoveGrade,
+) -> Option<BetterMove> {
+    if !matches!(
+        grade,
+        MoveGrade::Ok | MoveGrade::Mistake | MoveGrade::Blunder
+    ) {
+        return None;
+    }
+    // A pile that's already won has no next pile to suggest
+    eval_before.win_depth()?;
+    let better_pile = best_path.get(1)?;
+    Some(BetterMove {
+        events: find_events_between_piles(pile_before, better_pile).unwrap_or_default(),
+        pile: better_pile.clone(),
+        eval: eval_before,
+    })
+}
+
+/// The solver isn't perfect, so a later pile might find a faster win than its parent did.
+/// Walks backwards so that every pile is at least as good as playing the rest of the game. The
+/// played move is only one line, so the win stays exact only if the parent's eval already was.
+fn carry_wins_backwards(all_piles: &[Pile], evals: &mut [(PileEval, Vec<Pile>)]) {
+    for i in (0..evals.len() - 1).rev() {
+        let through_next = match (evals[i].0, evals[i + 1].0.add_turn()) {
+            (PileEval::Win(_), through_next) => through_next,
+            (_, PileEval::Win(depth)) => PileEval::WinWithin(depth),
+            (_, through_next) => through_next,
+        };
+        if through_next < evals[i].0 {
+            let mut path = vec![all_piles[i].clone()];
+            path.extend(evals[i + 1].1.iter().cloned());
+            evals[i] = (through_next, path);
+        }
+    }
+}
+
+/// Runs the solver at every step of a game, and compares each played move against the best
+/// move the solver could find.
+pub fn build_game_report<M: ModelT + Clone + 'static>(
+    record: &GameRecord,
+    model: &M,
+    game_end_check_type: GameEndCheckType,
+    max_iters_per_pile: usize,
+) -> GameReport {
+    let all_piles = record.all_piles();
+    let mut evals: Vec<(PileEval, Vec<Pile>)> = all_piles
+        .iter()
+        .map(|pile| evaluate_pile_with_path(pile, model, game_end_check_type, max_iters_per_pile))
+        .collect();
+
+    carry_wins_backwards(&all_piles, &mut evals);
+
+    let mut turns = Vec::new();
+    for i in 0..all_piles.len() - 1 {
+        let eval_before = evals[i].0;
+        let eval_played = evals[i + 1].0.add_turn();
+        let grade = grade_eval(eval_played, eval_before);
+        let is_grade_bounded = is_grade_bounded(eval_played, eval_before);
+        let better_move = find_better_move(&all_piles[i], &evals[i].1, eval_before, grade);
+
+        turns.push(TurnReport {
+            turn: i + 1,
+            pile_before: all_piles[i].clone(),
+            pile_after: all_piles[i + 1].clone(),
+            eval_before,
+            eval_played,
+            grade,
+            is_grade_bounded,
+            better_move,
+        });
+    }
+
+    GameReport {
+        turns,
+        final_eval: evals.last().unwrap().0,
+    }
+}
+
+pub fn print_game_report_custom_logger(report: &GameReport, log: &dyn Fn(&str)) {
+    for turn in &report.turns {
+        log(&format!(
+            "Turn {}: {:?} {} / played: {} ({})",
+            turn.turn,
+            turn.pile_before,
+            turn.eval_before,
+            turn.eval_played,
+            format_grade(turn.grade, turn.is_grade_bounded)
+        ));
+
+        if let Some(better_move) = &turn.better_move {
+            log(&format!(
+                "\tBetter: {:?} ({})",
+                better_move.pile, better_move.eval
+            ));
+            for e in &better_move.events {
+                log(&format!("\t\t{}", format_event_for_cli(e)));
+            }
+        }
+    }
+
+    log(&format!(
+        "{} turns, {} mistakes. Final state: {}",
+        report.turns.len(),
+        report.mistakes().count(),
+        report.final_eval
+    ));
+}
+
+pub fn print_game_report(report: &GameReport) {
+    print_game_report_custom_logger(report, &|x| println!("{}", x));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::model::Model;
+    use crate::utils::string_to_pile;
+
+    #[test]
+    fn test_record_from_piles() {
+        let a = string_to_pile("1 2 3");
+        let b = string_to_pile("2 3 1B");
+        let record = GameRecord::from_piles(&[a.clone(), b.clone()]).unwrap();
+        assert_eq!(record.start_pile, a);
+        assert_eq!(record.piles, vec![b]);
+        assert_eq!(record.all_piles().len(), 2);
+        assert!(GameRecord::from_piles(&[]).is_none());
+    }
+
+    #[test]
+    fn test_report_flags_worse_moves() {
+        let start_pile = string_to_pile("1A 2A 6A 7A");
+        let model = Model::new_empty_for_pile(&start_pile);
+        let analysis = analyze_moves(&start_pile, &model, GameEndCheckType::Standard, 2000);
+
+        let worst_win = analysis
+            .iter()
+            .filter(|a| a.eval.win_depth().is_some())
+            .last()
+            .unwrap();
+
+        let mut record = GameRecord::new(start_pile.clone());
+        record.push(worst_win.pile.clone());
+        let report = build_game_report(&record, &model, GameEndCheckType::Standard, 2000);
+
+        assert_eq!(report.turns.len(), 1);
+        let turn = &report.turns[0];
+        assert_eq!(turn.pile_after, worst_win.pile);
+        assert!(turn.eval_played > turn.eval_before);
+        assert_eq!(report.mistakes().count(), 1);
+
+        let better_move = turn.better_move.as_ref().unwrap();
+        assert_eq!(better_move.eval, analysis[0].eval);
+        assert!(!better_move.events.is_empty());
+    }
+
+    #[test]
+    fn test_report_flags_worse_moves_with_trained_model() {
+        let start_pile = string_to_pile("1A 2A 6A 7A");
+        let model: Model =
+            serde_yaml::from_str(include_str!("../../../data/models/Warrior.Ogre.yaml")).unwrap();
+        let analysis = analyze_moves(&start_pile, &model, GameEndCheckType::Standard, 100_000);
+
+        let worst_win = analysis
+            .iter()
+            .filter(|a| a.eval.win_depth().is_some())
+            .last()
+            .unwrap();
+        assert!(worst_win.eval.win_depth() > analysis[0].eval.win_depth());
+
+        let mut record = GameRecord::new(start_pile.clone());
+        record.push(worst_win.pile.clone());
+        let report = build_game_report(&record, &model, GameEndCheckType::Standard, 100_000);
+
+        let turn = &report.turns[0];
+        assert!(matches!(
+            turn.grade,
+            MoveGrade::Ok | MoveGrade::Mistake | MoveGrade::Blunder
+        ));
+        assert!(turn.is_grade_bounded);
+        assert_eq!(report.mistakes().count(), 1);
+        assert!(turn.better_move.is_some());
+    }
+
+    #[test]
+    fn test_tie_with_a_bound_is_not_a_mistake() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let path = vec![pile.clone(), string_to_pile("2A 6A 7A 1A")];
+        let eval_before = PileEval::Win(3);
+
+        let eval_played = PileEval::WinWithin(3);
+        assert!(eval_played > eval_before);
+        let grade = grade_eval(eval_played, eval_before);
+        assert_eq!(grade, MoveGrade::Best);
+        assert!(find_better_move(&pile, &path, eval_before, grade).is_none());
+
+        let grade = grade_eval(PileEval::Win(4), eval_before);
+        let better_move = find_better_move(&pile, &path, eval_before, grade).unwrap();
+        assert_eq!(better_move.pile, path[1]);
+    }
+
+    #[test]
+    fn test_solved_child_only_bounds_an_unsolved_parent() {
+        let parent = string_to_pile("1A 2A 6A 7A");
+        let child = string_to_pile("2A 6A 7A 1A");
+        let all_piles = vec![parent.clone(), child.clone()];
+
+        for parent_eval in [PileEval::Unknown, PileEval::WinWithin(6)] {
+            let mut evals = vec![
+                (parent_eval, vec![]),
+                (PileEval::Win(2), vec![child.clone()]),
+            ];
+            carry_wins_backwards(&all_piles, &mut evals);
+            assert_eq!(evals[0].0, PileEval::WinWithin(3));
+            assert_eq!(evals[0].1, all_piles);
+            assert!(is_grade_bounded(PileEval::Win(2).add_turn(), evals[0].0));
+        }
+
+        let mut evals = vec![
+            (PileEval::Win(6), vec![]),
+            (PileEval::Win(2), vec![child.clone()]),
+        ];
+        carry_wins_backwards(&all_piles, &mut evals);
+        assert_eq!(evals[0].0, PileEval::Win(3));
+    }
+
+    #[test]
+    fn test_report_continues_past_a_won_pile() {
+        let won = string_to_pile("6C 3C 2C 5D 8C 1C 4D 7C 9C");
+        let model = Model::new_empty_for_pile(&won);
+        let mut record = GameRecord::new(won.clone());
+        record.push(string_to_pile("6D 3C 2C 5D 8C 1C 4D 7C 9C"));
+        let report = build_game_report(&record, &model, GameEndCheckType::Standard, 100);
+
+        assert_eq!(report.turns[0].eval_before, PileEval::Win(0));
+        assert_eq!(report.turns[0].eval_played, PileEval::Loss);
+        assert!(report.turns[0].better_move.is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::game::end_game::standard_check_is_game_winner;
 use crate::game::*;
 use crate::solver::model::*;
+use crate::solver::model_t::ModelT;
 use std::fmt::Debug;
 
 type DepthType = u8;
@@ -17,15 +18,24 @@ pub struct IdaSolver {
     pub total_iters: usize,
     pub max_depth: DepthType,
     pub f_score_cutoff: f32,
+    // True if branches were cut before they could be ruled out, so the win found might not be
+    // the shortest, and finding none doesn't prove a loss
+    pub is_optimality_lost: bool,
+    is_model_admissible: bool,
+    has_win: bool,
 }
 
 impl IdaSolver {
     pub fn new(model: Model) -> Self {
+        let is_model_admissible = model.is_admissible();
         Self {
             model,
             total_iters: 0,
             max_depth: 250,
             f_score_cutoff: 0.0,
+            is_optimality_lost: false,
+            is_model_admissible,
+            has_win: false,
         }
     }
 
@@ -34,7 +44,8 @@ impl IdaSolver {
         let resolution = standard_check_is_game_winner(pile);
         if resolution == WinType::Win {
             self.max_depth = depth;
-            println!("found solution at depth {}", depth);
+            self.has_win = true;
+            eprintln!("found solution at depth {}", depth);
             self.f_score_cutoff = self.max_depth as f32 + F_CUTOFF;
             return NodeResult::Win(vec![pile.clone()]);
         } else if resolution == WinType::Lose {
@@ -43,6 +54,10 @@ impl IdaSolver {
 
         let child_depth = depth + 1;
         if child_depth >= self.max_depth {
+            // Once there's a win, max_depth is its length, so deeper wins don't matter
+            if !self.has_win {
+                self.is_optimality_lost = true;
+            }
             return NodeResult::Loss;
         }
 
@@ -57,6 +72,11 @@ impl IdaSolver {
         let mut best_win = vec![];
         for (child_score, child_pile) in child_states {
             if child_depth as f32 + child_score > self.f_score_cutoff {
+                // The cutoff is only a bound on the win once there is one, and only if the
+                // model never overestimates
+                if !self.has_win || !self.is_model_admissible {
+                    self.is_optimality_lost = true;
+                }
                 continue;
             }
 
@@ -85,9 +105,35 @@ impl IdaSolver {
         NodeResult::Loss
     }
 
-    pub fn solve(&mut self, pile: Pile) {
+    /// The shortest win found, from the start pile to the winning pile
+    pub fn solve(&mut self, pile: Pile) -> Option<Vec<Pile>> {
         self.f_score_cutoff = self.model.score_pile(&pile) + F_CUTOFF;
-        let result = self._solve_recursive(&pile, 0);
-        dbg!(result);
+        match self._solve_recursive(&pile, 0) {
+            NodeResult::Loss => None,
+            NodeResult::Win(mut win_path) => {
+                win_path.reverse();
+                Some(win_path)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::string_to_pile;
+
+    #[test]
+    fn test_cut_branches_lose_optimality() {
+        let won = string_to_pile("6C 3C 2C 5D 8C 1C 4D 7C 9C");
+        let mut solver = IdaSolver::new(Model::new_empty_for_pile(&won));
+        assert_eq!(solver.solve(won.clone()), Some(vec![won]));
+        assert!(!solver.is_optimality_lost);
+
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let mut solver = IdaSolver::new(Model::new_empty_for_pile(&pile));
+        solver.max_depth = 1;
+        assert_eq!(solver.solve(pile), None);
+        assert!(solver.is_optimality_lost);
     }
 }
@@ -0,0 +1,203 @@
+use crate::game::end_game::{is_game_winner, GameEndCheckType};
+use crate::game::*;
+use crate::solver::a_star::DepthType;
+use crate::utils::*;
+use std::collections::{HashMap, HashSet};
+
+pub const DEFAULT_LOSS_PROOF_TURNS: DepthType = 2;
+
+/// Set of piles that are known to lose, no matter which moves are played.
+/// Terminal losses aren't stored, since they're cheap to check directly.
+#[derive(Clone, Debug)]
+pub struct LossCache {
+    pub game_end_check_type: GameEndCheckType,
+    proven_losses: HashSet<Pile>,
+}
+
+impl LossCache {
+    pub fn new(game_end_check_type: GameEndCheckType) -> Self {
+        Self {
+            game_end_check_type,
+            proven_losses: HashSet::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.proven_losses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proven_losses.is_empty()
+    }
+
+    pub fn contains(&self, pile: &Pile) -> bool {
+        self.proven_losses.contains(pile)
+    }
+
+    pub fn insert(&mut self, pile: Pile) -> bool {
+        self.proven_losses.insert(pile)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Pile> {
+        self.proven_losses.iter()
+    }
+
+    /// Drops piles until there are at most max_len. Which ones is arbitrary, since the cache
+    /// only prunes searches, and a dropped loss can be proven again.
+    pub fn truncate(&mut self, max_len: usize) {
+        if self.len() > max_len {
+            self.proven_losses = self.proven_losses.drain().take(max_len).collect();
+        }
+    }
+
+    /// True if the pile is either lost already, or known to lose
+    pub fn is_lost(&self, pile: &Pile) -> bool {
+        self.contains(pile) || is_game_winner(pile, self.game_end_check_type) == WinType::Lose
+    }
+
+    /// Tries to prove that every line from this pile loses within max_turns activations.
+    /// Every pile proven along the way is added to the cache.
+    pub fn prove_loss(&mut self, pile: &Pile, max_turns: DepthType) -> bool {
+        self.prove_loss_collecting(pile, max_turns, &mut vec![])
+    }
+
+    /// Same as prove_loss, but also pushes every pile newly added to the cache onto new_losses
+    pub fn prove_loss_collecting(
+        &mut self,
+        pile: &Pile,
+        max_turns: DepthType,
+        new_losses: &mut Vec<Pile>,
+    ) -> bool {
+        let mut failed_at_turns: HashMap<Pile, DepthType> = HashMap::new();
+        self.prove_loss_inner(pile, max_turns, &mut failed_at_turns, new_losses)
+    }
+
+    fn prove_loss_inner(
+        &mut self,
+        pile: &Pile,
+        rem_turns: DepthType,
+        failed_at_turns: &mut HashMap<Pile, DepthType>,
+        new_losses: &mut Vec<Pile>,
+    ) -> bool {
+        if self.contains(pile) {
+            return true;
+        }
+
+        match is_game_winner(pile, self.game_end_check_type) {
+            WinType::Lose => return true,
+            WinType::Win => return false,
+            WinType::Unresolved => (),
+        }
+
+        if rem_turns == 0 {
+            return false;
+        }
+
+        if failed_at_turns
+            .get(pile)
+            .map_or(false, |failed_turns| *failed_turns >= rem_turns)
+        {
+            return false;
+        }
+
+        for child_state in resolve_top_card(&GameStateNoEventLog::new(pile.clone())) {
+            if !self.prove_loss_inner(
+                &child_state.pile,
+                rem_turns - 1,
+                failed_at_turns,
+                new_losses,
+            ) {
+                failed_at_turns.insert(pile.clone(), rem_turns);
+                return false;
+            }
+        }
+
+        self.insert(pile.clone());
+        new_losses.push(pile.clone());
+        true
+    }
+
+    /// One pile per line, in the same format as string_to_pile
+    pub fn to_lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.proven_losses.iter().map(pile_to_string)
+    }
+
+    pub fn extend_from_lines<'a>(
+        &mut self,
+        lines: impl Iterator<Item = &'a str>,
+    ) -> Result<(), String> {
+        for line in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            self.insert(string_to_pile_result(line)?);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prove_loss() {
+        let mut loss_cache = LossCache::new(GameEndCheckType::Standard);
+        let lost = string_to_pile("6D 3C 2C 5D 8C 1C 4D 7C 9C");
+        let won = string_to_pile("6C 3C 2C 5D 8C 1C 4D 7C 9C");
+
+        assert!(loss_cache.prove_loss(&lost, 0));
+        assert!(!loss_cache.prove_loss(&won, 3));
+        // Terminal losses are never stored
+        assert!(loss_cache.is_empty());
+
+        let start = string_to_pile("1A 2A 6A 7A");
+        assert!(!loss_cache.prove_loss(&start, 3));
+    }
+
+    #[test]
+    fn test_proven_losses_are_cached() {
+        let pile = string_to_pile("7A 6A 2A 1C");
+        let mut loss_cache = LossCache::new(GameEndCheckType::Standard);
+
+        assert!(!loss_cache.prove_loss(&pile, 1));
+        assert!(loss_cache.prove_loss(&pile, 2));
+        assert!(loss_cache.contains(&pile));
+
+        for child_state in resolve_top_card(&GameStateNoEventLog::new(pile.clone())) {
+            assert!(loss_cache.is_lost(&child_state.pile));
+        }
+
+        let mut loss_cache = LossCache::new(GameEndCheckType::Standard);
+        let mut new_losses = vec![];
+        assert!(loss_cache.prove_loss_collecting(&pile, 2, &mut new_losses));
+        assert!(new_losses.contains(&pile));
+        assert_eq!(new_losses.len(), loss_cache.len());
+        // Already cached, so nothing is new
+        let mut new_losses = vec![];
+        assert!(loss_cache.prove_loss_collecting(&pile, 2, &mut new_losses));
+        assert!(new_losses.is_empty());
+    }
+
+    #[test]
+    fn test_lines_round_trip() {
+        let mut loss_cache = LossCache::new(GameEndCheckType::Standard);
+        loss_cache.insert(string_to_pile("1A 2B 6C 7D"));
+        loss_cache.insert(string_to_pile("7A 6A 2A 1A"));
+
+        let lines: Vec<String> = loss_cache.to_lines().collect();
+        let mut other = LossCache::new(GameEndCheckType::Standard);
+        other
+            .extend_from_lines(lines.iter().map(|l| l.as_str()))
+            .unwrap();
+
+        assert_eq!(other.len(), 2);
+        assert!(other.contains(&string_to_pile("1A 2B 6C 7D")));
+        assert!(other.extend_from_lines(["beep"].into_iter()).is_err());
+
+        other.truncate(5);
+        assert_eq!(other.len(), 2);
+        other.truncate(1);
+        assert_eq!(other.len(), 1);
+    }
+}
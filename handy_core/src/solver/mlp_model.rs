@@ -0,0 +1,244 @@
+use crate::game::*;
+use crate::solver::model_t::ModelT;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MLP_HIDDEN_SIZE: usize = 32;
+
+const NUM_FACES: usize = 4;
+// Hero, Monster, anything else
+const NUM_ALLEGIANCE_INPUTS: usize = 3;
+const NUM_FEATURE_INPUTS: usize = 12;
+const SEMANTIC_INPUTS_PER_POSITION: usize = NUM_ALLEGIANCE_INPUTS + NUM_FEATURE_INPUTS;
+
+/// Small neural network heuristic with a single ReLU hidden layer.
+/// Inputs are all 0 or 1, so they're stored as the list of active indexes:
+/// - position x card x face one-hots, for the cards the model was built for
+/// - position x (allegiance one-hot + feature bits), which also covers unknown cards
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct MlpModel {
+    pub card_ids: Vec<CardId>,
+    pub hidden_size: usize,
+    // input_size rows of hidden_size weights
+    pub hidden_weights: Vec<f32>,
+    pub hidden_biases: Vec<f32>,
+    pub output_weights: Vec<f32>,
+    pub output_bias: f32,
+}
+
+impl MlpModel {
+    pub fn new_random_for_cards<R: Rng>(cards: &[CardId], hidden_size: usize, rng: &mut R) -> Self {
+        let mut card_ids = cards.to_vec();
+        card_ids.sort();
+        card_ids.dedup();
+
+        let input_size = Self::input_size_for_num_cards(card_ids.len());
+        // Only around 2 inputs per card are active at once
+        let hidden_scale = 1.0 / ((2 * MAX_PILE_LEN) as f32).sqrt();
+        let output_scale = 1.0 / (hidden_size as f32).sqrt();
+
+        Self {
+            card_ids,
+            hidden_size,
+            hidden_weights: (0..input_size * hidden_size)
+                .map(|_| rng.gen_range(-hidden_scale..hidden_scale))
+                .collect(),
+            hidden_biases: vec![0.0; hidden_size],
+            output_weights: (0..hidden_size)
+                .map(|_| rng.gen_range(-output_scale..output_scale))
+                .collect(),
+            output_bias: 0.0,
+        }
+    }
+
+    fn input_size_for_num_cards(num_cards: usize) -> usize {
+        MAX_PILE_LEN * (num_cards * NUM_FACES + SEMANTIC_INPUTS_PER_POSITION)
+    }
+
+    pub fn input_size(&self) -> usize {
+        Self::input_size_for_num_cards(self.card_ids.len())
+    }
+
+    /// Whether the weights are the sizes card_ids and hidden_size call for, which scoring relies on
+    pub fn is_valid(&self) -> bool {
+        self.hidden_weights.len() == self.input_size() * self.hidden_size
+            && self.hidden_biases.len() == self.hidden_size
+            && self.output_weights.len() == self.hidden_size
+    }
+
+    pub fn encode_pile(&self, pile: &Pile) -> Vec<usize> {
+        let card_inputs_per_position = self.card_ids.len() * NUM_FACES;
+        let semantic_start = MAX_PILE_LEN * card_inputs_per_position;
+        let mut result = Vec::with_capacity(pile.len() * 8);
+
+        for (i, card) in pile.iter().enumerate() {
+            if let Some(card_idx) = self.card_ids.iter().position(|c| *c == card.get_card_id()) {
+                result.push(
+                    i * card_inputs_per_position
+                        + card_idx * NUM_FACES
+                        + card.get_card_face() as usize,
+                );
+            }
+
+            let position_start = semantic_start + i * SEMANTIC_INPUTS_PER_POSITION;
+            let face = card.get_active_face();
+            let allegiance_idx = match face.allegiance {
+                Allegiance::Hero => 0,
+                Allegiance::Monster => 1,
+                _ => 2,
+            };
+            result.push(position_start + allegiance_idx);
+
+            for bit in 0..NUM_FEATURE_INPUTS {
+                if face.features.bits() & (1 << bit) != 0 {
+                    result.push(position_start + NUM_ALLEGIANCE_INPUTS + bit);
+                }
+            }
+        }
+
+        result
+    }
+
+    fn hidden_pre_activations(&self, inputs: &[usize]) -> Vec<f32> {
+        let mut hidden = self.hidden_biases.clone();
+        for input in inputs {
+            let row =
+                &self.hidden_weights[input * self.hidden_size..(input + 1) * self.hidden_size];
+            for (h, w) in hidden.iter_mut().zip(row) {
+                *h += w;
+            }
+        }
+        hidden
+    }
+
+    fn score_inputs(&self, inputs: &[usize]) -> f32 {
+        let hidden = self.hidden_pre_activations(inputs);
+        self.output_bias
+            + hidden
+                .iter()
+                .zip(&self.output_weights)
+                .map(|(h, w)| h.max(0.0) * w)
+                .sum::<f32>()
+    }
+
+    pub fn score_pile(&self, pile: &Pile) -> f32 {
+        self.score_inputs(&self.encode_pile(pile))
+    }
+
+    /// Single step of gradient descent on squared error. Returns the loss before the update.
+    pub fn train_example(&mut self, pile: &Pile, target: f32, learning_rate: f32) -> f32 {
+        let inputs = self.encode_pile(pile);
+        let hidden = self.hidden_pre_activations(&inputs);
+        let prediction = self.output_bias
+            + hidden
+                .iter()
+                .zip(&self.output_weights)
+                .map(|(h, w)| h.max(0.0) * w)
+                .sum::<f32>();
+
+        let diff = prediction - target;
+        let step = learning_rate * 2.0 * diff;
+
+        for (j, pre_activation) in hidden.iter().enumerate() {
+            if *pre_activation <= 0.0 {
+                continue;
+            }
+            let hidden_step = step * self.output_weights[j];
+            self.output_weights[j] -= step * pre_activation;
+            self.hidden_biases[j] -= hidden_step;
+            for input in &inputs {
+                self.hidden_weights[input * self.hidden_size + j] -= hidden_step;
+            }
+        }
+        self.output_bias -= step;
+
+        diff * diff
+    }
+}
+
+impl ModelT for MlpModel {
+    fn score_pile(&self, pile: &Pile) -> f32 {
+        self.score_pile(pile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::string_to_pile;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn cards_for_pile(pile: &Pile) -> Vec<CardId> {
+        pile.iter().map(|c| c.get_card_id()).collect()
+    }
+
+    #[test]
+    fn test_encoding() {
+        let pile = string_to_pile("1A 2B 6C 7D");
+        let model = MlpModel::new_random_for_cards(
+            &cards_for_pile(&pile),
+            4,
+            &mut StdRng::seed_from_u64(0),
+        );
+
+        let inputs = model.encode_pile(&pile);
+        assert!(inputs.iter().all(|i| *i < model.input_size()));
+        // A card one-hot and an allegiance for each card, plus any feature bits
+        assert!(inputs.len() >= 2 * pile.len());
+
+        let mut deduped = inputs.clone();
+        deduped.sort();
+        deduped.dedup();
+        assert_eq!(deduped.len(), inputs.len());
+
+        // Cards the model doesn't know about still get semantic inputs
+        let unknown_pile = string_to_pile("3A 4A");
+        assert!(model.encode_pile(&unknown_pile).len() >= unknown_pile.len());
+    }
+
+    #[test]
+    fn test_training_fits_examples() {
+        let near = string_to_pile("1A 2A 6A 7A");
+        let far = string_to_pile("7A 6A 2A 1A");
+        let mut model = MlpModel::new_random_for_cards(
+            &cards_for_pile(&near),
+            8,
+            &mut StdRng::seed_from_u64(0),
+        );
+
+        for _ in 0..500 {
+            model.train_example(&near, 2.0, 0.01);
+            model.train_example(&far, 10.0, 0.01);
+        }
+
+        assert!((model.score_pile(&near) - 2.0).abs() < 0.5);
+        assert!((model.score_pile(&far) - 10.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_mlp_serde() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let model = MlpModel::new_random_for_cards(
+            &cards_for_pile(&pile),
+            4,
+            &mut StdRng::seed_from_u64(0),
+        );
+
+        let model_str = serde_yaml::to_string(&model).unwrap();
+        let result_model: MlpModel = serde_yaml::from_str(&model_str).unwrap();
+        assert_eq!(model, result_model);
+        assert_eq!(model.score_pile(&pile), result_model.score_pile(&pile));
+        assert!(result_model.is_valid());
+
+        let mut truncated = model.clone();
+        truncated.hidden_weights.pop();
+        assert!(!truncated.is_valid());
+        let mut wrong_hidden_size = model.clone();
+        wrong_hidden_size.hidden_size = 5;
+        assert!(!wrong_hidden_size.is_valid());
+        let mut extra_card = model;
+        extra_card.card_ids.push(3);
+        assert!(!extra_card.is_valid());
+    }
+}
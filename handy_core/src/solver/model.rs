@@ -5,9 +5,9 @@ use crate::solver::vectorize::*;
 use enum_map::enum_map;
 use enum_map::EnumMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp;
 use std::collections::{BTreeMap, HashSet};
 use vectorize_derive::*;
-use std::cmp;
 
 pub type Matchup = (Class, Class);
 
@@ -122,6 +122,95 @@ pub struct Model {
         deserialize_with = "deserialize_card_side_values"
     )]
     card_face_features: Vec<Option<CardFaceFeaturesType>>,
+    // Logistic classifier over the same features, trained on lost vs won piles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    loss_head: Option<Box<Model>>,
+}
+
+/// One of the terms that Model::score_pile sums
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum ScoreFeature {
+    FlatValue,
+    Value,
+    ValueInPosition(usize),
+    SingleBadTouchingInfront,
+    SingleBadTouchingBehind,
+    BadTouchingInfrontCoeff,
+    BadTouchingBehindCoeff,
+    SingleGoodTouchingInfront,
+    SingleGoodTouchingBehind,
+    GoodTouchingInfrontCoeff,
+    GoodTouchingBehindCoeff,
+    IsTouchingStartThroughAllies,
+    IsStartNumConsecutiveAllies,
+    IsStartNumConsecutiveEnemies,
+    NumEnergy,
+    IsStartNumEnergy,
+}
+
+// Same names as the FaceFeatures fields, so they can be found in model files
+impl std::fmt::Display for ScoreFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ScoreFeature::FlatValue => "flat_value",
+            ScoreFeature::Value => "value",
+            ScoreFeature::ValueInPosition(i) => return write!(f, "value_in_position[{i}]"),
+            ScoreFeature::SingleBadTouchingInfront => "single_bad_touching_infront",
+            ScoreFeature::SingleBadTouchingBehind => "single_bad_touching_behind",
+            ScoreFeature::BadTouchingInfrontCoeff => "bad_touching_infront_coeff",
+            ScoreFeature::BadTouchingBehindCoeff => "bad_touching_behind_coeff",
+            ScoreFeature::SingleGoodTouchingInfront => "single_good_touching_infront",
+            ScoreFeature::SingleGoodTouchingBehind => "single_good_touching_behind",
+            ScoreFeature::GoodTouchingInfrontCoeff => "good_touching_infront_coeff",
+            ScoreFeature::GoodTouchingBehindCoeff => "good_touching_behind_coeff",
+            ScoreFeature::IsTouchingStartThroughAllies => "is_touching_start_through_allies",
+            ScoreFeature::IsStartNumConsecutiveAllies => "is_start_num_consecutive_allies",
+            ScoreFeature::IsStartNumConsecutiveEnemies => "is_start_num_consecutive_enemies",
+            ScoreFeature::NumEnergy => "num_energy",
+            ScoreFeature::IsStartNumEnergy => "is_start_num_energy",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ScoreContribution {
+    // Index into the pile of the card the term comes from, None for the flat value
+    pub position: Option<usize>,
+    pub feature: ScoreFeature,
+    pub contribution: f32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ScoreExplanation {
+    pub total: f32,
+    pub contributions: Vec<ScoreContribution>,
+}
+
+impl ScoreExplanation {
+    /// The n largest contributions by magnitude, skipping ones that are exactly zero
+    pub fn top_contributions(&self, n: usize) -> Vec<ScoreContribution> {
+        let mut result: Vec<ScoreContribution> = self
+            .contributions
+            .iter()
+            .filter(|c| c.contribution != 0.0)
+            .cloned()
+            .collect();
+        result.sort_by(|a, b| b.contribution.abs().total_cmp(&a.contribution.abs()));
+        result.truncate(n);
+        result
+    }
+
+    /// Sum of the contributions from each card, by position in the pile
+    pub fn card_totals(&self, pile_len: usize) -> Vec<f32> {
+        let mut result = vec![0.0; pile_len];
+        for c in &self.contributions {
+            if let Some(position) = c.position {
+                result[position] += c.contribution;
+            }
+        }
+        result
+    }
 }
 
 impl Model {
@@ -129,6 +218,7 @@ impl Model {
         Model {
             card_face_features: Vec::new(),
             flat_value: 0.0,
+            loss_head: None,
         }
     }
 
@@ -144,6 +234,11 @@ impl Model {
         res
     }
 
+    pub fn new_empty_for_pile(pile: &Pile) -> Self {
+        let cards: Vec<CardId> = pile.iter().map(|c| c.get_card_id()).collect();
+        Self::new_empty_for_cards(&cards)
+    }
+
     pub fn set(&mut self, i: usize, value: EnumMap<FaceKey, FaceFeatures>) {
         while self.card_face_features.len() <= i {
             self.card_face_features.push(None);
@@ -159,12 +254,41 @@ impl Model {
         }
     }
 
+    pub fn card_ids(&self) -> Vec<CardId> {
+        self.card_face_features
+            .iter()
+            .enumerate()
+            .filter(|(_, features)| features.is_some())
+            .map(|(i, _)| i as CardId)
+            .collect()
+    }
+
+    pub fn loss_head(&self) -> Option<&Model> {
+        self.loss_head.as_deref()
+    }
+
+    pub fn set_loss_head(&mut self, loss_head: Option<Model>) {
+        self.loss_head = loss_head.map(Box::new);
+    }
+
+    pub fn loss_probability(&self, pile: &Pile) -> f32 {
+        self.loss_head
+            .as_ref()
+            .map_or(0.0, |loss_head| sigmoid(loss_head.score_pile(pile)))
+    }
+
     pub fn trim_to_cards(&mut self, _: &Vec<CardId>) {
         // self.card_face_features.retain(|k, _| cards.contains(k));
     }
 
-    pub fn score_pile(&self, pile: &Pile) -> f32 {
-        let mut total = self.flat_value;
+    // Calls visit with every term that score_pile sums, in the same order
+    #[inline]
+    fn visit_score_terms(
+        &self,
+        pile: &Pile,
+        mut visit: impl FnMut(Option<usize>, ScoreFeature, f32),
+    ) {
+        visit(None, ScoreFeature::FlatValue, self.flat_value);
 
         let mut total_energy = 0;
         {
@@ -188,16 +312,37 @@ impl Model {
                     .as_ref()
                     .unwrap()[card.get_card_face()];
 
-                total += feature_face.value;
-                total += feature_face.value_in_position[std::cmp::min(i, 8)];
+                visit(Some(i), ScoreFeature::Value, feature_face.value);
+                let position_idx = std::cmp::min(i, 8);
+                visit(
+                    Some(i),
+                    ScoreFeature::ValueInPosition(position_idx),
+                    feature_face.value_in_position[position_idx],
+                );
 
                 if num_baddie_infront > 0 {
-                    total += feature_face.single_bad_touching_infront;
-                    total += num_baddie_infront as f32 * feature_face.bad_touching_infront_coeff;
+                    visit(
+                        Some(i),
+                        ScoreFeature::SingleBadTouchingInfront,
+                        feature_face.single_bad_touching_infront,
+                    );
+                    visit(
+                        Some(i),
+                        ScoreFeature::BadTouchingInfrontCoeff,
+                        num_baddie_infront as f32 * feature_face.bad_touching_infront_coeff,
+                    );
                 }
                 if num_good_infront > 0 {
-                    total += feature_face.single_good_touching_infront;
-                    total += num_good_infront as f32 * feature_face.good_touching_infront_coeff;
+                    visit(
+                        Some(i),
+                        ScoreFeature::SingleGoodTouchingInfront,
+                        feature_face.single_good_touching_infront,
+                    );
+                    visit(
+                        Some(i),
+                        ScoreFeature::GoodTouchingInfrontCoeff,
+                        num_good_infront as f32 * feature_face.good_touching_infront_coeff,
+                    );
                 }
 
                 if def_face.allegiance == Allegiance::Hero {
@@ -209,10 +354,18 @@ impl Model {
                 }
 
                 if i == 0 {
-                    total += feature_face.is_touching_start_through_allies;
+                    visit(
+                        Some(i),
+                        ScoreFeature::IsTouchingStartThroughAllies,
+                        feature_face.is_touching_start_through_allies,
+                    );
                     touching_start = Some(def_face.allegiance);
                 } else if Some(def_face.allegiance) == touching_start {
-                    total += feature_face.is_touching_start_through_allies;
+                    visit(
+                        Some(i),
+                        ScoreFeature::IsTouchingStartThroughAllies,
+                        feature_face.is_touching_start_through_allies,
+                    );
                 } else {
                     touching_start = None;
                 }
@@ -244,12 +397,28 @@ impl Model {
                     .unwrap()[card.get_card_face()];
 
                 if num_baddie_behind > 0 {
-                    total += feature_face.single_bad_touching_behind;
-                    total += num_baddie_behind as f32 * feature_face.bad_touching_behind_coeff;
+                    visit(
+                        Some(i),
+                        ScoreFeature::SingleBadTouchingBehind,
+                        feature_face.single_bad_touching_behind,
+                    );
+                    visit(
+                        Some(i),
+                        ScoreFeature::BadTouchingBehindCoeff,
+                        num_baddie_behind as f32 * feature_face.bad_touching_behind_coeff,
+                    );
                 }
                 if num_good_behind > 0 {
-                    total += feature_face.single_good_touching_behind;
-                    total += num_good_behind as f32 * feature_face.good_touching_behind_coeff;
+                    visit(
+                        Some(i),
+                        ScoreFeature::SingleGoodTouchingBehind,
+                        feature_face.single_good_touching_behind,
+                    );
+                    visit(
+                        Some(i),
+                        ScoreFeature::GoodTouchingBehindCoeff,
+                        num_good_behind as f32 * feature_face.good_touching_behind_coeff,
+                    );
                 }
 
                 if def_face.allegiance == Allegiance::Hero {
@@ -260,13 +429,27 @@ impl Model {
                     num_good_behind = 0;
                 }
 
-                total += total_energy as f32 * feature_face.num_energy;
+                visit(
+                    Some(i),
+                    ScoreFeature::NumEnergy,
+                    total_energy as f32 * feature_face.num_energy,
+                );
                 if i == 0 {
-                    total += total_energy as f32 * feature_face.is_start_num_energy;
-                    total +=
-                        num_ally_of_start as f32 * feature_face.is_start_num_consecutive_allies;
-                    total +=
-                        num_enemy_of_start as f32 * feature_face.is_start_num_consecutive_enemies;
+                    visit(
+                        Some(i),
+                        ScoreFeature::IsStartNumEnergy,
+                        total_energy as f32 * feature_face.is_start_num_energy,
+                    );
+                    visit(
+                        Some(i),
+                        ScoreFeature::IsStartNumConsecutiveAllies,
+                        num_ally_of_start as f32 * feature_face.is_start_num_consecutive_allies,
+                    );
+                    visit(
+                        Some(i),
+                        ScoreFeature::IsStartNumConsecutiveEnemies,
+                        num_enemy_of_start as f32 * feature_face.is_start_num_consecutive_enemies,
+                    );
                 } else {
                     if def_face.allegiance == start_type {
                         num_ally_of_start += 1;
@@ -278,9 +461,32 @@ impl Model {
                 }
             }
         }
+    }
 
+    pub fn score_pile(&self, pile: &Pile) -> f32 {
+        let mut total = 0.0;
+        self.visit_score_terms(pile, |_, _, contribution| total += contribution);
         total
     }
+
+    /// Every term of score_pile, with the pile position of the card it comes from
+    pub fn explain_score(&self, pile: &Pile) -> ScoreExplanation {
+        let mut total = 0.0;
+        let mut contributions = Vec::new();
+        self.visit_score_terms(pile, |position, feature, contribution| {
+            total += contribution;
+            contributions.push(ScoreContribution {
+                position,
+                feature,
+                contribution,
+            });
+        });
+
+        ScoreExplanation {
+            total,
+            contributions,
+        }
+    }
 }
 
 pub fn training_ex_to_model(pile: &Pile) -> Model {
@@ -396,6 +602,15 @@ impl ModelT for Model {
     fn score_pile(&self, pile: &Pile) -> f32 {
         self.score_pile(pile)
     }
+
+    fn loss_probability(&self, pile: &Pile) -> f32 {
+        self.loss_probability(pile)
+    }
+
+    // An empty model scores every pile 0, which can't overestimate
+    fn is_admissible(&self) -> bool {
+        self.vectorize().iter().all(|weight| *weight == 0.0)
+    }
 }
 
 pub fn vec_to_model(vec: &Vec<f32>, cards: &[CardId]) -> Model {
@@ -552,9 +767,35 @@ pub fn merge_models_for_pile(pile: &Pile, models: &Vec<Model>) -> Model {
 
     result.flat_value = flat_value_sum / flat_value_count as f32;
 
+    // Heads are merged from the models that have one, since a missing head predicts nothing
+    let loss_heads: Vec<Model> = models
+        .iter()
+        .filter_map(|model| model.loss_head().cloned())
+        .collect();
+    if !loss_heads.is_empty() {
+        result.set_loss_head(Some(merge_models_for_pile(pile, &loss_heads)));
+    }
+
+    result
+}
+
+/// The model, with features for the cards it doesn't have copied from the fallback
+pub fn fill_missing_cards(model: &Model, fallback: &Model) -> Model {
+    let mut result = model.clone();
+    for card_id in fallback.card_ids() {
+        if result.try_get_face_features(card_id as usize).is_none() {
+            if let Some(features) = fallback.try_get_face_features(card_id as usize) {
+                result.set(card_id as usize, features);
+            }
+        }
+    }
     result
 }
 
+pub fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
 pub fn dot_product(a: &[f32], b: &[f32]) -> f32 {
     assert_eq!(a.len(), b.len());
     let mut product = 0.0;
@@ -632,6 +873,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_explain_score() {
+        let mut rng = thread_rng();
+        let pile = get_start_from_classes(Class::Warrior, Class::Ogre, &mut rng);
+        let cards: Vec<_> = pile.iter().map(|c| c.get_card_id()).collect();
+        let model = vec_to_model(&get_random_float_vec(), &cards);
+
+        let explanation = model.explain_score(&pile);
+        assert_eq!(explanation.total, model.score_pile(&pile));
+
+        let card_sum: f32 = explanation.card_totals(pile.len()).iter().sum();
+        assert!((card_sum + model.flat_value - explanation.total).abs() < 0.0001);
+
+        let top = explanation.top_contributions(3);
+        assert_eq!(top.len(), 3);
+        assert!(top[0].contribution.abs() >= top[2].contribution.abs());
+    }
+
     #[test]
     fn test_model_serde() {
         let cards = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
@@ -649,4 +908,54 @@ mod tests {
             assert_eq!(reg_model, result_model);
         }
     }
+
+    #[test]
+    fn test_loss_head() {
+        let cards = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let pile = string_to_pile("2A, 6A, 5A, 4A, 8A, 7A, 3A, 9A, 1A");
+        let mut model = vec_to_model(&get_random_float_vec(), &cards);
+        assert_eq!(model.loss_probability(&pile), 0.0);
+
+        let mut head_vec = vec![0.0; MODEL_SIZE_FOR_9_CARDS];
+        head_vec[0] = 5.0;
+        model.set_loss_head(Some(vec_to_model(&head_vec, &cards)));
+        assert!((model.loss_probability(&pile) - sigmoid(5.0)).abs() < 0.0001);
+
+        let model_str = serde_json::to_string(&model).unwrap();
+        let result_model: Model = serde_json::from_str(&model_str).unwrap();
+        assert_eq!(model, result_model);
+
+        model.set_loss_head(None);
+        let model_str = serde_json::to_string(&model).unwrap();
+        assert!(!model_str.contains("loss_head"));
+
+        // Saved before loss heads existed
+        let old_model: Model =
+            serde_yaml::from_str(include_str!("../../../data/models/Warrior.Ogre.yaml")).unwrap();
+        assert!(old_model.loss_head().is_none());
+        assert_eq!(old_model.loss_probability(&pile), 0.0);
+    }
+
+    #[test]
+    fn test_merge_loss_heads() {
+        let cards = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let pile = string_to_pile("2A, 6A, 5A, 4A, 8A, 7A, 3A, 9A, 1A");
+        let with_head = |flat_value: f32| {
+            let mut head_vec = vec![0.0; MODEL_SIZE_FOR_9_CARDS];
+            head_vec[0] = flat_value;
+            let mut model = vec_to_model(&get_random_float_vec(), &cards);
+            model.set_loss_head(Some(vec_to_model(&head_vec, &cards)));
+            model
+        };
+        let without_head = vec_to_model(&get_random_float_vec(), &cards);
+
+        let merged = merge_models_for_pile(&pile, &vec![with_head(2.0), with_head(4.0)]);
+        assert!((merged.loss_probability(&pile) - sigmoid(3.0)).abs() < 0.0001);
+
+        let merged = merge_models_for_pile(&pile, &vec![with_head(2.0), without_head.clone()]);
+        assert!((merged.loss_probability(&pile) - sigmoid(2.0)).abs() < 0.0001);
+
+        let merged = merge_models_for_pile(&pile, &vec![without_head]);
+        assert!(merged.loss_head().is_none());
+    }
 }
@@ -0,0 +1,263 @@
+use crate::solver::model::{FaceFeatures, Model};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+
+// 0 is the bare Model, from before models carried metadata
+pub const MODEL_FORMAT_VERSION: u32 = 1;
+
+/// Names of the per face features this build scores with, with the length of array features
+pub fn model_feature_schema() -> Vec<String> {
+    let Ok(Value::Mapping(fields)) = serde_yaml::to_value(FaceFeatures::default()) else {
+        panic!("FaceFeatures should serialize to a mapping");
+    };
+    let mut result: Vec<String> = fields.iter().map(|(k, v)| feature_name(k, v)).collect();
+    result.sort();
+    result
+}
+
+fn feature_name(key: &Value, value: &Value) -> String {
+    let name = key.as_str().unwrap_or_default();
+    match value {
+        Value::Sequence(values) => format!("{name}[{}]", values.len()),
+        _ => name.to_owned(),
+    }
+}
+
+// FNV-1a, so the hash is the same across builds and platforms
+pub fn feature_schema_hash(schema: &[String]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in schema.join(",").bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct TrainingDataStats {
+    pub num_train_examples: usize,
+    pub num_validation_examples: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_loss_head_examples: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ModelMetadata {
+    pub format_version: u32,
+    pub feature_schema_hash: String,
+    pub feature_schema: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matchup: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub training_data: Option<TrainingDataStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_loss: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+}
+
+impl Default for ModelMetadata {
+    fn default() -> Self {
+        let feature_schema = model_feature_schema();
+        Self {
+            format_version: MODEL_FORMAT_VERSION,
+            feature_schema_hash: feature_schema_hash(&feature_schema),
+            feature_schema,
+            matchup: None,
+            training_data: None,
+            validation_loss: None,
+            created_at: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ModelEnvelope {
+    pub metadata: ModelMetadata,
+    pub model: Model,
+}
+
+impl ModelEnvelope {
+    pub fn new(model: Model, metadata: ModelMetadata) -> Self {
+        Self { metadata, model }
+    }
+
+    pub fn to_yaml_string(&self) -> Result<String, String> {
+        serde_yaml::to_string(self).map_err(|err| format!("{err}"))
+    }
+}
+
+/// A model brought up to the current format, along with what was changed to get there
+#[derive(PartialEq, Debug, Clone)]
+pub struct LoadedModel {
+    pub envelope: ModelEnvelope,
+    // The format version and schema hash as they were in the file
+    pub file_format_version: u32,
+    pub file_schema_hash: String,
+    pub migrations: Vec<String>,
+}
+
+impl LoadedModel {
+    pub fn is_migrated(&self) -> bool {
+        !self.migrations.is_empty()
+    }
+}
+
+// Bare models don't say which features they were trained with, so look at what they contain
+fn infer_feature_schema(model: &Value) -> Vec<String> {
+    let mut names: BTreeSet<String> = BTreeSet::new();
+    let mut models = vec![model];
+    while let Some(model) = models.pop() {
+        if let Some(Value::Mapping(cards)) = model.get("card_face_features") {
+            for faces in cards.values().filter_map(|faces| faces.as_sequence()) {
+                for face in faces.iter().filter_map(|face| face.as_mapping()) {
+                    names.extend(face.iter().map(|(k, v)| feature_name(k, v)));
+                }
+            }
+        }
+        if let Some(loss_head) = model.get("loss_head") {
+            models.push(loss_head);
+        }
+    }
+    names.into_iter().collect()
+}
+
+/// Reads a model file in the current format or any older one. Rejects files from newer builds,
+/// and files with features this build doesn't have, since their weights would be dropped.
+/// Features the file doesn't have yet are zero filled, and listed in the migrations.
+pub fn parse_model_yaml(contents: &str) -> Result<LoadedModel, String> {
+    let value: Value = serde_yaml::from_str(contents).map_err(|err| format!("{err}"))?;
+
+    let (metadata, model_value) = match (value.get("metadata"), value.get("model")) {
+        (Some(metadata), Some(model)) => {
+            let metadata: ModelMetadata =
+                serde_yaml::from_value(metadata.clone()).map_err(|err| format!("{err}"))?;
+            (metadata, model.clone())
+        }
+        _ => {
+            let feature_schema = infer_feature_schema(&value);
+            let metadata = ModelMetadata {
+                format_version: 0,
+                feature_schema_hash: feature_schema_hash(&feature_schema),
+                feature_schema,
+                ..ModelMetadata::default()
+            };
+            (metadata, value)
+        }
+    };
+
+    if metadata.format_version > MODEL_FORMAT_VERSION {
+        return Err(format!(
+            "Model format version {} is newer than this build supports ({MODEL_FORMAT_VERSION})",
+            metadata.format_version
+        ));
+    }
+
+    let current_schema = model_feature_schema();
+    let mut migrations = Vec::new();
+    if metadata.format_version < MODEL_FORMAT_VERSION {
+        migrations.push(format!(
+            "Format version {} -> {MODEL_FORMAT_VERSION}",
+            metadata.format_version
+        ));
+    }
+    if metadata.feature_schema_hash != feature_schema_hash(&current_schema) {
+        let unknown: Vec<&String> = metadata
+            .feature_schema
+            .iter()
+            .filter(|name| !current_schema.contains(name))
+            .collect();
+        if !unknown.is_empty() {
+            return Err(format!(
+                "Model has features this build doesn't: {unknown:?}"
+            ));
+        }
+        let missing: Vec<&String> = current_schema
+            .iter()
+            .filter(|name| !metadata.feature_schema.contains(name))
+            .collect();
+        if !missing.is_empty() {
+            migrations.push(format!("Zero filled new features: {missing:?}"));
+        }
+    }
+
+    let model: Model = serde_yaml::from_value(model_value).map_err(|err| format!("{err}"))?;
+    Ok(LoadedModel {
+        file_format_version: metadata.format_version,
+        file_schema_hash: metadata.feature_schema_hash.clone(),
+        envelope: ModelEnvelope::new(
+            model,
+            ModelMetadata {
+                format_version: MODEL_FORMAT_VERSION,
+                feature_schema_hash: feature_schema_hash(&current_schema),
+                feature_schema: current_schema,
+                ..metadata
+            },
+        ),
+        migrations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::CardId;
+
+    fn model_for_cards() -> Model {
+        let cards: Vec<CardId> = vec![1, 2, 6, 7];
+        Model::new_empty_for_cards(&cards)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let metadata = ModelMetadata {
+            matchup: Some("Warrior.Ogre".to_owned()),
+            validation_loss: Some(2.5),
+            ..ModelMetadata::default()
+        };
+        let envelope = ModelEnvelope::new(model_for_cards(), metadata);
+        let loaded = parse_model_yaml(&envelope.to_yaml_string().unwrap()).unwrap();
+
+        assert_eq!(loaded.envelope, envelope);
+        assert!(!loaded.is_migrated());
+        assert!(model_feature_schema().contains(&"value_in_position[9]".to_owned()));
+    }
+
+    #[test]
+    fn test_migrates_bare_models() {
+        let model = model_for_cards();
+        let loaded = parse_model_yaml(&serde_yaml::to_string(&model).unwrap()).unwrap();
+        assert_eq!(loaded.envelope.model, model);
+        assert_eq!(loaded.file_format_version, 0);
+        assert_eq!(loaded.migrations.len(), 1);
+
+        // Bare models from before a feature existed get it zero filled
+        let old_model = serde_yaml::to_string(&model)
+            .unwrap()
+            .lines()
+            .filter(|line| !line.contains("num_energy"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let loaded = parse_model_yaml(&old_model).unwrap();
+        assert_eq!(loaded.envelope.model, model);
+        assert_eq!(loaded.migrations.len(), 2);
+        assert!(loaded.migrations[1].contains("num_energy"));
+    }
+
+    #[test]
+    fn test_rejects_incompatible() {
+        let mut envelope = ModelEnvelope::new(model_for_cards(), ModelMetadata::default());
+        envelope.metadata.format_version = MODEL_FORMAT_VERSION + 1;
+        assert!(parse_model_yaml(&envelope.to_yaml_string().unwrap()).is_err());
+
+        let mut envelope = ModelEnvelope::new(model_for_cards(), ModelMetadata::default());
+        envelope
+            .metadata
+            .feature_schema
+            .push("removed_feature".to_owned());
+        envelope.metadata.feature_schema_hash =
+            feature_schema_hash(&envelope.metadata.feature_schema);
+        assert!(parse_model_yaml(&envelope.to_yaml_string().unwrap()).is_err());
+    }
+}
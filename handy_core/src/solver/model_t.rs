@@ -2,4 +2,15 @@ use crate::game::Pile;
 
 pub trait ModelT {
     fn score_pile(&self, pile: &Pile) -> f32;
+
+    /// Estimated probability that the pile can't be won. Models without a loss head return 0.
+    fn loss_probability(&self, _pile: &Pile) -> f32 {
+        0.0
+    }
+
+    /// Whether score_pile never overestimates the turns left to win. Only then does a search
+    /// that runs to the end prove that its win is the shortest, or that there is no win.
+    fn is_admissible(&self) -> bool {
+        false
+    }
 }
@@ -0,0 +1,440 @@
+use crate::game::end_game::{is_game_winner, GameEndCheckType};
+use crate::game::*;
+use crate::solver::a_star::*;
+use crate::solver::model_t::ModelT;
+use crate::solver::tiny_pile::NoopPileStorageConverter;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+
+pub const DEFAULT_ANALYSIS_MAX_ITERS: usize = 100_000;
+
+// Grades are based on how many extra turns a move costs compared to the best move
+const OK_MAX_EXTRA_TURNS: DepthType = 1;
+const MISTAKE_MAX_EXTRA_TURNS: DepthType = 3;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PileEval {
+    // Number of turns until the game is won
+    Win(DepthType),
+    // A win was found in this many turns, but the search couldn't rule out a faster one
+    WinWithin(DepthType),
+    // No win exists, proven by a search that explored every reachable state
+    Loss,
+    // Ran out of budget before finding a win
+    Unknown,
+}
+
+impl PileEval {
+    fn rank(&self) -> (u8, DepthType, u8) {
+        match self {
+            PileEval::Win(depth) => (0, *depth, 0),
+            PileEval::WinWithin(depth) => (0, *depth, 1),
+            PileEval::Unknown => (1, 0, 0),
+            PileEval::Loss => (2, 0, 0),
+        }
+    }
+
+    pub fn add_turn(&self) -> Self {
+        match self {
+            PileEval::Win(depth) => PileEval::Win(depth.saturating_add(1)),
+            PileEval::WinWithin(depth) => PileEval::WinWithin(depth.saturating_add(1)),
+            other => *other,
+        }
+    }
+
+    /// The number of turns of the best win found, exact or not
+    pub fn win_depth(&self) -> Option<DepthType> {
+        match self {
+            PileEval::Win(depth) | PileEval::WinWithin(depth) => Some(*depth),
+            PileEval::Loss | PileEval::Unknown => None,
+        }
+    }
+}
+
+impl Ord for PileEval {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+impl PartialOrd for PileEval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for PileEval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PileEval::Win(depth) => write!(f, "Win in {}", depth),
+            PileEval::WinWithin(depth) => write!(f, "Win in at most {}", depth),
+            PileEval::Loss => write!(f, "Loss"),
+            PileEval::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+#[derive(strum_macros::Display, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MoveGrade {
+    Best,
+    Ok,
+    Mistake,
+    Blunder,
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveAnalysis {
+    pub pile: Pile,
+    pub events: Vec<Event>,
+    // Distance to win from the pile before the move, when taking this move
+    pub eval: PileEval,
+    pub grade: MoveGrade,
+    // The grade compares wins that might be faster than found, so it's only an estimate
+    pub is_grade_bounded: bool,
+}
+
+/// Runs A* from a single pile until it either runs out of states or iterations.
+/// Depth is the number of activations needed to win starting from this pile. Wins are only
+/// exact if the search ran to the end without dropping any states, with an admissible model.
+/// Learned models overestimate, so with them wins are bounds. Losses only need the search to
+/// run out of states, since the model orders the search but doesn't prune it.
+pub fn evaluate_pile<M: ModelT + Clone + 'static>(
+    pile: &Pile,
+    model: &M,
+    game_end_check_type: GameEndCheckType,
+    max_iters: usize,
+) -> PileEval {
+    evaluate_pile_with_path(pile, model, game_end_check_type, max_iters).0
+}
+
+/// Same as evaluate_pile, but also returns the best path found, starting with the given pile.
+/// The path is empty if no win was found.
+pub fn evaluate_pile_with_path<M: ModelT + Clone + 'static>(
+    pile: &Pile,
+    model: &M,
+    game_end_check_type: GameEndCheckType,
+    max_iters: usize,
+) -> (PileEval, Vec<Pile>) {
+    match is_game_winner(pile, game_end_check_type) {
+        WinType::Win => return (PileEval::Win(0), vec![pile.clone()]),
+        WinType::Lose => return (PileEval::Loss, vec![]),
+        WinType::Unresolved => (),
+    }
+
+    let mut a_star_solver = AStarSolver::<Pile, NoopPileStorageConverter>::new(
+        &[pile.clone()],
+        Box::new(model.clone()),
+    );
+    a_star_solver.set_game_end_check_type(game_end_check_type);
+    a_star_solver.set_max_iters(max_iters);
+
+    loop {
+        match a_star_solver.single_iter() {
+            AStarIterResult::Done(reason) => {
+                let is_search_complete = a_star_solver.is_model_admissible
+                    && !a_star_solver.is_optimality_lost
+                    && matches!(
+                        reason,
+                        AStarDoneReason::EmptyQueue | AStarDoneReason::FScoreCutoff
+                    );
+                if let Some(best_win) = a_star_solver.best_win.clone() {
+                    let path = a_star_solver.unroll_state(best_win);
+                    let depth = a_star_solver.max_depth;
+                    let eval = if is_search_complete {
+                        PileEval::Win(depth)
+                    } else {
+                        PileEval::WinWithin(depth)
+                    };
+                    return (eval, path);
+                }
+                let eval = match reason {
+                    AStarDoneReason::MaxIters | AStarDoneReason::TimeLimit => PileEval::Unknown,
+                    // Dropped states might have led to a win
+                    _ if a_star_solver.is_optimality_lost => PileEval::Unknown,
+                    // Every reachable state was explored
+                    AStarDoneReason::EmptyQueue => PileEval::Loss,
+                    // Overestimated states past the cutoff might have led to a win
+                    AStarDoneReason::FScoreCutoff if !a_star_solver.is_model_admissible => {
+                        PileEval::Unknown
+                    }
+                    AStarDoneReason::FScoreCutoff => PileEval::Loss,
+                };
+                return (eval, vec![]);
+            }
+            AStarIterResult::NewBest(_) | AStarIterResult::Continue(_) => (),
+        }
+    }
+}
+
+fn grade_extra_turns(extra_turns: DepthType) -> MoveGrade {
+    if extra_turns == 0 {
+        MoveGrade::Best
+    } else if extra_turns <= OK_MAX_EXTRA_TURNS {
+        MoveGrade::Ok
+    } else if extra_turns <= MISTAKE_MAX_EXTRA_TURNS {
+        MoveGrade::Mistake
+    } else {
+        MoveGrade::Blunder
+    }
+}
+
+/// Grades a move by how many more turns its win takes than the best win. Wins that are only
+/// bounds are compared by the depths found, see is_grade_bounded.
+pub fn grade_eval(eval: PileEval, best_eval: PileEval) -> MoveGrade {
+    match (eval.win_depth(), best_eval.win_depth()) {
+        (Some(depth), Some(best_depth)) => grade_extra_turns(depth.saturating_sub(best_depth)),
+        // Nothing found a win, so there's nothing to compare against
+        (Some(_), None) => MoveGrade::Best,
+        (None, _) => match (eval, best_eval) {
+            (PileEval::Loss, PileEval::Loss) => MoveGrade::Best,
+            (PileEval::Loss, _) => MoveGrade::Blunder,
+            _ => MoveGrade::Unknown,
+        },
+    }
+}
+
+/// Whether grade_eval compared two wins where at least one might be faster than found
+pub fn is_grade_bounded(eval: PileEval, best_eval: PileEval) -> bool {
+    eval.win_depth().is_some()
+        && best_eval.win_depth().is_some()
+        && (matches!(eval, PileEval::WinWithin(_)) || matches!(best_eval, PileEval::WinWithin(_)))
+}
+
+pub fn format_grade(grade: MoveGrade, is_bounded: bool) -> String {
+    if is_bounded {
+        format!("{} (bounded)", grade)
+    } else {
+        grade.to_string()
+    }
+}
+
+/// Evaluates every distinct outcome of activating the top card of the pile.
+/// Results are sorted from best to worst.
+pub fn analyze_moves<M: ModelT + Clone + 'static>(
+    pile: &Pile,
+    model: &M,
+    game_end_check_type: GameEndCheckType,
+    max_iters_per_move: usize,
+) -> Vec<MoveAnalysis> {
+    let init_state = GameStateWithEventLog::new(pile.clone());
+    let mut seen_piles: HashSet<Pile> = HashSet::new();
+    let mut results: Vec<MoveAnalysis> = Vec::new();
+
+    for state in resolve_top_card(&init_state) {
+        if !seen_piles.insert(state.pile.clone()) {
+            continue;
+        }
+
+        let eval = evaluate_pile(&state.pile, model, game_end_check_type, max_iters_per_move);
+        results.push(MoveAnalysis {
+            pile: state.pile,
+            events: state.events,
+            eval: eval.add_turn(),
+            grade: MoveGrade::Unknown,
+            is_grade_bounded: false,
+        });
+    }
+
+    let Some(best_eval) = results.iter().map(|r| r.eval).min() else {
+        return results;
+    };
+
+    for result in results.iter_mut() {
+        result.grade = grade_eval(result.eval, best_eval);
+        result.is_grade_bounded = is_grade_bounded(result.eval, best_eval);
+    }
+    results.sort_by_key(|r| r.eval);
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::model::Model;
+    use crate::utils::string_to_pile;
+
+    #[test]
+    fn test_pile_eval_order() {
+        assert!(PileEval::Win(1) < PileEval::Win(2));
+        assert!(PileEval::Win(2) < PileEval::WinWithin(2));
+        assert!(PileEval::WinWithin(2) < PileEval::Win(3));
+        assert!(PileEval::Win(20) < PileEval::Unknown);
+        assert!(PileEval::Unknown < PileEval::Loss);
+    }
+
+    #[test]
+    fn test_grade_eval() {
+        assert_eq!(
+            grade_eval(PileEval::Win(3), PileEval::Win(3)),
+            MoveGrade::Best
+        );
+        assert_eq!(
+            grade_eval(PileEval::Win(4), PileEval::Win(3)),
+            MoveGrade::Ok
+        );
+        assert_eq!(
+            grade_eval(PileEval::Win(6), PileEval::Win(3)),
+            MoveGrade::Mistake
+        );
+        assert_eq!(
+            grade_eval(PileEval::Win(7), PileEval::Win(3)),
+            MoveGrade::Blunder
+        );
+        assert_eq!(
+            grade_eval(PileEval::Loss, PileEval::Win(3)),
+            MoveGrade::Blunder
+        );
+        assert_eq!(grade_eval(PileEval::Loss, PileEval::Loss), MoveGrade::Best);
+        assert_eq!(
+            grade_eval(PileEval::Unknown, PileEval::Win(3)),
+            MoveGrade::Unknown
+        );
+        assert!(!is_grade_bounded(PileEval::Win(4), PileEval::Win(3)));
+        assert!(!is_grade_bounded(PileEval::Loss, PileEval::WinWithin(3)));
+    }
+
+    #[test]
+    fn test_grade_eval_with_bounds() {
+        let cases = [
+            (PileEval::WinWithin(3), PileEval::Win(3), MoveGrade::Best),
+            (PileEval::WinWithin(4), PileEval::Win(3), MoveGrade::Ok),
+            (PileEval::Win(7), PileEval::WinWithin(3), MoveGrade::Blunder),
+            (PileEval::Win(4), PileEval::WinWithin(3), MoveGrade::Ok),
+            (
+                PileEval::WinWithin(3),
+                PileEval::WinWithin(3),
+                MoveGrade::Best,
+            ),
+            (
+                PileEval::WinWithin(6),
+                PileEval::WinWithin(3),
+                MoveGrade::Mistake,
+            ),
+        ];
+        for (eval, best_eval, grade) in cases {
+            assert_eq!(grade_eval(eval, best_eval), grade);
+            assert!(is_grade_bounded(eval, best_eval));
+        }
+        assert_eq!(format_grade(MoveGrade::Ok, true), "Ok (bounded)");
+        assert_eq!(format_grade(MoveGrade::Ok, false), "Ok");
+    }
+
+    #[test]
+    fn test_evaluate_out_of_iters_is_a_bound() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let model = Model::new_empty_for_pile(&pile);
+
+        assert_eq!(
+            evaluate_pile(&pile, &model, GameEndCheckType::Standard, 100_000),
+            PileEval::Win(10)
+        );
+        // Enough to find the win, but not to finish the search
+        let (eval, path) = evaluate_pile_with_path(&pile, &model, GameEndCheckType::Standard, 1300);
+        assert_eq!(eval, PileEval::WinWithin(10));
+        assert_eq!(path.len(), 11);
+    }
+
+    // Scores this many turns per card
+    #[derive(Clone)]
+    struct OverestimatingModel(f32);
+
+    impl ModelT for OverestimatingModel {
+        fn score_pile(&self, pile: &Pile) -> f32 {
+            self.0 * pile.len() as f32
+        }
+    }
+
+    #[test]
+    fn test_evaluate_with_inadmissible_model_is_a_bound() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let model = OverestimatingModel(3.0);
+        let eval = evaluate_pile(&pile, &model, GameEndCheckType::Standard, 100_000);
+        assert!(matches!(eval, PileEval::WinWithin(depth) if depth >= 10));
+
+        // Every state scores past the f-score cutoff, so the search ends without a win
+        let model = OverestimatingModel(100.0);
+        let eval = evaluate_pile(&pile, &model, GameEndCheckType::Standard, 100_000);
+        assert_eq!(eval, PileEval::Unknown);
+
+        let lost = string_to_pile("7A 6A 2A 1C");
+        let eval = evaluate_pile(&lost, &model, GameEndCheckType::Standard, 100_000);
+        assert_eq!(eval, PileEval::Unknown);
+
+        // Nothing is dropped, so running out of states proves the loss
+        let model = OverestimatingModel(3.0);
+        let eval = evaluate_pile(&lost, &model, GameEndCheckType::Standard, 100_000);
+        assert_eq!(eval, PileEval::Loss);
+    }
+
+    #[test]
+    fn test_analyze_moves_with_trained_model() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let model: Model =
+            serde_yaml::from_str(include_str!("../../../data/models/Warrior.Ogre.yaml")).unwrap();
+        assert!(!model.is_admissible());
+
+        let analysis = analyze_moves(&pile, &model, GameEndCheckType::Standard, 100_000);
+        assert!(matches!(analysis[0].eval, PileEval::WinWithin(_)));
+        assert_eq!(analysis[0].grade, MoveGrade::Best);
+
+        let worse_wins: Vec<&MoveAnalysis> = analysis
+            .iter()
+            .filter(|a| a.eval.win_depth() > analysis[0].eval.win_depth())
+            .collect();
+        assert!(!worse_wins.is_empty());
+        for worse_win in worse_wins {
+            assert!(worse_win.is_grade_bounded);
+            assert!(matches!(
+                worse_win.grade,
+                MoveGrade::Ok | MoveGrade::Mistake | MoveGrade::Blunder
+            ));
+        }
+
+        let last = analysis.last().unwrap();
+        assert_eq!(last.eval, PileEval::Loss);
+        assert_eq!(last.grade, MoveGrade::Blunder);
+    }
+
+    #[test]
+    fn test_evaluate_finished_piles() {
+        let won = string_to_pile("6C 3C 2C 5D 8C 1C 4D 7C 9C");
+        let lost = string_to_pile("6D 3C 2C 5D 8C 1C 4D 7C 9C");
+        let model = Model::new_empty_for_pile(&won);
+
+        assert_eq!(
+            evaluate_pile(&won, &model, GameEndCheckType::Standard, 100),
+            PileEval::Win(0)
+        );
+        assert_eq!(
+            evaluate_pile(&lost, &model, GameEndCheckType::Standard, 100),
+            PileEval::Loss
+        );
+    }
+
+    #[test]
+    fn test_analyze_moves_grades_all_outcomes() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let model = Model::new_empty_for_pile(&pile);
+        let analysis = analyze_moves(&pile, &model, GameEndCheckType::Standard, 2000);
+
+        let unique_outcomes: HashSet<Pile> =
+            resolve_top_card(&GameStateNoEventLog::new(pile.clone()))
+                .into_iter()
+                .map(|s| s.pile)
+                .collect();
+        assert_eq!(analysis.len(), unique_outcomes.len());
+
+        for pair in analysis.windows(2) {
+            assert!(pair[0].eval <= pair[1].eval);
+        }
+        assert!(matches!(analysis[0].eval, PileEval::Win(_)));
+        assert_eq!(analysis[0].grade, MoveGrade::Best);
+
+        let last = analysis.last().unwrap();
+        assert_eq!(last.eval, PileEval::Loss);
+        assert_eq!(last.grade, MoveGrade::Blunder);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::game::*;
+use crate::solver::model::dot_product;
+use crate::solver::model_t::ModelT;
+use serde::{Deserialize, Serialize};
+
+// bias, allegiance (hero, monster, other), health (empty, half, full), feature bits,
+// rows, mandatory rows, assists, swarm, action kinds, reactions, rage, modifier
+const NUM_FEATURE_BITS: usize = 12;
+const NUM_ACTION_KINDS: usize = 5;
+const NUM_REACTION_KINDS: usize = 6;
+pub const NUM_FACE_DESCRIPTORS: usize =
+    1 + 3 + 3 + NUM_FEATURE_BITS + 4 + NUM_ACTION_KINDS + NUM_REACTION_KINDS + 2;
+
+// bias, is top, is second, relative position, is bottom, enemy / ally in front,
+// enemy / ally behind, touching the top card through allies
+pub const NUM_POSITION_CONTEXTS: usize = 10;
+
+pub const NUM_SEMANTIC_PARAMS: usize = 1 + NUM_FACE_DESCRIPTORS * NUM_POSITION_CONTEXTS;
+
+fn action_kind(action: &Action) -> usize {
+    match action {
+        Action::Hit(_)
+        | Action::Arrow
+        | Action::ArrowTwice
+        | Action::Fireball
+        | Action::FireballTwice
+        | Action::Ablaze
+        | Action::Backstab
+        | Action::BackstabTwice
+        | Action::Poison
+        | Action::Claws(_)
+        | Action::SpacedClaws(_)
+        | Action::Death
+        | Action::Void => 0,
+        Action::Pull(_)
+        | Action::Push(_)
+        | Action::Quicken(_)
+        | Action::Delay(_)
+        | Action::Teleport
+        | Action::Maneuver => 1,
+        Action::Heal | Action::Revive | Action::Inspire => 2,
+        Action::CallAssist | Action::CallAssistTwice => 3,
+        Action::Rats | Action::Hypnosis | Action::Key(_) => 4,
+    }
+}
+
+fn reaction_kind(reaction: &Reaction) -> usize {
+    match reaction {
+        Reaction::Standard(_, standard) => match standard.trigger {
+            ReactionTrigger::Block => 0,
+            ReactionTrigger::Dodge => 1,
+        },
+        Reaction::Assist(_) => 2,
+        Reaction::WhenHit(_) => 3,
+        Reaction::Roll(_) => 4,
+        Reaction::Key(_) => 5,
+    }
+}
+
+fn is_hero(face: &FaceDef) -> bool {
+    face.allegiance == Allegiance::Hero
+}
+
+/// Describes a face by what it does, rather than which card it's on
+pub fn face_descriptors(face: &FaceDef) -> [f32; NUM_FACE_DESCRIPTORS] {
+    let mut result = [0.0; NUM_FACE_DESCRIPTORS];
+    result[0] = 1.0;
+
+    let allegiance_idx = match face.allegiance {
+        Allegiance::Hero => 0,
+        Allegiance::Monster => 1,
+        _ => 2,
+    };
+    result[1 + allegiance_idx] = 1.0;
+
+    let health_idx = match face.health {
+        Health::Empty => 0,
+        Health::Half => 1,
+        Health::Full => 2,
+    };
+    result[4 + health_idx] = 1.0;
+
+    let mut idx = 7;
+    for bit in 0..NUM_FEATURE_BITS {
+        if face.features.bits() & (1 << bit) != 0 {
+            result[idx + bit] = 1.0;
+        }
+    }
+    idx += NUM_FEATURE_BITS;
+
+    result[idx] = face.rows.len() as f32;
+    result[idx + 1] = face.rows.iter().filter(|r| r.is_mandatory).count() as f32;
+    result[idx + 2] = face.assists.len() as f32;
+    result[idx + 3] = face.swarm.is_some() as u8 as f32;
+    idx += 4;
+
+    for row in face.rows.iter().chain(face.swarm.iter()) {
+        for wrapped_action in &row.actions {
+            result[idx + action_kind(&wrapped_action.action)] += 1.0;
+        }
+    }
+    idx += NUM_ACTION_KINDS;
+
+    if let Some(reaction) = &face.reaction {
+        result[idx + reaction_kind(reaction)] = 1.0;
+    }
+    if face.reaction_assist.is_some() {
+        result[idx + 2] = 1.0;
+    }
+    idx += NUM_REACTION_KINDS;
+
+    result[idx] = face.rage as f32;
+    result[idx + 1] = face.modifier.is_some() as u8 as f32;
+
+    result
+}
+
+/// Where each card sits, relative to the rest of the pile
+pub fn position_contexts(pile: &Pile) -> Vec<[f32; NUM_POSITION_CONTEXTS]> {
+    let faces: Vec<&FaceDef> = pile.iter().map(|c| c.get_active_face()).collect();
+    let last_idx = pile.len().saturating_sub(1);
+    let mut is_touching_top = true;
+
+    faces
+        .iter()
+        .enumerate()
+        .map(|(i, face)| {
+            let mut context = [0.0; NUM_POSITION_CONTEXTS];
+            context[0] = 1.0;
+            context[1] = (i == 0) as u8 as f32;
+            context[2] = (i == 1) as u8 as f32;
+            context[3] = if last_idx == 0 {
+                0.0
+            } else {
+                i as f32 / last_idx as f32
+            };
+            context[4] = (i == last_idx) as u8 as f32;
+
+            if i > 0 {
+                let is_ally_in_front = is_hero(faces[i - 1]) == is_hero(face);
+                context[5 + is_ally_in_front as usize] = 1.0;
+            }
+            if i < last_idx {
+                let is_ally_behind = is_hero(faces[i + 1]) == is_hero(face);
+                context[7 + is_ally_behind as usize] = 1.0;
+            }
+
+            is_touching_top = is_touching_top && is_hero(face) == is_hero(faces[0]);
+            context[9] = is_touching_top as u8 as f32;
+
+            context
+        })
+        .collect()
+}
+
+/// Sum over the cards of the outer product of face descriptors and position context,
+/// with a leading 1 for the flat value
+pub fn semantic_features(pile: &Pile) -> Vec<f32> {
+    let mut result = vec![0.0; NUM_SEMANTIC_PARAMS];
+    result[0] = 1.0;
+
+    for (card, context) in pile.iter().zip(position_contexts(pile)) {
+        let descriptors = face_descriptors(card.get_active_face());
+        for (d, descriptor) in descriptors.iter().enumerate() {
+            if *descriptor == 0.0 {
+                continue;
+            }
+            let start = 1 + d * NUM_POSITION_CONTEXTS;
+            for (c, value) in context.iter().enumerate() {
+                result[start + c] += descriptor * value;
+            }
+        }
+    }
+
+    result
+}
+
+/// Linear heuristic over card semantics, so one model covers every matchup, including cards
+/// and combinations it was never trained on
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct SemanticModel {
+    pub weights: Vec<f32>,
+}
+
+impl Default for SemanticModel {
+    fn default() -> Self {
+        Self::new_empty()
+    }
+}
+
+impl SemanticModel {
+    pub fn new_empty() -> Self {
+        Self {
+            weights: vec![0.0; NUM_SEMANTIC_PARAMS],
+        }
+    }
+
+    pub fn from_weights(weights: Vec<f32>) -> Result<Self, String> {
+        if weights.len() != NUM_SEMANTIC_PARAMS {
+            return Err(format!(
+                "Expected {NUM_SEMANTIC_PARAMS} semantic weights, got {}",
+                weights.len()
+            ));
+        }
+        Ok(Self { weights })
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.weights.len() == NUM_SEMANTIC_PARAMS
+    }
+
+    pub fn score_pile(&self, pile: &Pile) -> f32 {
+        dot_product(&self.weights, &semantic_features(pile))
+    }
+}
+
+impl ModelT for SemanticModel {
+    fn score_pile(&self, pile: &Pile) -> f32 {
+        self.score_pile(pile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::string_to_pile;
+
+    #[test]
+    fn test_face_descriptors() {
+        let pile = string_to_pile("1A 6A");
+        let hero = face_descriptors(pile[0].get_active_face());
+        let monster = face_descriptors(pile[1].get_active_face());
+
+        assert_eq!(hero[0], 1.0);
+        assert_eq!(hero[1], 1.0);
+        assert_eq!(monster[2], 1.0);
+        // Every face is exactly one allegiance and one health
+        for descriptors in [hero, monster] {
+            assert_eq!(descriptors[1..4].iter().sum::<f32>(), 1.0);
+            assert_eq!(descriptors[4..7].iter().sum::<f32>(), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_contexts() {
+        let pile = string_to_pile("1A 2A 6A 7A");
+        let contexts = position_contexts(&pile);
+        assert_eq!(contexts.len(), 4);
+        assert_eq!(contexts[0][1], 1.0);
+        assert_eq!(contexts[3][4], 1.0);
+        assert_eq!(contexts[3][3], 1.0);
+
+        // Heroes on top, then monsters
+        assert_eq!(contexts[1][6], 1.0);
+        assert_eq!(contexts[1][9], 1.0);
+        assert_eq!(contexts[2][5], 1.0);
+        assert_eq!(contexts[2][9], 0.0);
+    }
+
+    #[test]
+    fn test_scores_any_cards() {
+        let mut model = SemanticModel::new_empty();
+        model.weights[0] = 2.5;
+        // Cards from any class, and any mix of them, get a score
+        for pile_str in ["1A 2A 6A 7A", "3B 102A 2B 101C 6B 8B", "5D 9C"] {
+            let pile = string_to_pile(pile_str);
+            assert_eq!(semantic_features(&pile).len(), NUM_SEMANTIC_PARAMS);
+            assert_eq!(model.score_pile(&pile), 2.5);
+        }
+
+        assert!(SemanticModel::from_weights(vec![0.0; 3]).is_err());
+        let model_str = serde_yaml::to_string(&model).unwrap();
+        let result_model: SemanticModel = serde_yaml::from_str(&model_str).unwrap();
+        assert_eq!(model, result_model);
+    }
+}
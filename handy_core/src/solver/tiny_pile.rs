@@ -193,11 +193,11 @@ mod tests {
     #[test]
     fn test_face_pack() {
         assert_eq!(
-            pack_faces_from_pile(&string_to_pile("1A 1A 1A 1A 1A 1A 1A 1A 1A")),
+            pack_faces_from_pile(&string_to_pile("1A 2A 3A 4A 5A 6A 7A 8A 9A")),
             [0, 0, 0] as PackedFaces
         );
 
-        let ex2 = pack_faces_from_pile(&string_to_pile("1A 1B 1C 1D 1D 1C 1B 1A 1D"));
+        let ex2 = pack_faces_from_pile(&string_to_pile("1A 2B 3C 4D 5D 6C 7B 8A 9D"));
         assert_eq!(ex2, [0b00011011, 0b11100100, 0b00000011] as PackedFaces);
 
         assert_eq!(face_key_from_byte(ex2[0], 0), FaceKey::A);
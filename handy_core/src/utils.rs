@@ -1,9 +1,11 @@
 pub mod interaction_utils;
+pub mod pile_notation;
 pub mod pile_utils;
 pub mod printing_utils;
 pub mod string_utils;
 
 pub use interaction_utils::*;
+pub use pile_notation::*;
 pub use pile_utils::*;
 pub use printing_utils::*;
 pub use string_utils::*;
@@ -159,6 +159,14 @@ pub fn find_next_event_matching_prefix_and_with_final_state(
     res.map(|res| res.0)
 }
 
+/// Events for the first way of getting from parent to child in a single activation
+pub fn find_events_between_piles(parent: &Pile, child: &Pile) -> Option<Vec<Event>> {
+    resolve_top_card(&GameStateWithEventLog::new(parent.clone()))
+        .into_iter()
+        .find(|state| &state.pile == child)
+        .map(|state| state.events)
+}
+
 pub fn compact_pile_string(pile: &Pile, sep: &str) -> String {
     pile.iter()
         .map(|x| format!("{:?}", x))
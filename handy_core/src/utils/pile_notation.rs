@@ -0,0 +1,378 @@
+use crate::game::card_defs::*;
+use crate::game::card_ptr::*;
+use crate::game::primitives::*;
+use std::fmt;
+
+/// Ways of writing a pile. Every dialect reads every separator, so "1A2B", "1A 2B", "1A, 2B" and
+/// "[1A, 2B]" are the same pile, and only the direction changes how a pile is read.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default, strum_macros::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum PileDialect {
+    // 1A 2B 6A, top first
+    #[default]
+    Spaced,
+    // 1A2B6A, top first
+    Compact,
+    // 1A > 2B > 6A, top first
+    Arrows,
+    // 6A > 2B > 1A, top last, as piles are posted in the BoardGameGeek puzzle thread
+    Reversed,
+}
+
+impl PileDialect {
+    pub fn is_top_last(&self) -> bool {
+        *self == PileDialect::Reversed
+    }
+
+    fn separator(&self) -> &'static str {
+        match self {
+            PileDialect::Spaced => " ",
+            PileDialect::Compact => "",
+            PileDialect::Arrows | PileDialect::Reversed => " > ",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PileNotationError {
+    // Of the character the error is about, counting characters from 0
+    pub position: usize,
+    pub message: String,
+}
+
+impl PileNotationError {
+    pub fn new(position: usize, message: String) -> Self {
+        Self { position, message }
+    }
+
+    /// The input with a ^ under the character the error is about
+    pub fn pointer(&self, input: &str) -> String {
+        format!("{input}\n{}^ {}", " ".repeat(self.position), self.message)
+    }
+}
+
+impl fmt::Display for PileNotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at character {}", self.message, self.position + 1)
+    }
+}
+
+fn parse_face(c: char) -> Option<FaceKey> {
+    match c.to_ascii_uppercase() {
+        'A' => Some(FaceKey::A),
+        'B' => Some(FaceKey::B),
+        'C' => Some(FaceKey::C),
+        'D' => Some(FaceKey::D),
+        _ => None,
+    }
+}
+
+/// The characters between optional surrounding brackets, as printed by {:?}
+fn bracketed_range(chars: &[char]) -> Result<(usize, usize), PileNotationError> {
+    let first = chars.iter().position(|c| !c.is_whitespace());
+    let last = chars.iter().rposition(|c| !c.is_whitespace());
+    let (Some(first), Some(last)) = (first, last) else {
+        return Ok((0, 0));
+    };
+    match (chars[first] == '[', chars[last] == ']') {
+        (true, true) if first < last => Ok((first + 1, last)),
+        (true, _) => Err(PileNotationError::new(last, "Missing ]".to_owned())),
+        (false, true) => Err(PileNotationError::new(last, "Missing [".to_owned())),
+        (false, false) => Ok((first, last + 1)),
+    }
+}
+
+/// One card as written, at character position. face is None for ?, and pinned_at is the n of
+/// @n. Both are only read for card specs, see parse_notation_cards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NotationCard {
+    pub card_id: CardId,
+    pub face: Option<FaceKey>,
+    pub pinned_at: Option<usize>,
+    pub position: usize,
+}
+
+/// Cards are an id and an optional face, A if there's none. Ids are read greedily, so in the
+/// compact dialect "12" is card 12 rather than 1A 2A.
+pub fn parse_pile_notation(input: &str, dialect: PileDialect) -> Result<Pile, PileNotationError> {
+    let cards = parse_notation_cards(input, false)?;
+    let mut pile: Pile = cards
+        .iter()
+        .map(|card| CardPtr::new_from_id(card.card_id, card.face.unwrap_or(FaceKey::A)))
+        .collect();
+    if dialect.is_top_last() {
+        pile.reverse();
+    }
+    Ok(pile)
+}
+
+/// The cards of a pile in order, as written. With is_spec, a card can also be on any face, like
+/// 15?, or pinned n from the top, like 2B@0, for describing sets of piles.
+pub fn parse_notation_cards(
+    input: &str,
+    is_spec: bool,
+) -> Result<Vec<NotationCard>, PileNotationError> {
+    let chars: Vec<char> = input.chars().collect();
+    let (start, end) = bracketed_range(&chars)?;
+
+    let mut cards: Vec<NotationCard> = Vec::new();
+    // Separators have to be between two cards
+    let mut pending_separator: Option<(usize, char)> = None;
+    let mut i = start;
+    while i < end {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == ',' || c == '>' {
+            if cards.is_empty() || pending_separator.is_some() {
+                return Err(PileNotationError::new(
+                    i,
+                    format!("Expected a card before {c}"),
+                ));
+            }
+            pending_separator = Some((i, c));
+            i += 1;
+            continue;
+        }
+        if !c.is_ascii_digit() {
+            let message = if parse_face(c).is_some() {
+                format!("Face {c} has no card id before it")
+            } else {
+                format!("Unexpected {c}")
+            };
+            return Err(PileNotationError::new(i, message));
+        }
+
+        let card_start = i;
+        while i < end && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        let id_str: String = chars[card_start..i].iter().collect();
+        let face = match chars.get(i).filter(|_| i < end) {
+            Some('?') if is_spec => {
+                i += 1;
+                None
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let face = parse_face(*c).ok_or_else(|| {
+                    PileNotationError::new(i, format!("Unknown face {c}, expected A to D"))
+                })?;
+                i += 1;
+                Some(face)
+            }
+            _ => Some(FaceKey::A),
+        };
+        let pinned_at = if is_spec && i < end && chars[i] == '@' {
+            let at = i;
+            i += 1;
+            let digits_start = i;
+            while i < end && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let digits: String = chars[digits_start..i].iter().collect();
+            Some(digits.parse::<usize>().map_err(|_| {
+                PileNotationError::new(at, "Expected a position after @".to_owned())
+            })?)
+        } else {
+            None
+        };
+
+        let card_id = id_str
+            .parse::<CardId>()
+            .ok()
+            .filter(|id| CARDS.get_card_if_exists(*id as usize).is_some())
+            .ok_or_else(|| {
+                PileNotationError::new(card_start, format!("There's no card {id_str}"))
+            })?;
+        // The dummy card stands in for any card in engine tests, so it can be there many times
+        let is_dummy = CARDS.get_card(card_id as usize).class == Class::Dummy;
+        if !is_dummy && cards.iter().any(|card| card.card_id == card_id) {
+            return Err(PileNotationError::new(
+                card_start,
+                format!("Card {card_id} is in the pile twice"),
+            ));
+        }
+        if cards.len() == MAX_PILE_LEN {
+            return Err(PileNotationError::new(
+                card_start,
+                format!("Piles have at most {MAX_PILE_LEN} cards"),
+            ));
+        }
+        cards.push(NotationCard {
+            card_id,
+            face,
+            pinned_at,
+            position: card_start,
+        });
+        pending_separator = None;
+    }
+
+    if let Some((position, separator)) = pending_separator {
+        return Err(PileNotationError::new(
+            position,
+            format!("Expected a card after {separator}"),
+        ));
+    }
+    if cards.is_empty() {
+        return Err(PileNotationError::new(
+            start,
+            "Could not parse any cards".to_owned(),
+        ));
+    }
+    Ok(cards)
+}
+
+/// Always has the face, so parse_pile_notation in the same dialect gives back the same pile
+pub fn format_pile_notation(pile: &Pile, dialect: PileDialect) -> String {
+    let cards = pile
+        .iter()
+        .map(|c| format!("{}{}", c.get_card_id(), c.get_card_face()));
+    let cards: Vec<String> = if dialect.is_top_last() {
+        cards.rev().collect()
+    } else {
+        cards.collect()
+    };
+    cards.join(dialect.separator())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::get_random_face;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    const DIALECTS: [PileDialect; 4] = [
+        PileDialect::Spaced,
+        PileDialect::Compact,
+        PileDialect::Arrows,
+        PileDialect::Reversed,
+    ];
+
+    fn pile(cards: &[(CardId, FaceKey)]) -> Pile {
+        cards
+            .iter()
+            .map(|(id, face)| CardPtr::new_from_id(*id, *face))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_dialects() {
+        let expected = pile(&[(1, FaceKey::A), (2, FaceKey::B), (12, FaceKey::A)]);
+        for input in [
+            "1A 2B 12",
+            "1a2b12a",
+            "1A > 2B > 12A",
+            "[1A, 2B, 12A]",
+            " 1A,2B 12 ",
+        ] {
+            assert_eq!(
+                parse_pile_notation(input, PileDialect::Spaced),
+                Ok(expected.clone()),
+                "{input}"
+            );
+        }
+
+        let mut reversed = expected.clone();
+        reversed.reverse();
+        assert_eq!(
+            parse_pile_notation("1A > 2B > 12A", PileDialect::Reversed),
+            Ok(reversed)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| parse_pile_notation(input, PileDialect::Spaced).unwrap_err();
+
+        assert_eq!(error("1A 2E").position, 4);
+        assert_eq!(error("1A 2E").message, "Unknown face E, expected A to D");
+        assert_eq!(error("1A 2B 1C").position, 6);
+        assert_eq!(error("1A 2B 1C").message, "Card 1 is in the pile twice");
+        assert_eq!(error("1A 999").message, "There's no card 999");
+        assert_eq!(error("1A ? 2A").position, 3);
+        assert_eq!(error("1A B").message, "Face B has no card id before it");
+        assert_eq!(error("1A > > 2A").position, 5);
+        assert_eq!(error("1A > 2A >").message, "Expected a card after >");
+        assert_eq!(error("> 1A").position, 0);
+        assert_eq!(error("[1A 2A").message, "Missing ]");
+        assert_eq!(error("   ").message, "Could not parse any cards");
+        assert_eq!(error("[]").message, "Could not parse any cards");
+
+        let too_long: Vec<String> = (1..=13).map(|id| id.to_string()).collect();
+        let err = error(&too_long.join(" "));
+        assert_eq!(err.message, "Piles have at most 12 cards");
+        assert_eq!(err.position, too_long[..12].join(" ").len() + 1);
+
+        assert_eq!(
+            error("1A 2E").pointer("1A 2E"),
+            "1A 2E\n    ^ Unknown face E, expected A to D"
+        );
+        assert_eq!(
+            error("1A 2E").to_string(),
+            "Unknown face E, expected A to D at character 5"
+        );
+    }
+
+    #[test]
+    fn test_parse_spec_cards() {
+        let cards = parse_notation_cards("15? > 2B@0", true).unwrap();
+        assert_eq!(
+            cards,
+            vec![
+                NotationCard {
+                    card_id: 15,
+                    face: None,
+                    pinned_at: None,
+                    position: 0,
+                },
+                NotationCard {
+                    card_id: 2,
+                    face: Some(FaceKey::B),
+                    pinned_at: Some(0),
+                    position: 6,
+                },
+            ]
+        );
+
+        let error = parse_notation_cards("1A 2@", true).unwrap_err();
+        assert_eq!(error.message, "Expected a position after @");
+        assert_eq!(error.position, 4);
+        assert_eq!(
+            parse_notation_cards("1A 2?", false).unwrap_err().position,
+            4
+        );
+        assert_eq!(parse_notation_cards("2@0", false).unwrap_err().position, 1);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut ids: Vec<CardId> = (1..=40)
+            .filter(|id| CARDS.get_card_if_exists(*id as usize).is_some())
+            .collect();
+        for _ in 0..100 {
+            ids.shuffle(&mut rng);
+            let len = rng.gen_range(1..=MAX_PILE_LEN);
+            let cards: Vec<(CardId, FaceKey)> = ids[..len]
+                .iter()
+                .map(|id| (*id, get_random_face(&mut rng)))
+                .collect();
+            let pile = pile(&cards);
+            for dialect in DIALECTS {
+                let notation = format_pile_notation(&pile, dialect);
+                assert_eq!(parse_pile_notation(&notation, dialect), Ok(pile.clone()));
+            }
+        }
+
+        let pile = pile(&[(5, FaceKey::C), (6, FaceKey::D), (2, FaceKey::B)]);
+        assert_eq!(format_pile_notation(&pile, PileDialect::Compact), "5C6D2B");
+        assert_eq!(
+            format_pile_notation(&pile, PileDialect::Reversed),
+            "2B > 6D > 5C"
+        );
+    }
+}
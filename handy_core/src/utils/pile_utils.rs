@@ -1,6 +1,7 @@
 use crate::game::card_defs::*;
 use crate::game::card_ptr::*;
 use crate::game::primitives::*;
+use crate::utils::pile_notation::*;
 use rand::seq::SliceRandom;
 use rand::Rng;
 use rand::RngCore;
@@ -155,27 +156,16 @@ pub fn string_to_card_ptr(input: &str) -> CardPtr {
     string_to_card_ptr_result(input).unwrap()
 }
 
+/// Errors say which character is wrong, see parse_pile_notation for what's accepted
 pub fn string_to_pile_result(input: &str) -> Result<Pile, String> {
-    let mut result = Pile::default();
-
-    let re = Regex::new(r"(\d+[a-dA-D]?)").unwrap();
-
-    for (_, [card_ptr_str]) in re.captures_iter(input).map(|c| c.extract()) {
-        let card_ptr = string_to_card_ptr_result(card_ptr_str)?;
-        result.push(card_ptr);
-
-        if result.len() == MAX_PILE_LEN {
-            return Ok(result);
-        }
-    }
-
-    if result.len() == 0 {
-        Err("Could not parse any cards".to_owned())
-    } else {
-        Ok(result)
-    }
+    parse_pile_notation(input, PileDialect::Spaced).map_err(|err| format!("{err}"))
 }
 
 pub fn string_to_pile(input: &str) -> Pile {
     string_to_pile_result(input).unwrap()
 }
+
+/// Uncoloured version of a pile, that can be read back with string_to_pile
+pub fn pile_to_string(pile: &Pile) -> String {
+    format_pile_notation(pile, PileDialect::Spaced)
+}
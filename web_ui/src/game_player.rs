@@ -314,6 +314,12 @@ pub fn GamePlayer(
 
     let (is_showing_settings_getter, is_showing_settings_setter) = create_signal(false);
     let is_oracle_enabled = create_rw_signal(false);
+    let is_reviewing = create_rw_signal(false);
+    create_effect(move |_| {
+        if !current_state.get().resolution.is_over() {
+            is_reviewing.set(false);
+        }
+    });
 
     let render_cards_getter = move || {
         let mut result: Vec<RenderCard> = render_card_map_getter
@@ -761,6 +767,19 @@ pub fn GamePlayer(
                             style:height={move || wrap_px(placer_getter.get().scale(2.0))}
                         />
 
+                        <Button
+                            background=Signal::derive( || BUTTON_SELECTED_COLOUR.to_string())
+                            width=100.0
+                            height=30.0
+                            on:click=move |_| is_reviewing.set(true)
+                        >
+                            Review
+                        </Button>
+
+                        <div
+                            style:height={move || wrap_px(placer_getter.get().scale(2.0))}
+                        />
+
                         <Button
                             background=Signal::derive( || BUTTON_NON_SELECTED_COLOUR.to_string())
                             width=100.0
@@ -792,10 +811,21 @@ pub fn GamePlayer(
                     }
                 }
             >
-                <HistoryPanel
-                    game_history_getter=game_history_getter.into()
-                    height=GOLDEN_HEIGHT
-                />
+                <Show
+                    when=move || is_reviewing.get()
+                    fallback=move || view! {
+                        <HistoryPanel
+                            game_history_getter
+                            height=GOLDEN_HEIGHT
+                        />
+                    }
+                >
+                    <GameReviewPanel
+                        game_history_getter
+                        game_end_type=game_end_type_memo
+                        height=GOLDEN_HEIGHT
+                    />
+                </Show>
             </Show>
         </div>
 
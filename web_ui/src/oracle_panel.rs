@@ -34,7 +34,7 @@ fn quest_model_url() -> String {
     return get_full_path("static/models/Quest.yaml");
 }
 
-async fn fetch_model_from_full_url(url: &str) -> Result<Model, ()> {
+async fn fetch_model_from_full_url(url: &str) -> Result<Model, String> {
     let model_string = reqwasm::http::Request::get(url)
         .send()
         .await
@@ -43,8 +43,36 @@ async fn fetch_model_from_full_url(url: &str) -> Result<Model, ()> {
         .await
         .unwrap();
 
-    let model: Model = serde_yaml::from_str(&model_string).unwrap();
-    Ok(model)
+    // Older models are migrated, and ones from a newer build are rejected
+    let loaded = parse_model_yaml(&model_string).map_err(|err| format!("{url}: {err}"))?;
+    Ok(loaded.envelope.model)
+}
+
+pub async fn fetch_model_for_pile(pile: &Pile) -> Model {
+    let matchups = get_all_matchups_from_pile(pile);
+
+    let mut models: Vec<Model> = Vec::new();
+    for matchup in matchups {
+        let model_url = model_url(matchup);
+        let model = fetch_model_from_full_url(&model_url).await.unwrap();
+        models.push(model);
+    }
+
+    if does_have_quest(pile) {
+        let model_url = quest_model_url();
+        let model = fetch_model_from_full_url(&model_url).await.unwrap();
+        models.push(model);
+    }
+
+    if models.len() == 1 {
+        models[0].clone()
+    } else {
+        merge_models_for_pile(pile, &models)
+    }
+}
+
+pub fn get_worker_path() -> String {
+    get_full_path("worker.js")
 }
 
 #[component]
@@ -55,7 +83,7 @@ pub fn OraclePanel(
     game_end_type: Memo<GameEndCheckType>,
     is_enabled: RwSignal<bool>,
 ) -> impl IntoView {
-    let worker_path = get_full_path("worker.js");
+    let worker_path = get_worker_path();
     let (bridge_sink, mut bridge_stream) = SolverWorker::spawner().spawn(&worker_path).split();
     let bridge_sink = Rc::new(RefCell::new(bridge_sink));
 
@@ -63,6 +91,7 @@ pub fn OraclePanel(
         create_signal::<(GameEndCheckType, Vec<Pile>)>((game_end_type.get_untracked(), vec![]));
     let (is_worker_started, set_worker_started) = create_signal(false);
     let (worker_state, set_worker_state) = create_signal::<SolverState>(SolverState::Init);
+    let (model, set_model) = create_signal::<Option<Model>>(None);
 
     let game_winner = create_memo(move |_| {
         let current_frame = current_frame.get();
@@ -129,7 +158,7 @@ pub fn OraclePanel(
                     if is_game_winner(pile, game_end_check_type) == WinType::Lose {
                         None
                     } else {
-                        Some(format!("{pile:?}"))
+                        Some(pile_to_string(pile))
                     }
                 })
                 .collect();
@@ -178,26 +207,8 @@ pub fn OraclePanel(
         let bridge_sink = bridge_sink.clone();
         spawn_local(async move {
             let pile = current_frame.get_untracked().root_pile;
-            let matchups = get_all_matchups_from_pile(&pile);
-
-            let mut models: Vec<Model> = Vec::new();
-            for matchup in matchups {
-                let model_url = model_url(matchup);
-                let model = fetch_model_from_full_url(&model_url).await.unwrap();
-                models.push(model);
-            }
-
-            if does_have_quest(&pile) {
-                let model_url = quest_model_url();
-                let model = fetch_model_from_full_url(&model_url).await.unwrap();
-                models.push(model);
-            }
-
-            let final_model = if models.len() == 1 {
-                models[0].clone()
-            } else {
-                merge_models_for_pile(&pile, &models)
-            };
+            let final_model = fetch_model_for_pile(&pile).await;
+            set_model.set(Some(final_model.clone()));
 
             if let Ok(mut bridge_sink) = bridge_sink.try_borrow_mut() {
                 bridge_sink
@@ -240,6 +251,7 @@ pub fn OraclePanel(
                 OutputSignal::Init => {
                     set_worker_state.set(SolverState::Init);
                 }
+                OutputSignal::GameReview(_) => (),
             };
         }
     });
@@ -352,6 +364,28 @@ pub fn OraclePanel(
         }
     };
 
+    // The model terms that matter most for the current pile, shown on hover
+    let score_tooltip = move || {
+        let Some(model) = model.get() else {
+            return String::new();
+        };
+        let pile = current_frame.get().current_pile;
+        let explanation = model.explain_score(&pile);
+
+        let mut lines = vec![format!("Score: {:.2}", explanation.total)];
+        for contribution in explanation.top_contributions(8) {
+            let source = match contribution.position {
+                Some(position) => format!("{:?}", pile[position]),
+                None => "-".to_owned(),
+            };
+            lines.push(format!(
+                "{source} {}: {:.2}",
+                contribution.feature, contribution.contribution
+            ));
+        }
+        lines.join("\n")
+    };
+
     on_cleanup(closure!(clone bridge_sink, || {
                 let bridge_sink = bridge_sink.clone();
                 spawn_local(async move {
@@ -378,7 +412,7 @@ pub fn OraclePanel(
                 when=move || is_enabled.get()
                 fallback=move || "Show Engine (E)"
             >
-                <div>
+                <div title=score_tooltip>
                     {path_text}
                 </div>
                 <div>
@@ -1,5 +1,7 @@
+pub mod game_review_panel;
 pub mod helper_screen;
 pub mod history_panel;
 
+pub use game_review_panel::*;
 pub use helper_screen::*;
 pub use history_panel::*;
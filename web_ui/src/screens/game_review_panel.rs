@@ -0,0 +1,129 @@
+use crate::components::*;
+use crate::contexts::*;
+use crate::oracle_panel::*;
+use crate::screens::history_panel::*;
+use crate::solver::*;
+use crate::types::*;
+use futures::SinkExt;
+use futures::StreamExt;
+use gloo::worker::Spawnable;
+use handy_core::game::end_game::GameEndCheckType;
+use handy_core::game::*;
+use handy_core::utils::*;
+use leptos::*;
+
+/// Returns the start pile, followed by the pile after each completed activation
+pub fn game_history_to_record_piles(game_history: &GameHistory) -> Vec<Pile> {
+    let mut result: Vec<Pile> = Vec::new();
+    for frame in &game_history.all_frames {
+        if result.last() != Some(&frame.root_pile) {
+            result.push(frame.root_pile.clone());
+        }
+    }
+
+    result
+}
+
+#[component]
+pub fn TurnReviewItem(turn_review: TurnReview) -> impl IntoView {
+    let is_mistake = turn_review.better_pile.is_some();
+    view! {
+        <HistoryItemWrapper>
+            <div
+                style:font-weight={if is_mistake { "bold" } else { "normal" }}
+            >
+                {format!(
+                    "Turn {}: {} / played: {} ({})",
+                    turn_review.turn,
+                    turn_review.eval_before,
+                    turn_review.eval_played,
+                    turn_review.grade,
+                )}
+            </div>
+            <PileSpan pile=string_to_pile(&turn_review.pile_before) />
+            {turn_review.better_pile.map(|better_pile| view! {
+                <div>
+                    "Better: "
+                    <PileSpan pile=string_to_pile(&better_pile) />
+                </div>
+            })}
+        </HistoryItemWrapper>
+    }
+}
+
+#[component]
+pub fn GameReviewPanel(
+    game_history_getter: Signal<GameHistory>,
+    game_end_type: Memo<GameEndCheckType>,
+    height: WindowUnit,
+) -> impl IntoView {
+    let placer_getter = use_context::<Memo<GameComponentPlacer>>().unwrap();
+    let (review, set_review) = create_signal::<Option<Vec<TurnReview>>>(None);
+
+    let record_piles = game_history_to_record_piles(&game_history_getter.get_untracked());
+    let pile_strings: Vec<String> = record_piles.iter().map(pile_to_string).collect();
+    let game_end_check_type = game_end_type.get_untracked();
+
+    let (mut bridge_sink, mut bridge_stream) =
+        SolverWorker::spawner().spawn(&get_worker_path()).split();
+
+    spawn_local(async move {
+        while let Some(output_signal) = bridge_stream.next().await {
+            match output_signal {
+                OutputSignal::Start => {
+                    let model = fetch_model_for_pile(&record_piles[0]).await;
+                    bridge_sink
+                        .send(ControlSignal::SetGameEndMode(game_end_check_type))
+                        .await
+                        .unwrap();
+                    bridge_sink
+                        .send(ControlSignal::SetModel(model))
+                        .await
+                        .unwrap();
+                    bridge_sink
+                        .send(ControlSignal::ReviewGame(pile_strings.clone()))
+                        .await
+                        .unwrap();
+                }
+                OutputSignal::GameReview(turn_reviews) => {
+                    set_review.set(Some(turn_reviews));
+                    bridge_sink.send(ControlSignal::End).await.unwrap();
+                    break;
+                }
+                _ => (),
+            }
+        }
+    });
+
+    view! {
+        <div
+            class="select-text"
+            style:overflow="auto"
+            style:height={move || wrap_px(placer_getter.get().scale(height))}
+        >
+            {move || match review.get() {
+                None => view! {
+                    <HistoryItemWrapper>
+                        "Reviewing game..."
+                    </HistoryItemWrapper>
+                }.into_view(),
+                Some(turn_reviews) => {
+                    let summary = format!(
+                        "{} turns, {} mistakes",
+                        turn_reviews.len(),
+                        turn_reviews.iter().filter(|t| t.better_pile.is_some()).count()
+                    );
+                    view! {
+                        <HistoryItemWrapper>
+                            {summary}
+                        </HistoryItemWrapper>
+                        {turn_reviews
+                            .into_iter()
+                            .map(|turn_review| view! { <TurnReviewItem turn_review /> })
+                            .collect_view()}
+                    }.into_view()
+                }
+            }}
+        </div>
+    }
+}
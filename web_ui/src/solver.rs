@@ -19,13 +19,25 @@ pub enum ControlSignal {
     SetGameEndMode(GameEndCheckType),
     SetRootPiles(Vec<String>),
     ClearRootPiles,
+    ReviewGame(Vec<String>),
     End,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TurnReview {
+    pub turn: usize,
+    pub pile_before: String,
+    pub eval_before: String,
+    pub eval_played: String,
+    pub grade: String,
+    pub better_pile: Option<String>,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum OutputSignal {
     Start,
     SolutionCrumb(GameEndCheckType, Vec<String>),
+    GameReview(Vec<TurnReview>),
     Init,
     Sleeping,
     Working,
@@ -45,17 +57,23 @@ struct SolverWorkerState {
     model: Option<Model>,
     root_piles: Vec<Pile>,
     a_star_solver: Option<AStarSolver<Pile, NoopPileStorageConverter>>,
+    // Quick approximate solution, sent before A* starts reporting
+    pending_beam_solution: Option<Vec<Pile>>,
     state: SolverState,
     game_end_check_type: GameEndCheckType,
 }
 
 const ITER_BATCH_SIZE: usize = 1000;
 const SLEEP_TIME_MS: u64 = 100;
+const REVIEW_MAX_ITERS_PER_PILE: usize = 20_000;
+// Kept small so that slow devices get a first answer right away
+const QUICK_BEAM_WIDTH: usize = 50;
 
 impl SolverWorkerState {
     fn new() -> Self {
         Self {
             a_star_solver: None,
+            pending_beam_solution: None,
             model: None,
             root_piles: vec![],
             state: SolverState::Init,
@@ -69,6 +87,7 @@ impl SolverWorkerState {
             _ => SolverState::Idle,
         };
         self.a_star_solver = None;
+        self.pending_beam_solution = None;
     }
 
     fn set_model(&mut self, model: Model) {
@@ -94,12 +113,51 @@ impl SolverWorkerState {
         self._check_should_start();
     }
 
+    fn review_game(&self, piles: Vec<Pile>) -> Vec<TurnReview> {
+        let (Some(model), Some(game_record)) = (&self.model, GameRecord::from_piles(&piles)) else {
+            return vec![];
+        };
+
+        let report = build_game_report(
+            &game_record,
+            model,
+            self.game_end_check_type,
+            REVIEW_MAX_ITERS_PER_PILE,
+        );
+
+        report
+            .turns
+            .iter()
+            .map(|turn| TurnReview {
+                turn: turn.turn,
+                pile_before: pile_to_string(&turn.pile_before),
+                eval_before: turn.eval_before.to_string(),
+                eval_played: turn.eval_played.to_string(),
+                grade: format_grade(turn.grade, turn.is_grade_bounded),
+                better_pile: turn
+                    .better_move
+                    .as_ref()
+                    .map(|better_move| pile_to_string(&better_move.pile)),
+            })
+            .collect()
+    }
+
     fn _check_should_start(&mut self) {
         if self.root_piles.len() > 0 {
             if let Some(model) = &self.model {
                 self.state = SolverState::Working;
                 let mut new_solver = AStarSolver::new(&self.root_piles, Box::new(model.clone()));
                 new_solver.set_game_end_check_type(self.game_end_check_type);
+
+                let mut beam_solver = BeamSolver::new(Box::new(model.clone()));
+                beam_solver.set_beam_width(QUICK_BEAM_WIDTH);
+                beam_solver.set_game_end_check_type(self.game_end_check_type);
+                if let Some(beam_solution) = beam_solver.solve(&self.root_piles) {
+                    // A* only needs to report solutions that beat the beam
+                    new_solver.set_max_depth(beam_solution.depth() as DepthType);
+                    self.pending_beam_solution = Some(beam_solution.piles);
+                }
+
                 self.a_star_solver = Some(new_solver);
             }
         }
@@ -117,6 +175,15 @@ impl SolverWorkerState {
             }
             SolverState::Working => {
                 if let Some(ref mut a_star_solver) = self.a_star_solver {
+                    if let Some(beam_solution) = self.pending_beam_solution.take() {
+                        let strings: Vec<_> =
+                            beam_solution.iter().map(pile_to_string).collect();
+                        return OutputSignal::SolutionCrumb(
+                            a_star_solver.game_end_check_type,
+                            strings,
+                        );
+                    }
+
                     for _ in 0..ITER_BATCH_SIZE {
                         let iter_result = a_star_solver.single_iter();
                         match iter_result {
@@ -127,7 +194,7 @@ impl SolverWorkerState {
                             AStarIterResult::NewBest(pile) => {
                                 let unrolled = a_star_solver.unroll_state(pile);
                                 let strings: Vec<_> =
-                                    unrolled.iter().map(|pile| format!("{pile:?}")).collect();
+                                    unrolled.iter().map(pile_to_string).collect();
                                 return OutputSignal::SolutionCrumb(
                                     a_star_solver.game_end_check_type,
                                     strings,
@@ -171,6 +238,12 @@ pub async fn SolverWorker(mut scope: ReactorScope<ControlSignal, OutputSignal>)
                         ControlSignal::ClearRootPiles => {
                             solver_worker_state.clear_root_piles();
                         }
+                        ControlSignal::ReviewGame(pile_strings) => {
+                            let piles: Vec<_> =
+                                pile_strings.iter().map(|s| string_to_pile(s)).collect();
+                            let review = solver_worker_state.review_game(piles);
+                            scope.send(OutputSignal::GameReview(review)).await.unwrap();
+                        }
                         ControlSignal::End => {
                             log!("worker got signal: {:?}", signal);
                             break;